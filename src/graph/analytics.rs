@@ -0,0 +1,387 @@
+//! Analytics on the service graph: fan-in and fan-out, cycles, betweenness centrality and the blast radius of each service.
+use super::process_node::{ProcessNodeType, ProcessNodes};
+use crate::utils::{self, Chapter, CsvFileBuffer};
+use serde::Serialize;
+use std::{
+    collections::{BTreeSet, VecDeque},
+    error::Error,
+    fs,
+    path::Path,
+};
+
+#[derive(Serialize, Debug)]
+pub struct ServiceAnalytics {
+    pub service: String,
+    pub ptype: ProcessNodeType,
+    /// the services that call this service (fan-in)
+    pub callers: Vec<String>,
+    /// the services called by this service (fan-out)
+    pub callees: Vec<String>,
+    /// normalized betweenness centrality, i.e. the fraction of the shortest paths between other services that pass this service
+    pub betweenness: f64,
+    /// the endpoints that depend on this service (the blast radius when this service fails)
+    pub endpoints: Vec<String>,
+    /// number of calls on call-chains in which this service occurs more than once
+    pub looped_calls: u64,
+}
+
+impl ServiceAnalytics {
+    pub fn fan_in(&self) -> usize {
+        self.callers.len()
+    }
+
+    pub fn fan_out(&self) -> usize {
+        self.callees.len()
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct GraphAnalytics {
+    /// the analytics per service, ordered by service-name
+    pub services: Vec<ServiceAnalytics>,
+    /// Groups of services that call each other in a cycle (strongly connected components of the service graph). A service that
+    /// loops back to itself via services that are not in the graph is reported as a cycle of a single service.
+    pub cycles: Vec<Vec<String>>,
+}
+
+/// Tarjan's algorithm for the strongly connected components of a directed graph
+struct Tarjan<'a> {
+    adjacent: &'a [Vec<usize>],
+    index: Vec<Option<usize>>,
+    low_link: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    next_index: usize,
+    components: Vec<Vec<usize>>,
+}
+
+impl<'a> Tarjan<'a> {
+    fn components(adjacent: &'a [Vec<usize>]) -> Vec<Vec<usize>> {
+        let n = adjacent.len();
+        let mut tarjan = Self {
+            adjacent,
+            index: vec![None; n],
+            low_link: vec![0; n],
+            on_stack: vec![false; n],
+            stack: Vec::new(),
+            next_index: 0,
+            components: Vec::new(),
+        };
+        (0..n).for_each(|v| {
+            if tarjan.index[v].is_none() {
+                tarjan.connect(v)
+            }
+        });
+        tarjan.components
+    }
+
+    fn connect(&mut self, v: usize) {
+        self.index[v] = Some(self.next_index);
+        self.low_link[v] = self.next_index;
+        self.next_index += 1;
+        self.stack.push(v);
+        self.on_stack[v] = true;
+        for w in self.adjacent[v].clone() {
+            match self.index[w] {
+                None => {
+                    self.connect(w);
+                    self.low_link[v] = self.low_link[v].min(self.low_link[w]);
+                }
+                Some(w_index) if self.on_stack[w] => {
+                    self.low_link[v] = self.low_link[v].min(w_index)
+                }
+                _ => (),
+            }
+        }
+        if Some(self.low_link[v]) == self.index[v] {
+            let mut component = Vec::new();
+            while let Some(w) = self.stack.pop() {
+                self.on_stack[w] = false;
+                component.push(w);
+                if w == v {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
+
+/// Brandes' algorithm for the betweenness centrality of the nodes of an unweighted directed graph, normalized by (n-1)(n-2).
+fn betweenness_centrality(adjacent: &[Vec<usize>]) -> Vec<f64> {
+    let n = adjacent.len();
+    let mut centrality = vec![0.0; n];
+    for s in 0..n {
+        let mut stack = Vec::new();
+        let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+        let mut num_paths = vec![0.0; n];
+        let mut distance: Vec<Option<usize>> = vec![None; n];
+        num_paths[s] = 1.0;
+        distance[s] = Some(0);
+        let mut queue = VecDeque::from([s]);
+        while let Some(v) = queue.pop_front() {
+            stack.push(v);
+            let dist_v = distance[v].unwrap();
+            for &w in &adjacent[v] {
+                if distance[w].is_none() {
+                    distance[w] = Some(dist_v + 1);
+                    queue.push_back(w);
+                }
+                if distance[w] == Some(dist_v + 1) {
+                    num_paths[w] += num_paths[v];
+                    predecessors[w].push(v);
+                }
+            }
+        }
+        let mut dependency = vec![0.0; n];
+        while let Some(w) = stack.pop() {
+            for &v in &predecessors[w] {
+                dependency[v] += num_paths[v] / num_paths[w] * (1.0 + dependency[w]);
+            }
+            if w != s {
+                centrality[w] += dependency[w];
+            }
+        }
+    }
+    if n > 2 {
+        let norm = ((n - 1) * (n - 2)) as f64;
+        centrality.iter_mut().for_each(|c| *c /= norm);
+    }
+    centrality
+}
+
+impl GraphAnalytics {
+    pub fn new(process_nodes: &ProcessNodes) -> Self {
+        let mut names: Vec<_> = process_nodes.processes().map(|pn| &pn.name[..]).collect();
+        names.sort_unstable();
+        let idx = |name: &str| names.binary_search(&name).ok();
+
+        let mut adjacent = vec![Vec::new(); names.len()];
+        let mut callers = vec![Vec::new(); names.len()];
+        process_nodes
+            .service_links()
+            .into_keys()
+            .for_each(|(from, to)| {
+                if let (Some(from_idx), Some(to_idx)) = (idx(from), idx(to)) {
+                    adjacent[from_idx].push(to_idx);
+                    callers[to_idx].push(from.to_owned());
+                }
+            });
+        let betweenness = betweenness_centrality(&adjacent);
+
+        let services: Vec<_> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let pn = process_nodes
+                    .get(name)
+                    .expect("name is taken from the nodes");
+                ServiceAnalytics {
+                    service: name.to_string(),
+                    ptype: pn.ptype,
+                    callers: callers[i].clone(),
+                    callees: adjacent[i].iter().map(|j| names[*j].to_owned()).collect(),
+                    betweenness: betweenness[i],
+                    endpoints: pn.endpoints.iter().cloned().collect(),
+                    looped_calls: pn.looped_calls,
+                }
+            })
+            .collect();
+
+        let mut cycles: Vec<Vec<String>> = Tarjan::components(&adjacent)
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .map(|component| {
+                let mut cycle: Vec<_> =
+                    component.into_iter().map(|i| names[i].to_owned()).collect();
+                cycle.sort_unstable();
+                cycle
+            })
+            .collect();
+        let in_cycle: BTreeSet<_> = cycles.iter().flatten().cloned().collect();
+        services
+            .iter()
+            .filter(|sa| sa.looped_calls > 0 && !in_cycle.contains(&sa.service))
+            .for_each(|sa| cycles.push(vec![sa.service.clone()]));
+        cycles.sort_unstable();
+
+        Self { services, cycles }
+    }
+
+    pub fn get(&self, service: &str) -> Option<&ServiceAnalytics> {
+        self.services.iter().find(|sa| sa.service == service)
+    }
+
+    /// The endpoints that depend on the service, which are the endpoints of the blast-radius query for this service (see 'ProcessNode::endpoints')
+    pub fn blast_radius(&self, service: &str) -> Option<&[String]> {
+        self.get(service).map(|sa| &sa.endpoints[..])
+    }
+
+    /// The 'n' services with the highest betweenness centrality
+    pub fn most_central(&self, n: usize) -> Vec<&ServiceAnalytics> {
+        let mut services: Vec<_> = self.services.iter().collect();
+        services.sort_by(|a, b| b.betweenness.total_cmp(&a.betweenness));
+        services.truncate(n);
+        services
+    }
+
+    pub fn write_csv(&self, path: &Path) {
+        let mut csv = CsvFileBuffer::new();
+        csv.add_section("Service graph analytics");
+        csv.add_line("Service; Type; Fan-in; Fan-out; Betweenness; Num endpoints; Looped calls; Callers; Callees; Endpoints (blast radius)".to_owned());
+        self.services.iter().for_each(|sa| {
+            csv.add_line(format!(
+                "{}; {:?}; {}; {}; {}; {}; {}; {}; {}; {}",
+                sa.service,
+                sa.ptype,
+                sa.fan_in(),
+                sa.fan_out(),
+                utils::format_float(sa.betweenness),
+                sa.endpoints.len(),
+                sa.looped_calls,
+                sa.callers.join(", "),
+                sa.callees.join(", "),
+                sa.endpoints.join(", ")
+            ))
+        });
+        csv.add_section("Cycles in the service graph");
+        csv.add_line("Services".to_owned());
+        self.cycles
+            .iter()
+            .for_each(|cycle| csv.add_line(cycle.join(", ")));
+        csv.write_file(path);
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// report the cycles and the most central service in the 'Analysis' chapter
+    pub fn report(&self) {
+        self.cycles.iter().for_each(|cycle| {
+            let looped_calls: u64 = cycle
+                .iter()
+                .filter_map(|s| self.get(s))
+                .map(|sa| sa.looped_calls)
+                .sum();
+            utils::report(
+                Chapter::Analysis,
+                format!(
+                    "Cycle in the service graph between [{}] ({looped_calls} looped calls)",
+                    cycle.join(", ")
+                ),
+            )
+        });
+        if let Some(sa) = self
+            .most_central(1)
+            .into_iter()
+            .find(|sa| sa.betweenness > 0.0)
+        {
+            utils::report(
+                Chapter::Analysis,
+                format!(
+                    "Service '{}' is the most central service (betweenness {:.3}) with a blast radius of {} endpoints",
+                    sa.service,
+                    sa.betweenness,
+                    sa.endpoints.len()
+                ),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{betweenness_centrality, GraphAnalytics, Tarjan};
+    use crate::{
+        graph::{build_graph, ProcessNodeType},
+        processed::test_traces::{build_trace, TestSpan},
+        stats::{BasicStatsRec, StatsRec},
+    };
+
+    #[test]
+    fn graph_of_stats_rec() {
+        let traces = [
+            build_trace(
+                "t1",
+                &[
+                    TestSpan::new("s1", None, "gw/GET", 0, 10_000),
+                    TestSpan::new("s2", Some("s1"), "svc/handle", 1_000, 8_000),
+                    TestSpan::new("s3", Some("s2"), "db/query", 2_000, 5_000),
+                ],
+            ),
+            // 'svc' loops back to itself, which is a cycle within a single service
+            build_trace(
+                "t2",
+                &[
+                    TestSpan::new("s1", None, "gw/GET", 0, 10_000),
+                    TestSpan::new("s2", Some("s1"), "svc/handle", 1_000, 8_000),
+                    TestSpan::new("s3", Some("s2"), "svc/inner", 2_000, 5_000),
+                    TestSpan::new("s4", Some("s3"), "svc/deep", 3_000, 2_000),
+                ],
+            ),
+            build_trace(
+                "t3",
+                &[
+                    TestSpan::new("s1", None, "admin/POST", 0, 10_000),
+                    TestSpan::new("s2", Some("s1"), "db/query", 1_000, 8_000),
+                ],
+            ),
+            // not rooted, so it does not add to the blast radius
+            build_trace(
+                "t4",
+                &[
+                    TestSpan::new("s1", Some("missing"), "batch/run", 0, 10_000),
+                    TestSpan::new("s2", Some("s1"), "db/query", 1_000, 8_000),
+                ],
+            ),
+        ];
+        let mut sr = StatsRec::new(BasicStatsRec::default());
+        traces
+            .iter()
+            .for_each(|trace| sr.extend_statistics(trace, false));
+
+        let process_nodes = build_graph(&sr);
+        let ptype = |name| process_nodes.get(name).unwrap().ptype;
+        assert_eq!(ptype("gw"), ProcessNodeType::EndPoint);
+        assert_eq!(ptype("batch"), ProcessNodeType::EndPoint);
+        assert_eq!(ptype("svc"), ProcessNodeType::Intermediate);
+        assert_eq!(ptype("db"), ProcessNodeType::Terminal);
+
+        // each call-chain only adds its last link, so the links are not counted once per prefix
+        let service_links = process_nodes.service_links();
+        assert_eq!(service_links[&("gw", "svc")], 2);
+        assert_eq!(service_links[&("svc", "db")], 1);
+        assert_eq!(service_links.len(), 4);
+        let svc = process_nodes.get("svc").unwrap();
+        assert_eq!(svc.operations["handle"], 2);
+        assert_eq!(svc.operations["inner"], 1);
+
+        let ga = GraphAnalytics::new(&process_nodes);
+        assert_eq!(
+            ga.blast_radius("db").unwrap(),
+            &["admin/POST".to_owned(), "gw/GET".to_owned()]
+        );
+        assert!(ga.blast_radius("batch").unwrap().is_empty());
+        assert_eq!(ga.get("svc").unwrap().looped_calls, 1);
+        assert_eq!(ga.cycles, vec![vec!["svc".to_owned()]]);
+        assert_eq!(ga.most_central(1)[0].service, "svc");
+    }
+
+    #[test]
+    fn betweenness_of_a_chain() {
+        // 0 -> 1 -> 2: only node 1 is on a shortest path between other nodes
+        let centrality = betweenness_centrality(&[vec![1], vec![2], vec![]]);
+        assert_eq!(centrality, vec![0.0, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn cycle_is_one_component() {
+        // 0 -> 1 -> 2 -> 1 and 2 -> 3
+        let mut components = Tarjan::components(&[vec![1], vec![2], vec![1, 3], vec![]]);
+        components.iter_mut().for_each(|c| c.sort_unstable());
+        assert!(components.contains(&vec![1, 2]));
+        assert_eq!(components.len(), 3);
+    }
+}
@@ -1,23 +1,34 @@
-use super::{fix_callchain::fix_call_chain, process_node::ProcessNodes};
-use crate::stats::file::StatsRecJson;
+use super::{
+    fix_callchain::fix_call_chain,
+    process_node::{LinkStats, ProcessNodes},
+};
+use crate::stats::StatsRec;
 
-pub fn build_graph(srj: &StatsRecJson) -> ProcessNodes {
+pub fn build_graph(stats_rec: &StatsRec) -> ProcessNodes {
     let mut process_nodes = ProcessNodes::default();
 
-    srj.stats.iter().for_each(|(_root_process_key, stat)| {
-        stat.call_chain.iter().for_each(|(cc_key, cc_val)| {
-            let count = cc_val.count.try_into().unwrap();
-            let fixed_cc = fix_call_chain(&cc_key.call_chain);
-            // ProcessNodes::tmp_check_cc(
-            //     &fixed_cc,
-            //     cc_key.is_leaf,
-            //     cc_val.rooted,
-            //     &cc_val.looped,
-            // );
-            process_nodes.add_call_chain(&fixed_cc, count);
-            //            process_nodes.add_call_chain(&cc_key.call_chain, count);
-        })
-    });
+    stats_rec
+        .stats
+        .iter()
+        .for_each(|(_root_process_key, stat)| {
+            stat.call_chain.0.iter().for_each(|(cc_key, cc_val)| {
+                let fixed_cc = fix_call_chain(&cc_key.call_chain);
+                // ProcessNodes::tmp_check_cc(
+                //     &fixed_cc,
+                //     cc_key.is_leaf,
+                //     cc_val.rooted,
+                //     &cc_val.looped,
+                // );
+                let link = LinkStats::new(
+                    cc_val.count as u64,
+                    cc_val.get_avg_rate(&stats_rec.file_windows, stats_rec.num_files),
+                    cc_val.get_avg_millis(),
+                    cc_val.get_p_millis(0.95),
+                );
+                process_nodes.add_call_chain(&fixed_cc, link, cc_val.rooted, &cc_val.looped);
+            })
+        });
+    process_nodes.classify();
 
     process_nodes
 }
@@ -0,0 +1,278 @@
+//! Export of a service dependency graph as Graphviz DOT, GraphML (for Gephi or yEd) or as a JSON node/edge list.
+//! The nodes are Service/Operation combinations that are grouped per service, and the edges carry the call statistics.
+use super::id_mapper::IdMapper;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{collections::BTreeMap, error::Error, fs, path::Path};
+
+/// Version of the JSON node/edge list
+const GRAPH_JSON_VERSION: u16 = 1;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum GraphFormat {
+    Dot,
+    Graphml,
+    Json,
+}
+
+impl GraphFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Dot => "dot",
+            Self::Graphml => "graphml",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// A node is an operation of a service (the id is '<service>/<operation>')
+#[derive(Serialize, Debug)]
+pub struct GraphNode {
+    pub id: String,
+    pub service: String,
+    pub operation: String,
+    /// the number of calls received by (or made from) this operation, when known
+    pub count: Option<u64>,
+}
+
+/// An edge is a call from one operation to another, with the statistics of these calls
+#[derive(Serialize, Debug)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+    /// number of calls
+    pub count: u64,
+    /// average number of calls per second
+    pub rate: Option<f64>,
+    pub avg_duration_millis: Option<f64>,
+    pub p95_millis: Option<f64>,
+}
+
+/// A service dependency graph that can be written as DOT, GraphML or JSON.
+#[derive(Serialize, Debug)]
+pub struct GraphExport {
+    pub version: u16,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl Default for GraphExport {
+    fn default() -> Self {
+        Self {
+            version: GRAPH_JSON_VERSION,
+            nodes: Vec::new(),
+            edges: Vec::new(),
+        }
+    }
+}
+
+fn node_id(service: &str, operation: &str) -> String {
+    format!("{service}/{operation}")
+}
+
+/// escape a string for use in a quoted DOT-string
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// escape a string for use in XML
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+impl GraphExport {
+    /// Add the node for 'service/operation' (when it does not exist yet) and return its id.
+    /// The count is added to the count of an existing node.
+    pub fn add_node(&mut self, service: &str, operation: &str, count: Option<u64>) -> String {
+        let id = node_id(service, operation);
+        match self.nodes.iter_mut().find(|node| node.id == id) {
+            Some(node) => {
+                if let Some(count) = count {
+                    node.count = Some(node.count.unwrap_or(0) + count)
+                }
+            }
+            None => self.nodes.push(GraphNode {
+                id: id.clone(),
+                service: service.to_owned(),
+                operation: operation.to_owned(),
+                count,
+            }),
+        }
+        id
+    }
+
+    pub fn add_edge(&mut self, edge: GraphEdge) {
+        self.edges.push(edge)
+    }
+
+    /// sort nodes and edges such that the output is stable
+    pub fn sort(&mut self) {
+        self.nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        self.edges
+            .sort_by(|a, b| (&a.from, &a.to).cmp(&(&b.from, &b.to)));
+    }
+
+    /// Graphviz DOT with a cluster per service that contains the operation-nodes
+    pub fn to_dot(&self) -> String {
+        let mut node_ids = IdMapper::new("n".to_owned());
+        let mut cluster_ids = IdMapper::new("cluster".to_owned());
+        let mut services: BTreeMap<&str, Vec<&GraphNode>> = BTreeMap::new();
+        self.nodes
+            .iter()
+            .for_each(|node| services.entry(&node.service).or_default().push(node));
+
+        let mut lines = vec![
+            "digraph services {".to_owned(),
+            "    rankdir=LR;".to_owned(),
+            "    node [shape=box, style=rounded];".to_owned(),
+        ];
+        services.into_iter().for_each(|(service, nodes)| {
+            lines.push(format!("    subgraph {} {{", cluster_ids.map_key(service)));
+            lines.push(format!("        label=\"{}\";", dot_escape(service)));
+            nodes.into_iter().for_each(|node| {
+                let label = match node.count {
+                    Some(count) => format!("{}\\n{count}", dot_escape(&node.operation)),
+                    None => dot_escape(&node.operation),
+                };
+                lines.push(format!(
+                    "        {} [label=\"{label}\"];",
+                    node_ids.map_key(&node.id)
+                ))
+            });
+            lines.push("    }".to_owned());
+        });
+        self.edges.iter().for_each(|edge| {
+            let mut attrs = vec![
+                format!("label=\"{}\"", edge.count),
+                format!("count={}", edge.count),
+            ];
+            [
+                ("rate", edge.rate),
+                ("avg_duration_millis", edge.avg_duration_millis),
+                ("p95_millis", edge.p95_millis),
+            ]
+            .into_iter()
+            .filter_map(|(name, value)| value.map(|v| format!("{name}={v:.3}")))
+            .for_each(|attr| attrs.push(attr));
+            let from = node_ids.map_key(&edge.from).to_owned();
+            lines.push(format!(
+                "    {from} -> {} [{}];",
+                node_ids.map_key(&edge.to),
+                attrs.join(", ")
+            ))
+        });
+        lines.push("}".to_owned());
+        lines.join("\n")
+    }
+
+    /// GraphML, which can be read by Gephi and yEd. The statistics are attributes of the edges.
+    pub fn to_graphml(&self) -> String {
+        let mut lines = vec![
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#.to_owned(),
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#.to_owned(),
+            r#"  <key id="service" for="node" attr.name="service" attr.type="string"/>"#.to_owned(),
+            r#"  <key id="operation" for="node" attr.name="operation" attr.type="string"/>"#.to_owned(),
+            r#"  <key id="node_count" for="node" attr.name="count" attr.type="long"/>"#.to_owned(),
+            r#"  <key id="count" for="edge" attr.name="count" attr.type="long"/>"#.to_owned(),
+            r#"  <key id="rate" for="edge" attr.name="rate" attr.type="double"/>"#.to_owned(),
+            r#"  <key id="avg_duration_millis" for="edge" attr.name="avg_duration_millis" attr.type="double"/>"#.to_owned(),
+            r#"  <key id="p95_millis" for="edge" attr.name="p95_millis" attr.type="double"/>"#.to_owned(),
+            r#"  <graph id="services" edgedefault="directed">"#.to_owned(),
+        ];
+        self.nodes.iter().for_each(|node| {
+            lines.push(format!(r#"    <node id="{}">"#, xml_escape(&node.id)));
+            lines.push(format!(
+                r#"      <data key="service">{}</data>"#,
+                xml_escape(&node.service)
+            ));
+            lines.push(format!(
+                r#"      <data key="operation">{}</data>"#,
+                xml_escape(&node.operation)
+            ));
+            if let Some(count) = node.count {
+                lines.push(format!(r#"      <data key="node_count">{count}</data>"#));
+            }
+            lines.push("    </node>".to_owned());
+        });
+        self.edges.iter().for_each(|edge| {
+            lines.push(format!(
+                r#"    <edge source="{}" target="{}">"#,
+                xml_escape(&edge.from),
+                xml_escape(&edge.to)
+            ));
+            lines.push(format!(r#"      <data key="count">{}</data>"#, edge.count));
+            [
+                ("rate", edge.rate),
+                ("avg_duration_millis", edge.avg_duration_millis),
+                ("p95_millis", edge.p95_millis),
+            ]
+            .into_iter()
+            .filter_map(|(key, value)| value.map(|v| (key, v)))
+            .for_each(|(key, value)| {
+                lines.push(format!(r#"      <data key="{key}">{value}</data>"#))
+            });
+            lines.push("    </edge>".to_owned());
+        });
+        lines.push("  </graph>".to_owned());
+        lines.push("</graphml>".to_owned());
+        lines.join("\n")
+    }
+
+    /// JSON node/edge list (see the README for the layout)
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Failed to serialize the graph")
+    }
+
+    pub fn to_format(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Graphml => self.to_graphml(),
+            GraphFormat::Json => self.to_json(),
+        }
+    }
+
+    pub fn write_file(&self, path: &Path, format: GraphFormat) -> Result<(), Box<dyn Error>> {
+        fs::write(path, self.to_format(format))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GraphEdge, GraphExport};
+
+    fn graph() -> GraphExport {
+        let mut graph = GraphExport::default();
+        let from = graph.add_node("gateway", "GET \"/api\"", Some(10));
+        let to = graph.add_node("app", "a<b", None);
+        graph.add_edge(GraphEdge {
+            from,
+            to,
+            count: 10,
+            rate: Some(0.5),
+            avg_duration_millis: None,
+            p95_millis: Some(12.0),
+        });
+        graph
+    }
+
+    #[test]
+    fn dot_has_clusters_and_edge_attributes() {
+        let dot = graph().to_dot();
+        assert!(dot.contains("subgraph cluster_0 {"));
+        assert!(dot.contains(r#"label="GET \"/api\"\n10""#));
+        assert!(dot.contains("n_1 -> n_0 [label=\"10\", count=10, rate=0.500, p95_millis=12.000];"));
+    }
+
+    #[test]
+    fn graphml_is_escaped() {
+        let graphml = graph().to_graphml();
+        assert!(graphml.contains(r#"<node id="app/a&lt;b">"#));
+        assert!(graphml.contains(r#"<data key="rate">0.5</data>"#));
+        assert!(!graphml.contains(r#"key="avg_duration_millis">"#));
+    }
+}
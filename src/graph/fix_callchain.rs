@@ -1,4 +1,7 @@
-use crate::stats::call_chain::{CallChain, CallDirection};
+use crate::{
+    stats::call_chain::{CallChain, CallDirection},
+    utils::{self, Chapter},
+};
 
 /// The fix_call_chain returns a copy of the call-chain where CallDirection is always set (no more Unknown)
 /// Each time a new Process appears on the call-chain it will be an Inbound call, the subsequent calls are the outbound calls.
@@ -30,13 +33,19 @@ pub fn fix_call_chain(call_chain: &CallChain) -> CallChain {
         })
         .collect();
     if !issues.is_empty() {
-        println!("\nFailed on idx = {issues:?}:");
-        for idx in 0..call_chain.len() {
-            println!(
-                "{idx}: {:?}  ->  {:?}",
-                call_chain[idx], fixed_call_chain[idx]
-            );
-        }
+        let calls = (0..call_chain.len())
+            .map(|idx| {
+                format!(
+                    "{idx}: {:?}  ->  {:?}",
+                    call_chain[idx], fixed_call_chain[idx]
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n\t");
+        utils::report(
+            Chapter::Details,
+            format!("Unexpected call-direction at idx = {issues:?}:\n\t{calls}"),
+        );
     }
 
     fixed_call_chain
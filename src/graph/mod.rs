@@ -1,11 +1,15 @@
-//! NOT USED yet. DEAD CODE
-//! This was aimed at getting dot-graphs. However we switched to using Mermaid.
-//!
-//! Build a graph out of the Call-chain infomration.
+//! Build a service dependency graph out of the Call-chain information, and export it (or the ServiceOperGraph that is used for the
+//! Mermaid diagrams) as Graphviz DOT, GraphML or as a JSON node/edge list. The service graph is also analyzed for cycles, central
+//! services and the blast radius of each service.
 //!
+mod analytics;
 mod build_graph;
+mod export;
 mod fix_callchain;
 mod id_mapper;
 mod process_node;
 
+pub use analytics::{GraphAnalytics, ServiceAnalytics};
 pub use build_graph::build_graph;
+pub use export::{GraphEdge, GraphExport, GraphFormat, GraphNode};
+pub use process_node::{ProcessNode, ProcessNodeType, ProcessNodes};
@@ -1,8 +1,13 @@
-use crate::stats::call_chain::{Call, CallChain, CallDirection};
-use std::{collections::HashMap, iter};
+use super::export::{GraphEdge, GraphExport};
+use crate::{
+    mermaid,
+    stats::call_chain::{Call, CallChain, CallDirection},
+    utils::{AggregateData, AverageData},
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
-#[allow(dead_code)] // enum might have unused variants
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum ProcessNodeType {
     /// represents a process that is directly exposed (via an API gateway), so this is the starting point of a service-process
     EndPoint,
@@ -25,6 +30,11 @@ pub struct ProcessNode {
     pub methods: HashMap<String, i32>, //LinkCount>,
     // This category is only used for call-chains that have not been fixed, to set call_direction for CallDirection::Unknown.
     pub direction_unknown: HashMap<String, i32>, //LinkCount>,
+    /// The endpoints (Service/Operation of the root) of the rooted call-chains that enter this process, i.e. the blast radius of the process.
+    /// These are the same endpoints as reported by the blast-radius query ('TracePaths::blast_radius') for this service.
+    pub endpoints: BTreeSet<String>,
+    /// Number of calls on call-chains in which this process occurs more than once (a loop)
+    pub looped_calls: u64,
 }
 
 impl ProcessNode {
@@ -60,8 +70,50 @@ impl ProcessNode {
     }
 }
 
+/// Statistics of the calls over a link from one Service/Operation to the next
+#[derive(Debug)]
+pub struct LinkStats {
+    pub count: u64,
+    pub rate: Option<f64>,
+    pub avg_duration_millis: AverageData,
+    pub p95_millis: AverageData,
+}
+
+impl LinkStats {
+    pub fn new(
+        count: u64,
+        rate: Option<f64>,
+        avg_duration_millis: f64,
+        p95_millis: Option<f64>,
+    ) -> Self {
+        Self {
+            count,
+            rate,
+            avg_duration_millis: AverageData::new(count, Some(avg_duration_millis)),
+            p95_millis: AverageData::new(count, p95_millis),
+        }
+    }
+
+    /// merge the statistics of another call-chain that ends with the same link
+    fn merge(&mut self, other: LinkStats) {
+        self.count += other.count;
+        self.rate = match (self.rate, other.rate) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+        self.avg_duration_millis
+            .add(other.count, other.avg_duration_millis.get_value());
+        self.p95_millis
+            .add(other.count, other.p95_millis.get_value());
+    }
+}
+
 #[derive(Debug, Default)]
-pub struct ProcessNodes(HashMap<String, ProcessNode>);
+pub struct ProcessNodes {
+    nodes: HashMap<String, ProcessNode>,
+    /// the links between a caller and a callee
+    links: HashMap<(Call, Call), LinkStats>,
+}
 
 impl ProcessNodes {
     // pub fn new() -> Self {
@@ -97,41 +149,114 @@ impl ProcessNodes {
         // }
     }
 
-    pub fn add_call_chain(&mut self, call_chain: &CallChain, count: i32) {
-        let initial_call = Call {
-            service: "User".to_owned(),
-            operation: "method".to_owned(),
-            call_direction: CallDirection::Outbound,
+    /// Add a call-chain. As each prefix of a call-chain is a call-chain itself, only the last call and the link towards it are added.
+    /// The 'looped' processes occur more than once on the call-chain.
+    pub fn add_call_chain(
+        &mut self,
+        call_chain: &CallChain,
+        link: LinkStats,
+        rooted: bool,
+        looped: &[String],
+    ) {
+        let Some(call) = call_chain.last() else {
+            return;
         };
-        iter::once(&initial_call)
-            .chain(call_chain.iter())
-            .zip(call_chain.iter())
-            // call_chain.iter()
-            //     .scan(initial_call, |prev, call| {
-            //         let pair = (prev.clone(), call);
-            //         prev = &mut &call;
-            //         Some(pair)
-            //     })
-            .for_each(|(_pred, call)| {
-                // work in progress: pred will be used
-                let amend = |pn: &mut ProcessNode| match call.call_direction {
-                    CallDirection::Outbound => pn.add_method(call.operation.clone(), count),
-                    CallDirection::Inbound => pn.add_operation(call.operation.clone(), count),
-                    CallDirection::Unknown => {
-                        pn.add_direction_unknown(call.operation.clone(), count)
-                    } // panic!("Unknown call-direction, this should have been fixed earlier!")
-                };
-                self.0
-                    .entry(call.service.clone())
-                    .and_modify(|pn| {
-                        amend(pn);
-                    })
-                    .or_insert_with(|| {
-                        let mut pn = ProcessNode::new(call.service.clone());
-                        amend(&mut pn);
-                        pn
-                    });
+        let count = link.count as i32;
+        let pn = self
+            .nodes
+            .entry(call.service.clone())
+            .or_insert_with(|| ProcessNode::new(call.service.clone()));
+        match call.call_direction {
+            CallDirection::Outbound => pn.add_method(call.operation.clone(), count),
+            CallDirection::Inbound => pn.add_operation(call.operation.clone(), count),
+            CallDirection::Unknown => pn.add_direction_unknown(call.operation.clone(), count), // panic!("Unknown call-direction, this should have been fixed earlier!")
+        };
+        if rooted && mermaid::ends_in(call_chain, &call.service, None) {
+            pn.endpoints.insert(call_chain[0].get_process_method());
+        }
+        if looped.contains(&call.service) {
+            pn.looped_calls += link.count;
+        }
+
+        if call_chain.len() >= 2 {
+            let pred = call_chain[call_chain.len() - 2].clone();
+            match self.links.get_mut(&(pred.clone(), call.clone())) {
+                Some(stats) => stats.merge(link),
+                None => _ = self.links.insert((pred, call.clone()), link),
+            }
+        }
+    }
+
+    /// The calls between different processes aggregated per (caller, callee) pair
+    pub fn service_links(&self) -> BTreeMap<(&str, &str), u64> {
+        let mut service_links = BTreeMap::new();
+        self.links
+            .iter()
+            .filter(|((from, to), _)| from.service != to.service)
+            .for_each(|((from, to), stats)| {
+                *service_links
+                    .entry((&from.service[..], &to.service[..]))
+                    .or_default() += stats.count
+            });
+        service_links
+    }
+
+    /// Classify each process based on its inbound and outbound links: a process without inbound calls from other processes
+    /// is an EndPoint, a process without outbound calls to other processes is a Terminal, and all others are Intermediate.
+    pub fn classify(&mut self) {
+        let service_links = self.service_links();
+        let has_inbound: BTreeSet<_> = service_links.keys().map(|(_, to)| to.to_string()).collect();
+        let has_outbound: BTreeSet<_> = service_links
+            .keys()
+            .map(|(from, _)| from.to_string())
+            .collect();
+        self.nodes.values_mut().for_each(|pn| {
+            pn.ptype = if !has_inbound.contains(&pn.name) {
+                ProcessNodeType::EndPoint
+            } else if !has_outbound.contains(&pn.name) {
+                ProcessNodeType::Terminal
+            } else {
+                ProcessNodeType::Intermediate
+            }
+        })
+    }
+
+    /// get the process with this name
+    pub fn get(&self, name: &str) -> Option<&ProcessNode> {
+        self.nodes.get(name)
+    }
+
+    /// all processes (in arbitrary order)
+    pub fn processes(&self) -> impl Iterator<Item = &ProcessNode> {
+        self.nodes.values()
+    }
+
+    /// Convert to a graph with a node per Service/Operation and an edge per link, for export to DOT, GraphML or JSON.
+    pub fn to_graph_export(&self) -> GraphExport {
+        let mut graph = GraphExport::default();
+        self.nodes.values().for_each(|pn| {
+            pn.operations
+                .iter()
+                .chain(pn.methods.iter())
+                .chain(pn.direction_unknown.iter())
+                .for_each(|(oper, count)| {
+                    _ = graph.add_node(&pn.name, oper, Some(*count as u64));
+                })
+        });
+        self.links.iter().for_each(|((from, to), stats)| {
+            let from = graph.add_node(&from.service, &from.operation, None);
+            let to = graph.add_node(&to.service, &to.operation, None);
+            graph.add_edge(GraphEdge {
+                from,
+                to,
+                count: stats.count,
+                rate: stats.rate,
+                avg_duration_millis: stats.avg_duration_millis.get_value(),
+                p95_millis: stats.p95_millis.get_value(),
             })
+        });
+        graph.sort();
+        graph
     }
 }
 
@@ -9,24 +9,46 @@ mod view_api;
 mod graph;
 mod stitch;
 
-pub use graph::build_graph;
+pub use graph::{
+    build_graph, GraphAnalytics, GraphEdge, GraphExport, GraphFormat, GraphNode, ProcessNode,
+    ProcessNodeType, ProcessNodes, ServiceAnalytics,
+};
+pub use mermaid::{
+    jaeger_sequence_diagram, jaeger_waterfall, BlastRadius, EndpointShare, ServicePath,
+    ServicePaths, Waterfall, WaterfallRow,
+};
+pub use processed::set_infer_virtual_nodes;
 pub use raw::{
     read_file_or_folder, read_jaeger_trace_file, write_traces, JaegerItem, JaegerLog, JaegerSpan,
     JaegerTags, JaegerTrace,
 };
-pub use stats::{chained_stats, file::StatsRecJson, CChainEndPointCache, StatsRec};
+pub use stats::{
+    chained_stats,
+    file::{write_stats, StatsRecJson},
+    prune_cchain_file_locked, read_cchain_folder, set_compact_stats, set_gap_repair,
+    set_instance_stats, set_repair_strategy, set_slo_config, set_split_dimensions,
+    set_waterfall_output, write_cchain_folder, write_stats_to_csv_file, AmplificationMatrix,
+    CChainEndPointCache, ChainInfo, CompareParameters, DbQueryKey, DbQueryStats, DbQueryStatsValue,
+    DimensionKey, DimensionStats, DimensionStatsValue, EndPointCChains, FanOutKey, FanOutStats,
+    FanOutStatsValue, InstanceKey, InstanceStats, PrunePolicy, RepairStrategy, SloConfig,
+    SloObjective, StatsComparison, StatsRec,
+};
 pub use utils::{
     current_folder, datetime_micros_str, datetime_millis_str, datetime_to_micros, hash,
-    micros_to_datetime, report, set_comma_float, set_tz_offset_minutes, string_hash, write_report,
+    micros_to_datetime, normalize_sql, parse_interval_secs, report, set_comma_float,
+    set_tz_offset_minutes, sql_fingerprint, string_hash, write_report, DurationSketch, TimeBuckets,
 };
 
 pub use stitch::{
     AnomalyParameters, BestFit, StitchList, StitchParameters, Stitched, StitchedDataSet,
     StitchedLine, StitchedSet,
 };
-pub use trace_analysis::{analyze_file_or_folder, TraceDataSet};
+pub use trace_analysis::{
+    analyze_canary, analyze_file_or_folder, set_bucket_interval, CanaryComparison,
+    CanaryParameters, TraceDataSet,
+};
 pub use view_api::{
     load_viewer,
     types::{self, ServiceOperString},
-    MermaidScope, Metric, TraceScope, ViewError, Viewer,
+    DiagramType, MermaidScope, Metric, TraceScope, ViewError, Viewer,
 };
@@ -0,0 +1,79 @@
+use clap::Parser;
+use jaeger_stats::{
+    analyze_canary, set_comma_float, set_tz_offset_minutes, write_report, CanaryParameters,
+};
+use std::{path::Path, process};
+
+/// Compare the baseline and the candidate (canary) version of services within a single set of Jaeger traces.
+/// The process exits with a non-zero exit-code when the candidate regresses beyond the thresholds.
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    // file of folder to parse
+    input: String,
+
+    /// The span-tag or process-tag that contains the version of the service.
+    #[arg(long, default_value_t = String::from("service.version"))]
+    version_tag: String,
+
+    /// The baseline version. Default is the version that is observed first (per service).
+    #[arg(long)]
+    baseline: Option<String>,
+
+    /// The candidate version. Default is the version that is observed last (per service).
+    #[arg(long)]
+    candidate: Option<String>,
+
+    /// Significance level used for the latency and the error-rate tests.
+    #[arg(long, default_value_t = 0.01)]
+    alpha: f64,
+
+    /// Maximal accepted ratio between the median latency of the candidate and the baseline.
+    #[arg(long, default_value_t = 1.1)]
+    max_latency_ratio: f64,
+
+    /// Maximal accepted increase of the error-rate (absolute fraction).
+    #[arg(long, default_value_t = 0.01)]
+    max_error_rate_increase: f64,
+
+    /// Minimal number of calls of a Process/Operation for both versions before it is compared.
+    #[arg(long, default_value_t = 20)]
+    min_count: usize,
+
+    #[arg(short = 'z', long, default_value_t = 2*60)]
+    timezone_minutes: i64,
+
+    #[arg(short = 'f', long, default_value_t = true)]
+    comma_float: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    set_tz_offset_minutes(args.timezone_minutes);
+    set_comma_float(args.comma_float);
+
+    let pars = CanaryParameters {
+        version_tag: args.version_tag,
+        baseline: args.baseline,
+        candidate: args.candidate,
+        alpha: args.alpha,
+        max_latency_ratio: args.max_latency_ratio,
+        max_error_rate_increase: args.max_error_rate_increase,
+        min_count: args.min_count,
+    };
+
+    let (mut path, comparisons) = analyze_canary(Path::new(&args.input), &pars);
+    let num_regressions = comparisons.iter().filter(|cmp| cmp.is_regression()).count();
+    println!(
+        "Compared {} Process/Operations of which {num_regressions} regressed.",
+        comparisons.len()
+    );
+    path.push("canary_report.txt");
+    write_report(path.to_str().unwrap());
+
+    if num_regressions > 0 {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,209 @@
+use clap::{Parser, Subcommand};
+use jaeger_stats::{
+    prune_cchain_file_locked, read_cchain_folder, write_cchain_folder, EndPointCChains, PrunePolicy,
+};
+use std::{collections::BTreeMap, path::Path, process};
+
+/// Maintain call-chain cache folders: list their contents, compare two folders, merge folders and prune outdated call-chains.
+/// Plain '.cchain' files are read as well, but modified entries are always written in the structured '.cchain.json' format.
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List the endpoints in a cache folder with their number of call-chains
+    List {
+        folder: String,
+
+        /// Also show the individual call-chains with their count, first-seen and last-seen dates
+        #[arg(short, long, default_value_t = false)]
+        chains: bool,
+    },
+    /// Show the call-chains that only occur in one of the two cache folders
+    Diff { folder_a: String, folder_b: String },
+    /// Merge the source folders into the target folder
+    Merge {
+        target: String,
+
+        #[arg(required = true)]
+        sources: Vec<String>,
+    },
+    /// Remove outdated or rare call-chains. Call-chains from plain '.cchain' files have no metadata and are kept.
+    Prune {
+        folder: String,
+
+        /// Remove call-chains not seen during this number of days before the most recent observation in the folder
+        #[arg(long)]
+        max_age_days: Option<i64>,
+
+        /// Remove call-chains that have been observed fewer times
+        #[arg(long)]
+        min_count: Option<usize>,
+
+        /// Only show what would be removed
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+}
+
+fn read_folder(folder: &str) -> BTreeMap<String, EndPointCChains> {
+    match read_cchain_folder(Path::new(folder)) {
+        Ok(cache) => cache,
+        Err(err) => {
+            println!("Failed to read call-chain folder '{folder}'. Error: {err:?}");
+            process::exit(1);
+        }
+    }
+}
+
+fn write_folder(folder: &str, cache: &BTreeMap<String, EndPointCChains>, merge_on_write: bool) {
+    if let Err(err) = write_cchain_folder(Path::new(folder), cache, merge_on_write) {
+        println!("Failed to write call-chain folder '{folder}'. Error: {err:?}");
+        process::exit(1);
+    }
+}
+
+fn list(folder: &str, show_chains: bool) {
+    let cache = read_folder(folder);
+    cache.iter().for_each(|(key, epcc)| {
+        let last_seen = epcc
+            .last_seen()
+            .map_or("-".to_owned(), |dt| dt.date().to_string());
+        println!(
+            "{key}: {} call-chains (last seen {last_seen})",
+            epcc.chains.len()
+        );
+        if show_chains {
+            epcc.entries().for_each(|(cck, info)| {
+                let date = |dt: Option<chrono::NaiveDateTime>| {
+                    dt.map_or("-".to_owned(), |dt| dt.date().to_string())
+                };
+                println!(
+                    "\t{}\tcount={}  first={}  last={}  sources={}",
+                    cck.call_chain_key(),
+                    info.count,
+                    date(info.first_seen),
+                    date(info.last_seen),
+                    info.sources.len()
+                )
+            });
+        }
+    });
+    println!("{} endpoints in '{folder}'", cache.len());
+}
+
+fn diff(folder_a: &str, folder_b: &str) {
+    let cache_a = read_folder(folder_a);
+    let cache_b = read_folder(folder_b);
+    let mut num_diffs = 0;
+    let mut keys: Vec<_> = cache_a.keys().chain(cache_b.keys()).collect();
+    keys.sort_unstable();
+    keys.dedup();
+    keys.into_iter().for_each(|key| {
+        let chains = |cache: &BTreeMap<String, EndPointCChains>| {
+            cache
+                .get(key)
+                .map(|epcc| {
+                    epcc.chains
+                        .iter()
+                        .map(|cck| cck.call_chain_key())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+        let (chains_a, chains_b) = (chains(&cache_a), chains(&cache_b));
+        let only_a: Vec<_> = chains_a.iter().filter(|c| !chains_b.contains(c)).collect();
+        let only_b: Vec<_> = chains_b.iter().filter(|c| !chains_a.contains(c)).collect();
+        if !only_a.is_empty() || !only_b.is_empty() {
+            println!("{key}:");
+            only_a.iter().for_each(|c| println!("\t< {c}"));
+            only_b.iter().for_each(|c| println!("\t> {c}"));
+            num_diffs += only_a.len() + only_b.len();
+        }
+    });
+    println!("{num_diffs} call-chains differ between '{folder_a}' (<) and '{folder_b}' (>)");
+}
+
+fn merge(target: &str, sources: &[String]) {
+    let mut cache = read_folder(target);
+    sources.iter().for_each(|source| {
+        read_folder(source)
+            .into_iter()
+            .for_each(|(key, epcc)| match cache.get_mut(&key) {
+                Some(curr) => curr.merge(&epcc),
+                None => {
+                    let mut new_entry = EndPointCChains::with_info(Vec::new());
+                    new_entry.merge(&epcc);
+                    cache.insert(key, new_entry);
+                }
+            })
+    });
+    let num_updated = cache.values().filter(|epcc| epcc.is_dirty()).count();
+    write_folder(target, &cache, true);
+    println!(
+        "Merged {} folders into '{target}': {num_updated} endpoints updated",
+        sources.len()
+    );
+}
+
+fn prune(folder: &str, policy: PrunePolicy, dry_run: bool) {
+    let mut cache = read_folder(folder);
+    let Some(reference) = cache.values().filter_map(|epcc| epcc.last_seen()).max() else {
+        println!("No call-chains with metadata found in '{folder}', so nothing to prune.");
+        return;
+    };
+    let mut num_removed = 0;
+    cache.iter_mut().for_each(|(key, epcc)| {
+        // the file is re-read and pruned under its lock, such that call-chains added by a concurrent analysis are kept
+        let removed = if dry_run {
+            epcc.prune(&policy, reference)
+        } else {
+            match prune_cchain_file_locked(Path::new(folder), key, &policy, reference) {
+                Ok(removed) => removed,
+                Err(err) => {
+                    println!(
+                        "Failed to prune '{key}' in call-chain folder '{folder}'. Error: {err:?}"
+                    );
+                    process::exit(1);
+                }
+            }
+        };
+        if removed > 0 {
+            println!("{key}: {removed} call-chains removed");
+            num_removed += removed;
+        }
+    });
+    if dry_run {
+        println!("Dry-run: {num_removed} call-chains would be removed");
+    } else {
+        println!("Removed {num_removed} call-chains from '{folder}'");
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    match args.command {
+        Command::List { folder, chains } => list(&folder, chains),
+        Command::Diff { folder_a, folder_b } => diff(&folder_a, &folder_b),
+        Command::Merge { target, sources } => merge(&target, &sources),
+        Command::Prune {
+            folder,
+            max_age_days,
+            min_count,
+            dry_run,
+        } => prune(
+            &folder,
+            PrunePolicy {
+                max_age_days,
+                min_count,
+            },
+            dry_run,
+        ),
+    }
+}
@@ -0,0 +1,104 @@
+use clap::Parser;
+use jaeger_stats::{set_comma_float, CompareParameters, StatsComparison, StatsRec};
+use std::{ffi::OsString, path::Path, process};
+
+/// Compare two statistics-files (json, bson or bincode), for example the analyses before and after a release.
+/// The comparison is written as csv, json and markdown. The process exits with exit-code 1 when a regression is found, and with
+/// exit-code 2 when a file can not be read or written, such that a CI-pipeline can distinguish both.
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// The statistics-file before (the baseline)
+    before: String,
+
+    /// The statistics-file after (the candidate)
+    after: String,
+
+    /// Base-name of the output-files. The extensions '.csv', '.json' and '.md' are added.
+    #[arg(short, long, default_value_t = String::from("comparison"))]
+    output: String,
+
+    /// Significance level used for the latency and the error-rate tests.
+    #[arg(long, default_value_t = 0.01)]
+    alpha: f64,
+
+    /// Maximal accepted ratio between the median latency after and before.
+    #[arg(long, default_value_t = 1.1)]
+    max_latency_ratio: f64,
+
+    /// Maximal accepted ratio between the P95 latency after and before.
+    #[arg(long, default_value_t = 1.25)]
+    max_p95_ratio: f64,
+
+    /// Maximal accepted increase of the error-rate (absolute fraction).
+    #[arg(long, default_value_t = 0.01)]
+    max_error_rate_increase: f64,
+
+    /// Minimal number of calls in both files before a Process/Operation or call-chain is checked for regressions.
+    #[arg(long, default_value_t = 20)]
+    min_count: usize,
+
+    /// Count a Process/Operation that is no longer observed as a regression.
+    #[arg(long, default_value_t = false)]
+    fail_on_removed: bool,
+
+    #[arg(short = 'f', long, default_value_t = true)]
+    comma_float: bool,
+}
+
+/// exit-code when a file can not be read or written (exit-code 1 signals a regression)
+const IO_ERROR: i32 = 2;
+
+fn read_stats(input: &str) -> StatsRec {
+    match StatsRec::read_file(&OsString::from(input)) {
+        Ok(stats_rec) => stats_rec,
+        Err(err) => {
+            println!("Failed to read statistics-file '{input}'. Error: {err:?}");
+            process::exit(IO_ERROR);
+        }
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    set_comma_float(args.comma_float);
+
+    let pars = CompareParameters {
+        alpha: args.alpha,
+        max_latency_ratio: args.max_latency_ratio,
+        max_p95_ratio: args.max_p95_ratio,
+        max_error_rate_increase: args.max_error_rate_increase,
+        min_count: args.min_count,
+        fail_on_removed: args.fail_on_removed,
+    };
+
+    let before = read_stats(&args.before);
+    let after = read_stats(&args.after);
+    let comparison = StatsComparison::new(&before, &after, &pars);
+
+    let output = Path::new(&args.output);
+    comparison.write_csv(&output.with_extension("csv"));
+    if let Err(err) = comparison.write_json(&output.with_extension("json")) {
+        println!("Failed to write the json-file. Error: {err:?}");
+        process::exit(IO_ERROR);
+    }
+    if let Err(err) = comparison.write_markdown(&output.with_extension("md")) {
+        println!("Failed to write the markdown-file. Error: {err:?}");
+        process::exit(IO_ERROR);
+    }
+
+    let num_regressions = comparison.regressions().count();
+    println!(
+        "Compared {} Process/Operations and call-chains of which {num_regressions} regressed.",
+        comparison.items.len()
+    );
+    comparison
+        .regressions()
+        .for_each(|item| println!("\t{} ({})", item.key, item.regression_str()));
+
+    if num_regressions > 0 {
+        process::exit(1);
+    }
+}
@@ -1,31 +1,69 @@
 use clap::Parser;
-use jaeger_stats::{build_graph, StatsRecJson};
-use std::ffi::OsString;
+use jaeger_stats::{build_graph, GraphFormat, StatsRec, TraceDataSet};
+use std::{ffi::OsString, path::Path, process};
 
-/// Stitching results of different runs of trace_analysis into a single CSV for visualization in Excel
+/// Export the service dependency graph of a statistics-file (json, bson or bincode) as Graphviz DOT, GraphML or a JSON node/edge list.
+/// Without '--service-oper' the full graph over all call-chains is exported. With '--service-oper' the graph is restricted to the
+/// call-chains that pass through this Service/Operation (the same graph as shown in the Mermaid diagrams).
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    // List of files to be stitched
+    /// The statistics-file, for example 'Stats/cummulative_trace_stats.json'
     #[arg(default_value_t = String::from("input.json"))]
     input: String,
 
-    #[arg(short, long, default_value_t = String::from("stitched.csv"))]
+    /// Base-name of the output-file. The extension is derived from the format.
+    #[arg(short, long, default_value_t = String::from("service_graph"))]
     output: String,
-    // #[arg(short, long, default_value_t = true)]
-    // comma_float: bool,
+
+    /// Output format. Repeat to write multiple formats.
+    #[arg(short, long, value_enum, default_values_t = [GraphFormat::Dot])]
+    format: Vec<GraphFormat>,
+
+    /// Restrict the graph to the call-chains that pass through this Service/Operation
+    #[arg(short, long)]
+    service_oper: Option<String>,
+
+    /// Mark the call-chain with this key (only used together with '--service-oper')
+    #[arg(long)]
+    call_chain: Option<String>,
 }
 
 fn main() {
     let args = Args::parse();
 
-    let stats_rec_path = OsString::from(&args.input);
-
-    //    set_comma_float(args.comma_float);
+    let stats_rec = match StatsRec::read_file(&OsString::from(&args.input)) {
+        Ok(stats_rec) => stats_rec,
+        Err(err) => {
+            println!(
+                "Failed to read statistics-file '{}'. Error: {err:?}",
+                args.input
+            );
+            process::exit(1);
+        }
+    };
 
-    let data = StatsRecJson::read_file(&stats_rec_path).expect("Failed to read JSON-file");
-    // add the processing
-    let graph = build_graph(&data);
+    let graph = match &args.service_oper {
+        Some(service_oper) => {
+            TraceDataSet::new(stats_rec).get_graph_export(service_oper, args.call_chain.as_deref())
+        }
+        None => build_graph(&stats_rec).to_graph_export(),
+    };
 
-    println!("{graph:#?}");
+    let output = Path::new(&args.output);
+    args.format.iter().for_each(|format| {
+        let path = output.with_extension(format.extension());
+        match graph.write_file(&path, *format) {
+            Ok(()) => println!(
+                "Written graph with {} nodes and {} edges to '{}'",
+                graph.nodes.len(),
+                graph.edges.len(),
+                path.display()
+            ),
+            Err(err) => {
+                println!("Failed to write '{}'. Error: {err:?}", path.display());
+                process::exit(1);
+            }
+        }
+    });
 }
@@ -0,0 +1,75 @@
+use clap::Parser;
+use jaeger_stats::{
+    set_comma_float, set_compact_stats, write_stats, write_stats_to_csv_file, StatsRec,
+};
+use std::{ffi::OsString, path::Path, process};
+
+/// Merge a series of statistics-files (json, bson or bincode) into a single statistics-file, for example to roll up hourly analyses into a daily analysis.
+/// The merged statistics are written in the format of the extension of the output-file, and a csv-file is written alongside.
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// The statistics-files to merge
+    #[arg(required = true, num_args = 1..)]
+    inputs: Vec<String>,
+
+    /// The output-file. The extension ('json', 'bson' or 'bincode') determines the output-format.
+    #[arg(short, long)]
+    output: String,
+
+    #[arg(short = 'f', long, default_value_t = true)]
+    comma_float: bool,
+
+    /// Store the durations and start-times of the merged statistics as sketches and time-buckets (a merge of compact files is always compact).
+    #[arg(long, default_value_t = false)]
+    compact: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    set_comma_float(args.comma_float);
+
+    set_compact_stats(args.compact);
+
+    let output = Path::new(&args.output);
+    let Some(ext) = output.extension().and_then(|ext| ext.to_str()) else {
+        println!(
+            "The output-file '{}' needs an extension ('json', 'bson' or 'bincode').",
+            args.output
+        );
+        process::exit(1);
+    };
+
+    let mut merged: Option<StatsRec> = None;
+    for input in &args.inputs {
+        let stats_rec = match StatsRec::read_file(&OsString::from(input)) {
+            Ok(stats_rec) => stats_rec,
+            Err(err) => {
+                println!("Failed to read statistics-file '{input}'. Error: {err:?}");
+                process::exit(1);
+            }
+        };
+        println!(
+            "Read '{input}' containing {} traces.",
+            stats_rec.trace_id.len()
+        );
+        match merged.as_mut() {
+            Some(merged) => merged.merge(stats_rec),
+            None => merged = Some(stats_rec),
+        }
+    }
+    let merged = merged.expect("At least one input-file is required");
+    println!(
+        "Merged {} files into {} traces from {} input-files.",
+        args.inputs.len(),
+        merged.trace_id.len(),
+        merged.num_files
+    );
+
+    let csv_file = output.with_extension("csv");
+    let csv_file = csv_file.to_str().unwrap();
+    write_stats_to_csv_file(csv_file, &merged);
+    write_stats(csv_file, merged, ext);
+}
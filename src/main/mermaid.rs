@@ -1,6 +1,9 @@
 use clap::Parser;
-use jaeger_stats::{load_viewer, utils, MermaidScope, Metric, TraceDataSet, Viewer};
-use std::path::Path;
+use jaeger_stats::{
+    jaeger_sequence_diagram, load_viewer, read_file_or_folder, utils, DiagramType, MermaidScope,
+    Metric, TraceDataSet, Viewer,
+};
+use std::{path::Path, process};
 
 /// Parsing and analyzin}g Jaeger traces
 
@@ -17,7 +20,7 @@ struct Args {
     edge_value: Metric,
 
     #[arg(short, long)] // "bspc-productinzicht/geefProducten"))]
-    service_oper: String,
+    service_oper: Option<String>,
 
     #[arg(long, default_value_t = String::from(EMPTY_ARG))]
     call_chain: String,
@@ -30,6 +33,18 @@ struct Args {
 
     #[arg(long, value_enum, default_value_t = MermaidScope::Full)]
     scope: MermaidScope,
+
+    /// A flowchart of the Service/Operation, or a sequence diagram of the most common call-chains of the endpoint 'service_oper'
+    #[arg(long, value_enum, default_value_t = DiagramType::Flowchart)]
+    diagram_type: DiagramType,
+
+    /// Maximal number of call-chains shown in a sequence diagram
+    #[arg(long, default_value_t = 10)]
+    max_chains: usize,
+
+    /// Generate the sequence diagram of the single trace with this trace-id. In that case 'input' is a file or folder with raw Jaeger traces.
+    #[arg(long)]
+    trace_id: Option<String>,
 }
 
 fn to_opt_str(s: &str) -> Option<&str> {
@@ -64,6 +79,26 @@ fn write_diagram(folder: &Path, proc_oper: &str, diagram: String) {
 fn main() {
     let args = Args::parse();
 
+    if let Some(trace_id) = &args.trace_id {
+        let (traces, num_files, _path) = read_file_or_folder(Path::new(&args.input));
+        println!("Extracted {} traces from {num_files} files.", traces.len());
+        let Some(mermaid) = jaeger_sequence_diagram(&traces, trace_id) else {
+            println!("Trace '{trace_id}' not found in '{}'", args.input);
+            process::exit(1);
+        };
+        write_diagram(
+            &utils::current_folder(),
+            &format!("trace_{trace_id}"),
+            mermaid,
+        );
+        return;
+    }
+
+    let Some(service_oper) = &args.service_oper else {
+        println!("The '--service-oper' argument is required (unless a '--trace-id' is provided)");
+        process::exit(1);
+    };
+
     match load_viewer(&args.input) {
         Ok(viewer) => {
             println!(
@@ -74,14 +109,22 @@ fn main() {
 
             let folder = utils::current_folder();
             println!("found folder = {}", folder.to_str().unwrap());
-            let mermaid = viewer.get_mermaid_diagram(
-                &args.service_oper,
-                to_opt_str(&args.call_chain),
-                args.edge_value,
-                args.scope,
-                args.compact,
-            );
-            write_diagram(&folder, &args.service_oper, mermaid);
+            match args.diagram_type {
+                DiagramType::Flowchart => {
+                    let mermaid = viewer.get_mermaid_diagram(
+                        service_oper,
+                        to_opt_str(&args.call_chain),
+                        args.edge_value,
+                        args.scope,
+                        args.compact,
+                    );
+                    write_diagram(&folder, service_oper, mermaid);
+                }
+                DiagramType::Sequence => {
+                    let mermaid = viewer.get_sequence_diagram(service_oper, args.max_chains);
+                    write_diagram(&folder, &format!("{service_oper}_sequence"), mermaid);
+                }
+            }
         }
         Err(err) => panic!("Reading '{}' failed with error: {err:?}\nNOTE: Raw trace-json is not a valid input. You need data as returned by either 'trace_analysis' or 'stitch'.", args.input),
     }
@@ -0,0 +1,128 @@
+use clap::{Parser, Subcommand};
+use jaeger_stats::{load_viewer, utils, ServicePath, Viewer};
+use std::{path::Path, process};
+
+/// Query the call-chains of a statistics-file (as generated by 'trace_analysis') or a stitched file (as generated by 'stitch').
+/// Find the endpoints that depend on a Service/Operation (blast-radius), or all paths from one Service/Operation to another.
+/// A Service without an operation matches all operations of that service.
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// The statistics-file or stitched file, for example 'Stats/cummulative_trace_stats.json'
+    input: String,
+
+    /// Write a focused Mermaid diagram of the result to this file
+    #[arg(short, long)]
+    mermaid: Option<String>,
+
+    /// Write the result as JSON to this file
+    #[arg(short, long)]
+    json: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List all endpoints whose call-chains pass through the Service/Operation, weighted by their share of the traffic
+    BlastRadius { service_oper: String },
+    /// List all distinct paths from the 'from' Service/Operation to the 'to' Service/Operation
+    Paths { from: String, to: String },
+}
+
+fn load(input: &str) -> Box<dyn Viewer> {
+    match load_viewer(input) {
+        Ok(viewer) => viewer,
+        Err(err) => {
+            println!("Failed to read '{input}'. Error: {err:?}\nNOTE: Raw trace-json is not a valid input. You need data as returned by either 'trace_analysis' or 'stitch'.");
+            process::exit(1);
+        }
+    }
+}
+
+fn format_opt(val: Option<f64>) -> String {
+    val.map_or("--".to_owned(), |v| format!("{v:.1}"))
+}
+
+fn print_path(path: &ServicePath) {
+    println!(
+        "    {:>8}  avg {:>8.1} ms  p95 {:>8} ms  {}",
+        path.count,
+        path.avg_duration_millis,
+        format_opt(path.p95_millis),
+        path.steps.join(" -> ")
+    );
+}
+
+fn check_written<E: std::fmt::Debug>(file_name: &str, result: Result<(), E>) {
+    match result {
+        Ok(()) => println!("Written '{file_name}'"),
+        Err(err) => {
+            println!("Failed to write '{file_name}'. Error: {err:?}");
+            process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let viewer = load(&args.input);
+
+    let mermaid = match &args.command {
+        Command::BlastRadius { service_oper } => {
+            let br = viewer.get_blast_radius(service_oper);
+            if br.total_count == 0 {
+                println!("No call-chains found that reach '{service_oper}'");
+                process::exit(1);
+            }
+            println!(
+                "'{}' is called {} times, of which {} via call-chains that could not be traced back to an endpoint.",
+                br.service_oper, br.total_count, br.unrooted_count
+            );
+            println!(
+                "\n{} endpoints depend on '{}':",
+                br.endpoints.len(),
+                br.service_oper
+            );
+            br.endpoints.iter().for_each(|ep| {
+                println!(
+                    "  {:>6.1}%  {:>8} calls  {:>8} calls/request  {}",
+                    ep.share * 100.0,
+                    ep.count,
+                    format_opt(ep.calls_per_request),
+                    ep.endpoint
+                );
+                ep.paths.iter().for_each(print_path);
+            });
+            if let Some(file_name) = &args.json {
+                check_written(file_name, br.write_json(Path::new(file_name)));
+            }
+            br.to_mermaid()
+        }
+        Command::Paths { from, to } => {
+            let sp = viewer.get_paths_between(from, to);
+            if sp.paths.is_empty() {
+                println!("No paths found from '{from}' to '{to}'");
+                process::exit(1);
+            }
+            println!(
+                "{} distinct paths from '{}' to '{}' carrying {} calls:",
+                sp.paths.len(),
+                sp.from,
+                sp.to,
+                sp.total_count
+            );
+            sp.paths.iter().for_each(print_path);
+            if let Some(file_name) = &args.json {
+                check_written(file_name, sp.write_json(Path::new(file_name)));
+            }
+            sp.to_mermaid()
+        }
+    };
+
+    if let Some(file_name) = &args.mermaid {
+        check_written(file_name, utils::write_string_to_file(file_name, mermaid));
+    }
+}
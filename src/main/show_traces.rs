@@ -1,7 +1,12 @@
 use clap::Parser;
-use std::path::Path;
+use jaeger_stats::{jaeger_waterfall, utils};
+use std::{
+    io::{self, IsTerminal},
+    path::Path,
+};
 
 /// Show the Jaeger-traces, or a selection of jaeger-traces, as Pretty-printed JSON in UTF-8 format.
+/// With '--waterfall' or '--gantt' a timeline of the selected traces is shown instead.
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -18,6 +23,22 @@ struct Args {
     // call_chain_folder: String,
     #[arg(short = 'z', long, default_value_t = 2*60)]
     timezone_minutes: i64,
+
+    /// Print a waterfall of each selected trace in the terminal instead of writing the JSON
+    #[arg(short, long, default_value_t = false)]
+    waterfall: bool,
+
+    /// Write a Mermaid gantt-chart of each selected trace to '<trace_id>.gantt.mermaid' instead of writing the JSON
+    #[arg(short, long, default_value_t = false)]
+    gantt: bool,
+
+    /// Width in characters of the time-axis of the waterfall
+    #[arg(long, default_value_t = 60)]
+    width: usize,
+
+    /// Do not color the spans with errors (colors are only used when the output is a terminal)
+    #[arg(long, default_value_t = false)]
+    no_color: bool,
 }
 
 fn main() {
@@ -27,6 +48,39 @@ fn main() {
 
     println!("Extracted {} traces from {num_files} files.", traces.len());
 
+    if args.waterfall || args.gantt {
+        let trace_ids: Vec<_> = if args.trace_ids.is_empty() {
+            traces
+                .iter()
+                .flat_map(|jt| jt.data.iter().map(|item| item.traceID.clone()))
+                .collect()
+        } else {
+            args.trace_ids
+                .split(',')
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_owned())
+                .collect()
+        };
+        let ansi = !args.no_color && io::stdout().is_terminal();
+        trace_ids.iter().for_each(|trace_id| {
+            let Some(waterfall) = jaeger_waterfall(&traces, trace_id) else {
+                println!("Trace '{trace_id}' not found.");
+                return;
+            };
+            if args.waterfall {
+                println!("\n{}", waterfall.to_text(args.width, ansi));
+            }
+            if args.gantt {
+                let file_name = format!("{trace_id}.gantt.mermaid");
+                match utils::write_string_to_file(&file_name, waterfall.to_gantt()) {
+                    Ok(()) => println!("Written gantt-chart to '{file_name}'"),
+                    Err(err) => println!("Failed to write '{file_name}'. Error: {err:?}"),
+                }
+            }
+        });
+        return;
+    }
+
     //TODO: deduplication of traces needs to be added here, or in write-traces. However writing traces twice does not harm the proces.
 
     let num_written = jaeger_stats::write_traces(path, traces, &args.trace_ids);
@@ -1,5 +1,8 @@
 use clap::Parser;
-use jaeger_stats::{set_comma_float, AnomalyParameters, StitchList, StitchParameters, Stitched};
+use jaeger_stats::{
+    set_comma_float, set_slo_config, AnomalyParameters, SloConfig, StitchList, StitchParameters,
+    Stitched,
+};
 use std::path::Path;
 
 /// Stitching results of different runs of trace_analysis into a single CSV for visualization in Excel
@@ -43,6 +46,10 @@ struct Args {
 
     #[arg(long, default_value_t = 2.0)]
     l1_dev_bound: f64,
+
+    /// A json-file with Service Level Objectives per endpoint or Service/Operation. When provided the compliance, Apdex, burn-rate and budget consumed are added to the stitched Process/Operations.
+    #[arg(long)]
+    slo_config: Option<String>,
 }
 
 fn main() {
@@ -52,6 +59,16 @@ fn main() {
 
     set_comma_float(args.comma_float);
 
+    if let Some(slo_config) = args.slo_config {
+        match SloConfig::read_file(Path::new(&slo_config)) {
+            Ok(config) => set_slo_config(Some(config)),
+            Err(err) => {
+                println!("Failed to read SLO-config '{slo_config}': {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+
     let stitch_pars = {
         let scaled_slope_bound = args.scaled_slope_bound;
         let st_num_points = args.st_num_points;
@@ -1,5 +1,10 @@
 use clap::Parser;
-use jaeger_stats::{analyze_file_or_folder, set_comma_float, set_tz_offset_minutes, write_report};
+use jaeger_stats::{
+    analyze_file_or_folder, parse_interval_secs, set_bucket_interval, set_comma_float,
+    set_compact_stats, set_gap_repair, set_infer_virtual_nodes, set_instance_stats,
+    set_repair_strategy, set_slo_config, set_split_dimensions, set_tz_offset_minutes,
+    set_waterfall_output, write_report, RepairStrategy, SloConfig,
+};
 use std::path::Path;
 
 /// Parsing and analyzing Jaeger traces
@@ -28,6 +33,10 @@ struct Args {
     #[arg(short, long, default_value_t = false)]
     trace_output: bool,
 
+    /// Also write a waterfall of each trace to 'Traces/<trace-id>.waterfall.txt' (only applies together with --trace-output).
+    #[arg(long, default_value_t = false)]
+    waterfall_output: bool,
+
     /// The output-extension determines the output-types are 'bson', 'json' and 'bincode' (which is also used as the file-extension).
     #[arg(short, long, default_value_t = String::from("bson"))]
     output_ext: String,
@@ -37,6 +46,42 @@ struct Args {
 
     #[arg(long, default_value_t = String::from(EMPTY_ARG))]
     display_call_chain: String,
+
+    /// Collect statistics per instance (host/pod) of each Service/Operation and report the instances that deviate from their siblings.
+    #[arg(long, default_value_t = false)]
+    instance_stats: bool,
+
+    /// Infer virtual services for uninstrumented dependencies (databases, external API's) called by leaf client-spans. Inferred services are marked with '(inferred)'.
+    #[arg(long, default_value_t = false)]
+    infer_virtual_nodes: bool,
+
+    /// Comma-separated list of span-attributes (for example 'http.method,tenant,service.version') used to split the statistics per Process/Operation and call-chain into sub-series per attribute-value.
+    #[arg(long)]
+    split_dimensions: Option<String>,
+
+    /// Store the durations and start-times in the statistics-file as sketches and time-buckets instead of the raw values. This reduces the size of the file, while percentiles become approximate (1% relative error).
+    #[arg(long, default_value_t = false)]
+    compact: bool,
+
+    /// Interval of the time-buckets, for example '1m', '5m' or '1h'. When set the statistics are also computed per time-bucket and written as a time-series (in stitched format) alongside the cumulative statistics.
+    #[arg(long)]
+    bucket_interval: Option<String>,
+
+    /// A json-file with Service Level Objectives per endpoint or Service/Operation. When provided the compliance, Apdex, burn-rate and budget consumed are reported.
+    #[arg(long)]
+    slo_config: Option<String>,
+
+    /// Strategy to repair non-rooted call-chains that match several expected call-chains: 'unique' (only repair a unique match), 'most-probable' or 'proportional' (based on the observed frequencies of the candidate call-chains).
+    #[arg(long, default_value_t = String::from("unique"))]
+    repair_strategy: String,
+
+    /// Minimal share of the most probable candidate call-chain for a repair with the 'most-probable' strategy.
+    #[arg(long, default_value_t = 0.8)]
+    repair_confidence: f64,
+
+    /// Repair gaps in the middle of the call-chains of incomplete traces by joining orphan sub-trees to their ancestor, based on the known call-chains of the endpoint.
+    #[arg(long, default_value_t = false)]
+    repair_gaps: bool,
 }
 
 fn to_opt_str(s: &str) -> Option<&str> {
@@ -60,6 +105,48 @@ fn main() {
 
     set_comma_float(args.comma_float);
 
+    set_instance_stats(args.instance_stats);
+
+    set_waterfall_output(args.waterfall_output);
+
+    set_infer_virtual_nodes(args.infer_virtual_nodes);
+
+    set_compact_stats(args.compact);
+
+    if let Some(interval) = args.bucket_interval {
+        match parse_interval_secs(&interval) {
+            Ok(secs) => set_bucket_interval(Some(secs)),
+            Err(err) => {
+                println!("{err}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    set_gap_repair(args.repair_gaps);
+
+    match args.repair_strategy.parse::<RepairStrategy>() {
+        Ok(strategy) => set_repair_strategy(strategy, args.repair_confidence),
+        Err(err) => {
+            println!("{err}");
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(slo_config) = args.slo_config {
+        match SloConfig::read_file(Path::new(&slo_config)) {
+            Ok(config) => set_slo_config(Some(config)),
+            Err(err) => {
+                println!("Failed to read SLO-config '{slo_config}': {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(dimensions) = args.split_dimensions {
+        set_split_dimensions(dimensions.split(',').map(|s| s.trim().to_owned()).collect());
+    }
+
     let mut path = analyze_file_or_folder(
         Path::new(&args.input),
         caching_processes,
@@ -0,0 +1,112 @@
+use clap::Parser;
+use jaeger_stats::{set_comma_float, utils, AmplificationMatrix, StatsRec};
+use std::{collections::BTreeMap, ffi::OsString, path::Path, process};
+
+/// What-if calculator: project the request rate on each backend Service/Operation for a target mix of endpoint rates.
+/// The projection is based on the load amplification matrix derived from a statistics-file (json, bson or bincode).
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// The statistics-file, for example 'Stats/cummulative_trace_stats.bson'
+    input: String,
+
+    /// Target rate of an endpoint in requests per second, as '<service>/<operation>=<rate>'. Repeat this argument for each endpoint in the mix.
+    #[arg(short, long, required = true)]
+    rate: Vec<String>,
+
+    /// Write the projected rates to this csv-file
+    #[arg(short, long)]
+    output: Option<String>,
+
+    #[arg(short = 'f', long, default_value_t = true)]
+    comma_float: bool,
+}
+
+/// parse '<endpoint>=<rate>', where the last '=' separates the rate (the operation might contain a '=')
+fn parse_rate(s: &str) -> Result<(String, f64), String> {
+    let (endpoint, rate) = s
+        .rsplit_once('=')
+        .ok_or_else(|| format!("Expected '<service>/<operation>=<rate>', found '{s}'"))?;
+    let rate = rate
+        .trim()
+        .parse::<f64>()
+        .map_err(|err| format!("Invalid rate in '{s}': {err}"))?;
+    Ok((endpoint.trim().to_owned(), rate))
+}
+
+fn main() {
+    let args = Args::parse();
+
+    set_comma_float(args.comma_float);
+
+    let endpoint_rates = match args
+        .rate
+        .iter()
+        .map(|s| parse_rate(s))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(rates) => rates,
+        Err(err) => {
+            println!("{err}");
+            process::exit(1);
+        }
+    };
+
+    let stats_rec = match StatsRec::read_file(&OsString::from(&args.input)) {
+        Ok(stats_rec) => stats_rec,
+        Err(err) => {
+            println!(
+                "Failed to read statistics-file '{}'. Error: {err:?}",
+                args.input
+            );
+            process::exit(1);
+        }
+    };
+    let matrix = AmplificationMatrix::new(&stats_rec);
+
+    let backend_rates = match matrix.project(&endpoint_rates) {
+        Ok(rates) => rates,
+        Err(err) => {
+            println!("{err}");
+            println!("Known endpoints are:");
+            matrix
+                .endpoint_calls
+                .iter()
+                .for_each(|(ep, cnt)| println!("\t{ep}  ({cnt} calls)"));
+            process::exit(1);
+        }
+    };
+
+    // the service is the part of the Service/Operation before the first '/'
+    let mut service_rates: BTreeMap<&str, f64> = BTreeMap::new();
+    backend_rates.iter().for_each(|(backend, rate)| {
+        let service = backend.split_once('/').map_or(&backend[..], |(s, _)| s);
+        *service_rates.entry(service).or_default() += rate
+    });
+
+    let mut csv = utils::CsvFileBuffer::new();
+    csv.add_section("Projected request rate (req/sec) per backend Service/Operation");
+    csv.add_line("Backend; Rate".to_owned());
+    backend_rates.iter().for_each(|(backend, rate)| {
+        csv.add_line(format!("{backend}; {}", utils::format_float(*rate)))
+    });
+    csv.add_section("Projected request rate (req/sec) per backend service");
+    csv.add_line("Service; Rate".to_owned());
+    service_rates.iter().for_each(|(service, rate)| {
+        csv.add_line(format!("{service}; {}", utils::format_float(*rate)))
+    });
+
+    backend_rates
+        .iter()
+        .for_each(|(backend, rate)| println!("{backend}: {rate:.3} req/sec"));
+    println!();
+    service_rates
+        .iter()
+        .for_each(|(service, rate)| println!("{service}: {rate:.3} req/sec"));
+
+    if let Some(output) = args.output {
+        csv.write_file(Path::new(&output));
+        println!("Projected rates written to '{output}'");
+    }
+}
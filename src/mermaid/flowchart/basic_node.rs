@@ -1,7 +1,8 @@
 use super::{
     super::service_oper_graph::ServiceOperationType, escape_name::escape_mermaid_label,
-    indent::INDENT_STR,
+    indent::INDENT_STR, INFERRED_STYLE,
 };
+use crate::processed::is_inferred_service;
 
 /// A basic node without any nested nodes&
 pub struct MermaidBasicNode {
@@ -32,5 +33,11 @@ impl MermaidBasicNode {
         if self.serv_oper_type == ServiceOperationType::Emphasized {
             diagram.push(format!("{}style {} fill:#00802b", indent_str, the_service))
         };
+        if is_inferred_service(&self.service) {
+            diagram.push(format!(
+                "{}style {} {INFERRED_STYLE}",
+                indent_str, the_service
+            ))
+        }
     }
 }
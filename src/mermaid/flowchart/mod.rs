@@ -10,3 +10,6 @@ pub use basic_node::MermaidBasicNode;
 pub use link::MermaidLink;
 pub use mermaid::Mermaid;
 pub use sub_graph::MermaidSubGraph;
+
+/// style applied to inferred (virtual) services, which are uninstrumented dependencies
+const INFERRED_STYLE: &str = "stroke-dasharray: 5 5";
@@ -1,7 +1,8 @@
 use super::{
     super::service_oper_graph::ServiceOperationType, basic_node::MermaidBasicNode,
-    escape_name::escape_mermaid_label, indent::INDENT_STR, node::MermaidNode,
+    escape_name::escape_mermaid_label, indent::INDENT_STR, node::MermaidNode, INFERRED_STYLE,
 };
+use crate::processed::is_inferred_service;
 
 /// A node with an inner structure representing nested nodes.
 pub struct MermaidSubGraph {
@@ -55,5 +56,9 @@ impl MermaidSubGraph {
             };
             diagram.push(format!("\tstyle {} fill:#00b33c", the_service))
         };
+        if is_inferred_service(&self.service) {
+            let the_service = esc_service.as_ref().unwrap_or(&self.service);
+            diagram.push(format!("\tstyle {} {INFERRED_STYLE}", the_service))
+        }
     }
 }
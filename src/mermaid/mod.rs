@@ -1,11 +1,18 @@
 mod flowchart;
+mod path_query;
+mod sequence;
 mod service_oper_graph;
 mod trace_data;
 mod trace_forrest;
 mod trace_node;
 mod trace_paths;
 mod tt_utils;
+mod waterfall;
 
+pub use path_query::{BlastRadius, EndpointShare, ServicePath, ServicePaths};
+pub use sequence::jaeger_sequence_diagram;
 pub use trace_data::TraceData;
 pub use trace_paths::TracePaths;
+pub(crate) use tt_utils::ends_in;
 pub use tt_utils::split_service_operation;
+pub use waterfall::{jaeger_waterfall, Waterfall, WaterfallRow};
@@ -0,0 +1,420 @@
+//! Queries over the call-chains of a dataset: which endpoints depend on a Service/Operation (blast-radius) and
+//! which paths lead from one Service/Operation to another.
+use super::{
+    flowchart::{Mermaid, MermaidBasicNode, MermaidLink},
+    service_oper_graph::{LinkType, ServiceOperationType},
+    trace_data::TraceData,
+    trace_paths::TracePaths,
+    tt_utils::{call_matches, ends_in, split_service_operation},
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    error::Error,
+    fs,
+    path::Path,
+};
+
+/// Count-weighted average of an optional value
+#[derive(Default)]
+struct WeightedAvg {
+    sum: f64,
+    weight: u64,
+}
+
+impl WeightedAvg {
+    fn add(&mut self, value: Option<f64>, count: u64) {
+        if let Some(value) = value {
+            self.sum += value * count as f64;
+            self.weight += count;
+        }
+    }
+
+    fn get(&self) -> Option<f64> {
+        (self.weight > 0).then(|| self.sum / self.weight as f64)
+    }
+}
+
+/// Accumulator for the statistics of all call-chains that map to the same path
+#[derive(Default)]
+struct PathAcc {
+    count: u64,
+    rate: Option<f64>,
+    avg_duration_millis: WeightedAvg,
+    p95_millis: WeightedAvg,
+}
+
+impl PathAcc {
+    fn add(&mut self, td: &TraceData) {
+        let count = td.data.count;
+        self.count += count;
+        self.rate = match (self.rate, td.data.rate) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+        self.avg_duration_millis
+            .add(Some(td.data.avg_duration_millis), count);
+        self.p95_millis.add(td.data.p95_millis, count);
+    }
+
+    fn into_service_path(self, steps: Vec<String>) -> ServicePath {
+        ServicePath {
+            steps,
+            count: self.count,
+            rate: self.rate,
+            avg_duration_millis: self.avg_duration_millis.get().unwrap_or_default(),
+            p95_millis: self.p95_millis.get(),
+        }
+    }
+}
+
+/// Turn the accumulated paths in a list of ServicePaths ordered by descending count
+fn to_sorted_paths(per_path: HashMap<Vec<String>, PathAcc>) -> Vec<ServicePath> {
+    let mut paths: Vec<_> = per_path
+        .into_iter()
+        .map(|(steps, acc)| acc.into_service_path(steps))
+        .collect();
+    paths.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.steps.cmp(&b.steps)));
+    paths
+}
+
+/// Accumulate the links of a set of paths and render them as a focused Mermaid diagram.
+/// The 'emphasized' nodes are highlighted, and the links of the 'marked' path are drawn as emphasized links.
+fn focused_diagram<'a>(
+    title: String,
+    paths: impl Iterator<Item = (&'a [String], u64)>,
+    emphasized: &[&str],
+    marked: Option<&[String]>,
+) -> String {
+    let mut nodes = BTreeSet::new();
+    let mut links: BTreeMap<(&str, &str), u64> = BTreeMap::new();
+    paths.for_each(|(steps, count)| {
+        steps.iter().for_each(|step| {
+            nodes.insert(step.as_str());
+        });
+        steps.windows(2).for_each(|link| {
+            *links.entry((&link[0], &link[1])).or_default() += count;
+        })
+    });
+    let marked_links: BTreeSet<(&str, &str)> = marked
+        .map(|steps| {
+            steps
+                .windows(2)
+                .map(|link| (link[0].as_str(), link[1].as_str()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut mermaid = Mermaid::new(Some(title));
+    nodes.into_iter().for_each(|node| {
+        let serv_oper_type = if emphasized.contains(&node) {
+            ServiceOperationType::Emphasized
+        } else {
+            ServiceOperationType::Default
+        };
+        mermaid.add_node(MermaidBasicNode::new(node.to_owned(), serv_oper_type))
+    });
+    links.into_iter().for_each(|((src, target), count)| {
+        let link_type = if marked_links.contains(&(src, target)) {
+            LinkType::Emphasized
+        } else {
+            LinkType::Default
+        };
+        mermaid.add_link(MermaidLink::new(
+            src.to_owned(),
+            target.to_owned(),
+            Some(count as f64),
+            None,
+            link_type,
+        ))
+    });
+    mermaid.to_diagram()
+}
+
+#[derive(Serialize, Debug)]
+/// The traffic of a single endpoint that reaches the Service/Operation under investigation
+pub struct EndpointShare {
+    pub endpoint: String,
+    /// number of calls to the Service/Operation that originate from this endpoint
+    pub count: u64,
+    /// fraction of all rooted calls to the Service/Operation that originate from this endpoint
+    pub share: f64,
+    pub rate: Option<f64>,
+    /// average number of calls to the Service/Operation per request on this endpoint (if the endpoint-count is known)
+    pub calls_per_request: Option<f64>,
+    /// the distinct call-chains from the endpoint to the Service/Operation
+    pub paths: Vec<ServicePath>,
+}
+
+#[derive(Serialize, Debug, Default)]
+/// All endpoints whose call-chains pass through a Service/Operation, weighted by their share of the traffic.
+pub struct BlastRadius {
+    pub service_oper: String,
+    /// total number of calls to the Service/Operation
+    pub total_count: u64,
+    /// calls to the Service/Operation via call-chains that could not be traced back to an endpoint
+    pub unrooted_count: u64,
+    /// endpoints ordered by descending count
+    pub endpoints: Vec<EndpointShare>,
+}
+
+impl BlastRadius {
+    fn new(trace_paths: &TracePaths, service_oper: &str) -> Self {
+        let (service, oper_opt) = split_service_operation(service_oper);
+        let all_chains = || trace_paths.0.values().flat_map(|tds| tds.iter());
+
+        // number of requests per endpoint, based on the call-chains that consist of the endpoint only
+        let mut endpoint_counts: HashMap<String, u64> = HashMap::new();
+        all_chains()
+            .filter(|td| td.rooted && td.trace_path.call_chain.len() == 1)
+            .for_each(|td| {
+                *endpoint_counts
+                    .entry(td.trace_path.call_chain[0].get_process_method())
+                    .or_default() += td.data.count
+            });
+
+        let mut total_count = 0;
+        let mut unrooted_count = 0;
+        let mut per_endpoint: HashMap<String, HashMap<Vec<String>, PathAcc>> = HashMap::new();
+        all_chains()
+            .filter(|td| ends_in(&td.trace_path.call_chain, service, oper_opt))
+            .for_each(|td| {
+                total_count += td.data.count;
+                if !td.rooted {
+                    unrooted_count += td.data.count;
+                    return;
+                }
+                let steps: Vec<_> = td
+                    .trace_path
+                    .call_chain
+                    .iter()
+                    .map(|call| call.get_process_method())
+                    .collect();
+                per_endpoint
+                    .entry(steps[0].clone())
+                    .or_default()
+                    .entry(steps)
+                    .or_default()
+                    .add(td);
+            });
+
+        let rooted_count = total_count - unrooted_count;
+        let mut endpoints: Vec<_> = per_endpoint
+            .into_iter()
+            .map(|(endpoint, per_path)| {
+                let paths = to_sorted_paths(per_path);
+                let count = paths.iter().map(|p| p.count).sum();
+                let rate = paths.iter().filter_map(|p| p.rate).reduce(|a, b| a + b);
+                EndpointShare {
+                    share: if rooted_count > 0 {
+                        count as f64 / rooted_count as f64
+                    } else {
+                        0.0
+                    },
+                    calls_per_request: endpoint_counts
+                        .get(&endpoint)
+                        .filter(|ep_count| **ep_count > 0)
+                        .map(|ep_count| count as f64 / *ep_count as f64),
+                    endpoint,
+                    count,
+                    rate,
+                    paths,
+                }
+            })
+            .collect();
+        endpoints.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.endpoint.cmp(&b.endpoint))
+        });
+
+        Self {
+            service_oper: service_oper.to_owned(),
+            total_count,
+            unrooted_count,
+            endpoints,
+        }
+    }
+
+    /// A Mermaid diagram showing the paths from all affected endpoints to the Service/Operation
+    pub fn to_mermaid(&self) -> String {
+        let title = format!("Endpoints depending on {}", self.service_oper);
+        let emphasized: Vec<_> = self
+            .endpoints
+            .iter()
+            .flat_map(|ep| ep.paths.iter())
+            .filter_map(|path| path.steps.last().map(|s| s.as_str()))
+            .collect();
+        let paths = self
+            .endpoints
+            .iter()
+            .flat_map(|ep| ep.paths.iter().map(|p| (&p.steps[..], p.count)));
+        focused_diagram(title, paths, &emphasized, None)
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Debug)]
+/// A distinct path between two Service/Operations with the statistics of the calls to the target over this path
+pub struct ServicePath {
+    pub steps: Vec<String>,
+    pub count: u64,
+    pub rate: Option<f64>,
+    /// average duration of the calls to the target Service/Operation over this path
+    pub avg_duration_millis: f64,
+    pub p95_millis: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Default)]
+/// All distinct paths from one Service/Operation to another one, ordered by descending count
+pub struct ServicePaths {
+    pub from: String,
+    pub to: String,
+    pub total_count: u64,
+    pub paths: Vec<ServicePath>,
+}
+
+impl ServicePaths {
+    fn new(trace_paths: &TracePaths, from: &str, to: &str) -> Self {
+        let (from_service, from_oper) = split_service_operation(from);
+        let (to_service, to_oper) = split_service_operation(to);
+
+        let mut per_path: HashMap<Vec<String>, PathAcc> = HashMap::new();
+        trace_paths
+            .0
+            .values()
+            .flat_map(|tds| tds.iter())
+            .filter_map(|td| Self::sub_path(td, (from_service, from_oper), (to_service, to_oper)))
+            .for_each(|(steps, td)| per_path.entry(steps).or_default().add(td));
+        let paths = to_sorted_paths(per_path);
+
+        Self {
+            from: from.to_owned(),
+            to: to.to_owned(),
+            total_count: paths.iter().map(|p| p.count).sum(),
+            paths,
+        }
+    }
+
+    /// The part of the call-chain of 'td' from the last occurence of 'from' up to 'to', provided the call-chain ends in 'to'.
+    fn sub_path<'a>(
+        td: &'a TraceData,
+        (from_service, from_oper): (&str, Option<&str>),
+        (to_service, to_oper): (&str, Option<&str>),
+    ) -> Option<(Vec<String>, &'a TraceData)> {
+        let call_chain = &td.trace_path.call_chain;
+        if !ends_in(call_chain, to_service, to_oper) {
+            return None;
+        }
+        let start = call_chain[..call_chain.len() - 1]
+            .iter()
+            .rposition(|call| call_matches(call, from_service, from_oper))?;
+        let steps = call_chain[start..]
+            .iter()
+            .map(|call| call.get_process_method())
+            .collect();
+        Some((steps, td))
+    }
+
+    /// A Mermaid diagram of all paths, where the most frequently used path is emphasized
+    pub fn to_mermaid(&self) -> String {
+        let title = format!("Paths from {} to {}", self.from, self.to);
+        let emphasized: Vec<_> = self
+            .paths
+            .iter()
+            .flat_map(|p| [p.steps.first(), p.steps.last()])
+            .flatten()
+            .map(|s| s.as_str())
+            .collect();
+        let paths = self.paths.iter().map(|p| (&p.steps[..], p.count));
+        let marked = self.paths.first().map(|p| &p.steps[..]);
+        focused_diagram(title, paths, &emphasized, marked)
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+impl TracePaths {
+    /// All endpoints whose call-chains pass through 'service_oper' (a service or a service/operation)
+    pub fn blast_radius(&self, service_oper: &str) -> BlastRadius {
+        BlastRadius::new(self, service_oper)
+    }
+
+    /// All distinct paths from 'from' to 'to' (each a service or a service/operation)
+    pub fn paths_between(&self, from: &str, to: &str) -> ServicePaths {
+        ServicePaths::new(self, from, to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::trace_data::TraceData, TracePaths};
+    use std::collections::HashMap;
+
+    fn trace_paths(chains: &[(&str, bool, u64)]) -> TracePaths {
+        let data = chains
+            .iter()
+            .map(|(key, rooted, count)| {
+                TraceData::new(
+                    key,
+                    *rooted,
+                    false,
+                    *count,
+                    None,
+                    10.0,
+                    None,
+                    None,
+                    Some(20.0),
+                    None,
+                )
+            })
+            .collect();
+        TracePaths(HashMap::from([("all".to_string(), data)]))
+    }
+
+    #[test]
+    fn blast_radius_shares() {
+        let tp = trace_paths(&[
+            ("gw/a", true, 10),
+            ("gw/b", true, 5),
+            ("gw/a | svc/x", true, 30),
+            ("gw/b | mid/y | svc/x", true, 10),
+            ("mid/y | svc/x", false, 4),
+        ]);
+        let br = tp.blast_radius("svc/x");
+        assert_eq!(br.total_count, 44);
+        assert_eq!(br.unrooted_count, 4);
+        assert_eq!(br.endpoints.len(), 2);
+        assert_eq!(br.endpoints[0].endpoint, "gw/a");
+        assert_eq!(br.endpoints[0].share, 0.75);
+        assert_eq!(br.endpoints[0].calls_per_request, Some(3.0));
+        assert_eq!(
+            br.endpoints[1].paths[0].steps,
+            vec!["gw/b", "mid/y", "svc/x"]
+        );
+    }
+
+    #[test]
+    fn paths_between_services() {
+        let tp = trace_paths(&[
+            ("gw/a | mid/y | svc/x", true, 6),
+            ("gw/b | mid/y | svc/x", true, 2),
+            ("gw/b | mid/z | svc/x", true, 1),
+            ("gw/b | mid/y", true, 9),
+        ]);
+        let sp = tp.paths_between("mid", "svc/x");
+        assert_eq!(sp.total_count, 9);
+        assert_eq!(sp.paths.len(), 2);
+        assert_eq!(sp.paths[0].steps, vec!["mid/y", "svc/x"]);
+        assert_eq!(sp.paths[0].count, 8);
+        assert_eq!(sp.paths[0].p95_millis, Some(20.0));
+        assert!(sp.to_mermaid().contains("==>"));
+    }
+}
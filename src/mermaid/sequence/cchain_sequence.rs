@@ -0,0 +1,155 @@
+use super::{
+    super::{
+        trace_paths::TracePaths,
+        tt_utils::{call_matches, split_service_operation},
+    },
+    sequence_diagram::{Arrow, MermaidSequence},
+    CLIENT,
+};
+use crate::stats::call_chain::Call;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet},
+};
+
+/// Count and average duration of all call-chains with the same sequence of calls
+#[derive(Default)]
+struct StepStats {
+    count: u64,
+    sum_duration_millis: f64,
+    is_leaf: bool,
+}
+
+impl StepStats {
+    fn avg_duration_millis(&self) -> f64 {
+        if self.count > 0 {
+            self.sum_duration_millis / self.count as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The rooted call-chains that start at an endpoint, keyed by the sequence of calls
+struct ChainTree<'a> {
+    steps: BTreeMap<Vec<&'a Call>, StepStats>,
+    included: BTreeSet<Vec<&'a Call>>,
+}
+
+impl<'a> ChainTree<'a> {
+    /// add the call to 'chain[len - 1]' and the nested calls of the included chains
+    fn add_calls(&self, seq: &mut MermaidSequence, caller: &str, chain: &[&'a Call]) {
+        let Some(stats) = self.steps.get(chain) else {
+            // prefix that is not observed as a call-chain itself
+            return;
+        };
+        let call = chain[chain.len() - 1];
+        seq.add_message(
+            caller,
+            &call.service,
+            Arrow::Call,
+            format!("{} ({}x)", call.operation, stats.count),
+        );
+        seq.add_note(
+            &call.service,
+            format!("avg {:.1} ms", stats.avg_duration_millis()),
+        );
+
+        // the nested calls ordered by descending count
+        let mut nested: Vec<_> = self
+            .included
+            .iter()
+            .filter(|cc| cc.len() == chain.len() + 1 && cc.starts_with(chain))
+            .map(|cc| (cc, self.steps.get(cc).map_or(0, |s| s.count)))
+            .collect();
+        nested.sort_by_key(|(_, count)| Reverse(*count));
+        nested
+            .into_iter()
+            .for_each(|(cc, _)| self.add_calls(seq, &call.service, cc));
+
+        seq.add_message(&call.service, caller, Arrow::Reply, "return".to_owned());
+    }
+}
+
+impl TracePaths {
+    /// Build a Mermaid sequence diagram of the 'max_chains' most common (full) call-chains that start at the endpoint 'service_oper'.
+    /// The messages show the number of calls and the notes the average latencies.
+    pub fn get_sequence_diagram(&self, service_oper: &str, max_chains: usize) -> String {
+        let (service, oper_opt) = split_service_operation(service_oper);
+
+        let mut steps: BTreeMap<Vec<&Call>, StepStats> = BTreeMap::new();
+        self.0
+            .values()
+            .flat_map(|tds| tds.iter())
+            .filter(|td| {
+                td.rooted
+                    && td
+                        .trace_path
+                        .call_chain
+                        .first()
+                        .is_some_and(|call| call_matches(call, service, oper_opt))
+            })
+            .for_each(|td| {
+                let stats = steps
+                    .entry(td.trace_path.call_chain.iter().collect())
+                    .or_default();
+                stats.count += td.data.count;
+                stats.sum_duration_millis += td.data.avg_duration_millis * td.data.count as f64;
+                stats.is_leaf |= td.is_leaf;
+            });
+
+        // select the most common full call-chains (or all call-chains if no leaf is marked) and include all their prefixes
+        let mut chains: Vec<_> = steps.iter().filter(|(_, s)| s.is_leaf).collect();
+        if chains.is_empty() {
+            chains = steps.iter().collect();
+        }
+        let num_chains = chains.len();
+        chains.sort_by_key(|(_, s)| Reverse(s.count));
+        let included: BTreeSet<_> = chains
+            .into_iter()
+            .take(max_chains)
+            .flat_map(|(cc, _)| (1..=cc.len()).map(|len| cc[..len].to_vec()))
+            .collect();
+
+        let title = format!(
+            "Most common call-chains of {service_oper} ({} of {num_chains})",
+            num_chains.min(max_chains)
+        );
+        let mut seq = MermaidSequence::new(Some(title));
+        let tree = ChainTree { steps, included };
+        let mut endpoints: Vec<_> = tree.included.iter().filter(|cc| cc.len() == 1).collect();
+        endpoints.sort_by_key(|cc| Reverse(tree.steps.get(*cc).map_or(0, |s| s.count)));
+        endpoints
+            .into_iter()
+            .for_each(|cc| tree.add_calls(&mut seq, CLIENT, cc));
+        seq.to_diagram()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::{trace_data::TraceData, TracePaths};
+    use std::collections::HashMap;
+
+    #[test]
+    fn most_common_chains_only() {
+        let data = [
+            ("gw/a", 10, false),
+            ("gw/a | svc/x", 7, false),
+            ("gw/a | svc/x | db/q", 7, true),
+            ("gw/a | svc/y", 3, true),
+            ("gw/b | svc/y", 5, true),
+        ]
+        .into_iter()
+        .map(|(key, count, is_leaf)| {
+            TraceData::new(key, true, is_leaf, count, None, 2.0, None, None, None, None)
+        })
+        .collect();
+        let tp = TracePaths(HashMap::from([("all".to_string(), data)]));
+        let diagram = tp.get_sequence_diagram("gw/a", 1);
+        assert!(diagram.contains("P0->>P1: a (10x)"));
+        assert!(diagram.contains("P2->>P3: q (7x)"));
+        assert!(!diagram.contains("y ("));
+        assert!(diagram.ends_with("P1-->>P0: return"));
+    }
+}
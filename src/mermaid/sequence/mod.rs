@@ -0,0 +1,8 @@
+mod cchain_sequence;
+mod sequence_diagram;
+mod trace_sequence;
+
+pub use trace_sequence::jaeger_sequence_diagram;
+
+/// participant that represents the (external) client that calls the endpoint
+const CLIENT: &str = "client";
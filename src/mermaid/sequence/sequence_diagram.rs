@@ -0,0 +1,145 @@
+/// The arrow-types used in a sequence diagram
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Arrow {
+    /// synchronous call (solid line with arrowhead)
+    Call,
+    /// asynchronous call, i.e. the caller does not wait for a response (solid line with open arrow)
+    AsyncCall,
+    /// the response to a synchronous call (dotted line with arrowhead)
+    Reply,
+}
+
+impl Arrow {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Arrow::Call => "->>",
+            Arrow::AsyncCall => "-)",
+            Arrow::Reply => "-->>",
+        }
+    }
+}
+
+enum SequenceItem {
+    Message {
+        from: usize,
+        to: usize,
+        arrow: Arrow,
+        text: String,
+    },
+    Note {
+        over: usize,
+        text: String,
+    },
+}
+
+/// Escape text such that it does not break a line of the sequence diagram ('#' and ';' have a special meaning)
+fn escape_text(text: &str) -> String {
+    // a single pass, such that the ';' of an inserted entity-code is not escaped again
+    text.chars()
+        .flat_map(|c| match c {
+            '#' => "#35;".chars().collect(),
+            ';' => "#59;".chars().collect(),
+            c => vec![c],
+        })
+        .collect()
+}
+
+/// A Mermaid sequence diagram. Participants are declared in the order of their first appearance.
+pub struct MermaidSequence {
+    title: Option<String>,
+    participants: Vec<String>,
+    items: Vec<SequenceItem>,
+}
+
+impl MermaidSequence {
+    pub fn new(title: Option<String>) -> Self {
+        Self {
+            title,
+            participants: Vec::new(),
+            items: Vec::new(),
+        }
+    }
+
+    /// get the index of the participant, and add it if it does not exist yet
+    fn participant(&mut self, name: &str) -> usize {
+        match self.participants.iter().position(|p| p == name) {
+            Some(idx) => idx,
+            None => {
+                self.participants.push(name.to_owned());
+                self.participants.len() - 1
+            }
+        }
+    }
+
+    /// add a message from participant 'from' to participant 'to'
+    pub fn add_message(&mut self, from: &str, to: &str, arrow: Arrow, text: String) {
+        let from = self.participant(from);
+        let to = self.participant(to);
+        self.items.push(SequenceItem::Message {
+            from,
+            to,
+            arrow,
+            text,
+        })
+    }
+
+    /// add a note over a participant
+    pub fn add_note(&mut self, over: &str, text: String) {
+        let over = self.participant(over);
+        self.items.push(SequenceItem::Note { over, text })
+    }
+
+    pub fn to_diagram(&self) -> String {
+        let mut diagram = Vec::new();
+        if let Some(title) = self.title.as_ref() {
+            diagram.push(format!("---\ntitle: {title}\n---"));
+        };
+        diagram.push("sequenceDiagram".to_string());
+        // participants get a short identifier, as service-names can contain characters that are not allowed in identifiers
+        self.participants
+            .iter()
+            .enumerate()
+            .for_each(|(idx, name)| {
+                diagram.push(format!("\tparticipant P{idx} as {}", escape_text(name)))
+            });
+        self.items.iter().for_each(|item| match item {
+            SequenceItem::Message {
+                from,
+                to,
+                arrow,
+                text,
+            } => diagram.push(format!(
+                "\tP{from}{}P{to}: {}",
+                arrow.as_str(),
+                escape_text(text)
+            )),
+            SequenceItem::Note { over, text } => {
+                diagram.push(format!("\tNote over P{over}: {}", escape_text(text)))
+            }
+        });
+        diagram.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_text, Arrow, MermaidSequence};
+
+    #[test]
+    fn escape_hash_and_semicolon() {
+        assert_eq!(escape_text("Controller#create"), "Controller#35;create");
+        assert_eq!(escape_text("a;b#c"), "a#59;b#35;c");
+        assert_eq!(escape_text("GET /api"), "GET /api");
+
+        let mut seq = MermaidSequence::new(None);
+        seq.add_message(
+            "gw",
+            "app#1",
+            Arrow::Call,
+            "Controller#create;v2".to_owned(),
+        );
+        let diagram = seq.to_diagram();
+        assert!(diagram.contains("participant P1 as app#35;1"));
+        assert!(diagram.contains("P0->>P1: Controller#35;create#59;v2"));
+    }
+}
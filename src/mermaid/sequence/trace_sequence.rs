@@ -0,0 +1,131 @@
+use super::{
+    sequence_diagram::{Arrow, MermaidSequence},
+    CLIENT,
+};
+use crate::{
+    processed::{Span, Spans, Trace},
+    raw::JaegerTrace,
+};
+
+/// A child that ends at most this number of micro-seconds after its parent is still considered a synchronous call, as the
+/// clocks of the hosts of the parent and the child can be skewed.
+const ASYNC_MARGIN_MICROS: i64 = 10_000;
+
+/// The span is called asynchronously when it has a FOLLOWS_FROM reference, when it is a message-producer or -consumer,
+/// or when it ends clearly after its parent ended (so the parent did not wait for it).
+fn is_async(span: &Span, parent: &Span) -> bool {
+    let end_micros = |s: &Span| s.start_dt.and_utc().timestamp_micros() + s.duration_micros;
+    span.follows_from
+        || matches!(
+            span.span_kind.as_deref(),
+            Some("producer") | Some("consumer")
+        )
+        || end_micros(span) > end_micros(parent) + ASYNC_MARGIN_MICROS
+}
+
+struct TraceSequence<'a> {
+    spans: &'a Spans,
+    children: Vec<Vec<usize>>,
+    start_micros: i64,
+    seq: MermaidSequence,
+}
+
+impl<'a> TraceSequence<'a> {
+    fn new(trace: &'a Trace) -> Self {
+        Self {
+            spans: &trace.spans,
+            children: trace.spans.get_ordered_children(),
+            start_micros: trace.start_dt.and_utc().timestamp_micros(),
+            seq: MermaidSequence::new(Some(format!("Trace {}", trace.trace_id))),
+        }
+    }
+
+    /// add the call of span 'idx' by 'caller', followed by all nested calls in timestamp order
+    fn add_call(&mut self, caller: &str, idx: usize, arrow: Arrow) {
+        let span = &self.spans.items[idx];
+        let service = span.get_process_str();
+        self.seq
+            .add_message(caller, service, arrow, span.operation_name.clone());
+        let offset_millis =
+            (span.start_dt.and_utc().timestamp_micros() - self.start_micros) as f64 / 1000.0;
+        let async_label = if arrow == Arrow::AsyncCall {
+            " (async)"
+        } else {
+            ""
+        };
+        self.seq.add_note(
+            service,
+            format!(
+                "{:.1} ms{async_label} at +{offset_millis:.1} ms",
+                span.duration_micros as f64 / 1000.0
+            ),
+        );
+
+        let children = self.children[idx].clone();
+        children.into_iter().for_each(|child| {
+            let arrow = if is_async(&self.spans.items[child], span) {
+                Arrow::AsyncCall
+            } else {
+                Arrow::Call
+            };
+            self.add_call(service, child, arrow)
+        });
+
+        if arrow == Arrow::Call {
+            let reply = match span.http_status_code {
+                Some(code) => format!("HTTP {code}"),
+                None => "return".to_owned(),
+            };
+            self.seq.add_message(service, caller, Arrow::Reply, reply);
+        }
+    }
+}
+
+/// Build a Mermaid sequence diagram of a single trace, with the calls in timestamp order and the durations as notes.
+/// Asynchronous calls are shown as open arrows without a reply.
+pub fn trace_sequence_diagram(trace: &Trace) -> String {
+    let mut ts = TraceSequence::new(trace);
+    // The root and the spans with a missing parent are all shown as calls by the client
+    trace
+        .spans
+        .get_top_spans()
+        .into_iter()
+        .for_each(|idx| ts.add_call(CLIENT, idx, Arrow::Call));
+    ts.seq.to_diagram()
+}
+
+/// Build the Mermaid sequence diagram for the trace with 'trace_id' in a set of raw Jaeger traces (None if the trace is not found).
+pub fn jaeger_sequence_diagram(jaeger_traces: &[JaegerTrace], trace_id: &str) -> Option<String> {
+    Trace::find_in_jaeger(jaeger_traces, trace_id).map(|trace| trace_sequence_diagram(&trace))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::trace_sequence_diagram;
+    use crate::processed::test_traces::{build_trace, TestSpan};
+    use serde_json::json;
+
+    #[test]
+    fn async_calls() {
+        let trace = build_trace(
+            "seq-async",
+            &[
+                TestSpan::new("r", None, "gw/GET", 0, 100_000),
+                // ends 2 ms after its parent due to clock-skew
+                TestSpan::new("a", Some("r"), "skewed/get", 10_000, 92_000),
+                // ends 50 ms after its parent
+                TestSpan::new("b", Some("r"), "late/get", 20_000, 130_000),
+                TestSpan::new("c", Some("r"), "queue/send", 30_000, 1_000)
+                    .tag("span.kind", json!("producer")),
+            ],
+        );
+        let diagram = trace_sequence_diagram(&trace);
+        // the participants are P1 (gw), P2 (skewed), P3 (late) and P4 (queue)
+        assert!(diagram.contains("participant P2 as skewed"), "{diagram}");
+        assert!(diagram.contains("P1->>P2: get"), "{diagram}");
+        assert!(diagram.contains("P2-->>P1: HTTP 200"), "{diagram}");
+        assert!(diagram.contains("P1-)P3: get"), "{diagram}");
+        assert!(!diagram.contains("P3-->>P1"), "{diagram}");
+        assert!(diagram.contains("P1-)P4: send"), "{diagram}");
+    }
+}
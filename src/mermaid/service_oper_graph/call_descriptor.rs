@@ -27,7 +27,7 @@ impl CallDescriptorStats {
             p75_millis: AverageData::new(data.count, data.p75_millis),
             p90_millis: AverageData::new(data.count, data.p90_millis),
             p95_millis: AverageData::new(data.count, data.p95_millis),
-            p99_millis: AverageData::new(data.count, data.p90_millis),
+            p99_millis: AverageData::new(data.count, data.p99_millis),
         }
     }
 
@@ -95,3 +95,30 @@ impl CallDescriptor {
         //         |v| {v.update(data); *v}))  // Ugly as we update in place and then return the modified value (mix of functional and imperative)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::CallDescriptorStats;
+    use crate::{mermaid::trace_data::TraceDataStats, utils::AggregateData};
+
+    #[test]
+    fn percentiles_are_kept_apart() {
+        let data = |count, base: f64| TraceDataStats {
+            count,
+            rate: None,
+            avg_duration_millis: base,
+            p75_millis: Some(base + 1.0),
+            p90_millis: Some(base + 2.0),
+            p95_millis: Some(base + 3.0),
+            p99_millis: Some(base + 4.0),
+        };
+        let mut cds = CallDescriptorStats::new(&data(1, 10.0));
+        assert_eq!(cds.p90_millis.get_value(), Some(12.0));
+        assert_eq!(cds.p99_millis.get_value(), Some(14.0));
+
+        cds.update(&data(3, 20.0));
+        assert_eq!(cds.count, 4);
+        assert_eq!(cds.avg_duration_millis.get_value(), Some(17.5));
+        assert_eq!(cds.p99_millis.get_value(), Some(21.5));
+    }
+}
@@ -14,6 +14,14 @@ pub fn edge_value_to_selector(edge_value: Metric) -> EdgeValueSelector {
         Metric::P99Millis => |cds| cds.and_then(|ips| ips.p99_millis.get_value()),
         Metric::MaxDurationMillis => unimplemented!(),
         Metric::MedianDurationMillis => unimplemented!(),
+        // the SLO- and cache-metrics are not available per call, so the edges do not show a value
+        Metric::SloCompliance
+        | Metric::Apdex
+        | Metric::ErrorBudgetConsumed
+        | Metric::BurnRate
+        | Metric::CacheHitRatio
+        | Metric::DownstreamCalls
+        | Metric::FlushedDownstreamCalls => |_| None,
         metric => panic!(
             "Uncovered metric '{}' provided as edge-value",
             metric.to_str()
@@ -8,7 +8,13 @@ use super::{
     service::Service,
     service_oper_type::ServiceOperationType,
 };
-use crate::{mermaid::trace_data::TraceDataStats, stats::call_chain::Call, MermaidScope, Metric};
+use crate::{
+    graph::{GraphEdge, GraphExport},
+    mermaid::trace_data::TraceDataStats,
+    stats::call_chain::Call,
+    utils::AggregateData,
+    MermaidScope, Metric,
+};
 
 /// A ServiceOperGraph is a vector of service that each contain a vector of Operations. Each Operation collects data on the set of outbound calls.
 /// This data-structure tracks all links that exist between a caller and calllees.
@@ -184,6 +190,31 @@ impl ServiceOperGraph {
         &self.0[serv_idx]
     }
 
+    /// Convert to a graph with a node per Service/Operation and an edge per call, for export to DOT, GraphML or JSON.
+    pub fn to_graph_export(&self) -> GraphExport {
+        let mut graph = GraphExport::default();
+        self.0.iter().for_each(|service| {
+            service.operations.iter().for_each(|oper| {
+                let from = graph.add_node(&service.service, &oper.oper, None);
+                oper.calls.iter().for_each(|call| {
+                    let to_service = self.get_service(call.to_service);
+                    let to_oper = &to_service.get_operation(call.to_oper).oper;
+                    let to = graph.add_node(&to_service.service, to_oper, None);
+                    graph.add_edge(GraphEdge {
+                        from: from.clone(),
+                        to,
+                        count: call.stats.count,
+                        rate: call.stats.rate.get_value(),
+                        avg_duration_millis: call.stats.avg_duration_millis.get_value(),
+                        p95_millis: call.stats.p95_millis.get_value(),
+                    })
+                })
+            })
+        });
+        graph.sort();
+        graph
+    }
+
     /// generate a detailled Mermaid diagram, which includes the operations and the outbound calls of each of the services.
     fn mermaid_diagram_full(
         &self,
@@ -7,6 +7,7 @@ use super::{
     },
 };
 use crate::{
+    graph::GraphExport,
     mermaid::trace_forrest::TraceForrest,
     stats::{CChainStatsKey, LeafService},
     MermaidScope, Metric,
@@ -154,15 +155,12 @@ impl TracePaths {
         sog
     }
 
-    /// Build a diagram for the 'service_oper'  and 'call_chain_key' based on the stitched 'data'.
-    pub fn get_diagram(
+    /// Build the ServiceOperGraph for the 'service_oper' and mark the 'call_chain_key' (if provided)
+    fn build_marked_serv_oper_graph(
         &self,
         service_oper: &str,
         call_chain_key: Option<&str>,
-        edge_value: Metric,
-        scope: MermaidScope,
-        compact: bool,
-    ) -> String {
+    ) -> ServiceOperGraph {
         let sog = self.build_serv_oper_graph(service_oper);
 
         // If a callchain-key is specified we mark the this call-chain and add the additional statistics.
@@ -176,6 +174,25 @@ impl TracePaths {
         };
 
         sog.update_service_operation_type(service_oper, ServiceOperationType::Emphasized);
-        sog.mermaid_diagram(scope, compact, service_oper, edge_value)
+        sog
+    }
+
+    /// Build a diagram for the 'service_oper'  and 'call_chain_key' based on the stitched 'data'.
+    pub fn get_diagram(
+        &self,
+        service_oper: &str,
+        call_chain_key: Option<&str>,
+        edge_value: Metric,
+        scope: MermaidScope,
+        compact: bool,
+    ) -> String {
+        self.build_marked_serv_oper_graph(service_oper, call_chain_key)
+            .mermaid_diagram(scope, compact, service_oper, edge_value)
+    }
+
+    /// Build the graph of the 'service_oper' for export to DOT, GraphML or JSON.
+    pub fn get_graph_export(&self, service_oper: &str, call_chain_key: Option<&str>) -> GraphExport {
+        self.build_marked_serv_oper_graph(service_oper, call_chain_key)
+            .to_graph_export()
     }
 }
@@ -1,5 +1,5 @@
 use super::service_oper_graph::{LinkType, ServiceOperGraph};
-use crate::stats::CChainStatsKey;
+use crate::stats::{call_chain::Call, CChainStatsKey};
 
 // split a service out of a service-oper string by spitting at the '/' (or returning the full str if no '/' is present)
 pub fn split_service(service_oper: &str) -> &str {
@@ -8,10 +8,29 @@ pub fn split_service(service_oper: &str) -> &str {
 
 // split a a service-oper string by spitting at the '/' and returnning the tuple `(service, Some(operation))`  (or returning the `(Service, None)`` if no '/' is present)
 pub fn split_service_operation(service_oper: &str) -> (&str, Option<&str>) {
-    let mut parts = service_oper.split('/');
-    let service = parts.next().unwrap();
-    let oper_opt = parts.next();
-    (service, oper_opt)
+    // split at the first '/' only, as operations such as 'GET /api' can contain a '/'
+    match service_oper.split_once('/') {
+        Some((service, oper)) => (service, Some(oper)),
+        None => (service_oper, None),
+    }
+}
+
+/// Does the 'call' match the selector, which is either a 'service' (oper_opt is None) or a 'service/operation'
+pub fn call_matches(call: &Call, service: &str, oper_opt: Option<&str>) -> bool {
+    call.service == service && oper_opt.is_none_or(|oper| call.operation == oper)
+}
+
+/// Does the call-chain end in a call that matches the selector. When selecting a service only, the chain should enter the service
+/// in its last step, such that the (outbound) calls made by the service itself are not counted as additional calls to the service.
+/// This is the single definition of 'passing a service' that is used for the blast-radius, both in the path-queries and in the graph-analytics.
+pub fn ends_in(call_chain: &[Call], service: &str, oper_opt: Option<&str>) -> bool {
+    match call_chain {
+        [.., prev, last] if oper_opt.is_none() => {
+            call_matches(last, service, None) && prev.service != service
+        }
+        [.., last] => call_matches(last, service, oper_opt),
+        [] => false,
+    }
 }
 
 pub fn get_call_chain_prefix(service_oper: &str, call_chain_key: &str) -> String {
@@ -36,3 +55,33 @@ pub fn mark_selected_call_chain(
         .for_each(|(from, to)| sog.update_line_type(from, to, LinkType::Emphasized));
     sog
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{call_matches, split_service_operation};
+    use crate::stats::call_chain::Call;
+
+    #[test]
+    fn operation_can_contain_a_slash() {
+        assert_eq!(split_service_operation("svc"), ("svc", None));
+        assert_eq!(
+            split_service_operation("svc/handle"),
+            ("svc", Some("handle"))
+        );
+        // previously the operation was truncated to 'GET ', so the paths of this operation were not found
+        assert_eq!(
+            split_service_operation("gateway/GET /api/orders"),
+            ("gateway", Some("GET /api/orders"))
+        );
+
+        let call = Call {
+            service: "gateway".to_owned(),
+            operation: "GET /api/orders".to_owned(),
+            ..Default::default()
+        };
+        let (service, oper) = split_service_operation("gateway/GET /api/orders");
+        assert!(call_matches(&call, service, oper));
+        assert!(call_matches(&call, "gateway", None));
+        assert!(!call_matches(&call, "gateway", Some("GET ")));
+    }
+}
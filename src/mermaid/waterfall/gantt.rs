@@ -0,0 +1,44 @@
+use super::Waterfall;
+
+/// ':', ';' and '#' have a special meaning in a gantt task-line
+fn escape_task_name(name: &str) -> String {
+    name.replace([':', ';', '#'], " ")
+}
+
+impl Waterfall {
+    /// Render the waterfall as a Mermaid gantt-chart with a millisecond time-axis relative to the start of the trace.
+    /// The nesting is shown by indenting the task-names, and spans with errors are marked as critical (red).
+    pub fn to_gantt(&self) -> String {
+        let mut diagram = vec![
+            format!(
+                "---\ntitle: Trace {} ({:.1} ms)\n---",
+                self.trace_id,
+                self.duration_micros as f64 / 1000.0
+            ),
+            "gantt".to_owned(),
+            "\tdateFormat x".to_owned(),
+            "\taxisFormat %S.%L".to_owned(),
+            format!("\tsection {}", escape_task_name(&self.trace_id)),
+        ];
+        self.rows.iter().enumerate().for_each(|(idx, row)| {
+            let name = format!(
+                "{}{}/{} ({:.1} ms)",
+                "· ".repeat(row.depth),
+                row.service,
+                row.operation,
+                row.duration_micros as f64 / 1000.0
+            );
+            let start_millis = (row.offset_micros as f64 / 1000.0).round() as i64;
+            // tasks shorter than a millisecond are shown with the minimal width of 1 ms
+            let end_millis = ((row.offset_micros + row.duration_micros) as f64 / 1000.0)
+                .round()
+                .max(start_millis as f64 + 1.0) as i64;
+            let tag = if row.is_error { "crit, " } else { "" };
+            diagram.push(format!(
+                "\t{} :{tag}s{idx}, {start_millis}, {end_millis}",
+                escape_task_name(&name)
+            ));
+        });
+        diagram.join("\n")
+    }
+}
@@ -0,0 +1,196 @@
+//! A waterfall (timeline) of a single trace, with the spans nested by parent and aligned on a time-axis.
+//! The waterfall can be rendered as a Mermaid gantt-chart (for reports) or as a tree in the terminal (for quick triage).
+mod gantt;
+mod terminal;
+
+use crate::{
+    processed::{Span, Trace},
+    raw::JaegerTrace,
+    stats::get_span_error_information,
+};
+
+/// A single span in the waterfall
+pub struct WaterfallRow {
+    /// index of the row of the parent span (None for the root and for spans with a missing parent)
+    pub parent: Option<usize>,
+    pub depth: usize,
+    /// this is the last child of its parent (or the last top-level span)
+    pub is_last: bool,
+    pub service: String,
+    pub operation: String,
+    /// start of the span relative to the start of the trace
+    pub offset_micros: i64,
+    pub duration_micros: i64,
+    /// the span has a not-ok HTTP status, an ERROR log-line or an 'error' tag
+    pub is_error: bool,
+}
+
+impl WaterfallRow {
+    fn new(span: &Span, parent: Option<usize>, depth: usize, trace_start_micros: i64) -> Self {
+        let (http_codes, error_logs) = get_span_error_information(span);
+        let error_tag = span.attributes.get("error").is_some_and(|e| e == "true");
+        Self {
+            parent,
+            depth,
+            is_last: false,
+            service: span.get_process_str().to_owned(),
+            operation: span.operation_name.clone(),
+            offset_micros: span.start_dt.and_utc().timestamp_micros() - trace_start_micros,
+            duration_micros: span.duration_micros,
+            is_error: !http_codes.is_empty() || !error_logs.is_empty() || error_tag,
+        }
+    }
+}
+
+/// The spans of a trace in depth-first order, where the children of a span are ordered by their start-time
+pub struct Waterfall {
+    pub trace_id: String,
+    pub duration_micros: i64,
+    pub rows: Vec<WaterfallRow>,
+}
+
+impl Waterfall {
+    pub fn new(trace: &Trace) -> Self {
+        let spans = &trace.spans.items;
+        let trace_start_micros = trace.start_dt.and_utc().timestamp_micros();
+        let children = trace.spans.get_ordered_children();
+        let tops = trace.spans.get_top_spans();
+
+        // depth-first traversal via an explicit stack of (span_idx, parent_row, depth, is_last)
+        let mut rows = Vec::with_capacity(spans.len());
+        let mut stack: Vec<_> = tops
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, idx)| (*idx, None, 0, i == 0))
+            .collect();
+        while let Some((idx, parent, depth, is_last)) = stack.pop() {
+            let mut row = WaterfallRow::new(&spans[idx], parent, depth, trace_start_micros);
+            row.is_last = is_last;
+            rows.push(row);
+            let row_idx = rows.len() - 1;
+            children[idx]
+                .iter()
+                .rev()
+                .enumerate()
+                .for_each(|(i, child)| stack.push((*child, Some(row_idx), depth + 1, i == 0)));
+        }
+
+        Self {
+            trace_id: trace.trace_id.clone(),
+            duration_micros: trace.duration_micros,
+            rows,
+        }
+    }
+
+    pub fn num_errors(&self) -> usize {
+        self.rows.iter().filter(|row| row.is_error).count()
+    }
+}
+
+/// Build the waterfall for the trace with 'trace_id' in a set of raw Jaeger traces (None if the trace is not found).
+pub fn jaeger_waterfall(jaeger_traces: &[JaegerTrace], trace_id: &str) -> Option<Waterfall> {
+    Trace::find_in_jaeger(jaeger_traces, trace_id).map(|trace| Waterfall::new(&trace))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Waterfall, WaterfallRow};
+    use crate::processed::test_traces::{build_trace, TestSpan};
+    use serde_json::json;
+
+    #[test]
+    fn rows_of_trace() {
+        let trace = build_trace(
+            "t1",
+            &[
+                TestSpan::new("r", None, "gw/GET", 0, 10_000),
+                // the children are ordered by start-time, not by their position in the trace
+                TestSpan::new("b", Some("r"), "svcb/call", 5_000, 4_000),
+                TestSpan::new("a", Some("r"), "svca/call", 1_000, 3_000),
+                TestSpan::new("a1", Some("a"), "db/query", 2_000, 1_000)
+                    .tag("http.status_code", json!(500)),
+                // the parent is missing, so this span is shown as a top-level row
+                TestSpan::new("o", Some("missing"), "batch/run", 7_000, 1_000),
+            ],
+        );
+        let waterfall = Waterfall::new(&trace);
+        let rows: Vec<_> = waterfall
+            .rows
+            .iter()
+            .map(|row| {
+                (
+                    format!("{}/{}", row.service, row.operation),
+                    row.parent,
+                    row.depth,
+                    row.is_last,
+                )
+            })
+            .collect();
+        let expect = |name: &str, parent, depth, is_last| (name.to_owned(), parent, depth, is_last);
+        assert_eq!(
+            rows,
+            vec![
+                expect("gw/GET", None, 0, false),
+                expect("svca/call", Some(0), 1, false),
+                expect("db/query", Some(1), 2, true),
+                expect("svcb/call", Some(0), 1, true),
+                expect("batch/run", None, 0, true),
+            ]
+        );
+        assert_eq!(waterfall.rows[1].offset_micros, 1_000);
+        assert_eq!(waterfall.num_errors(), 1);
+        assert!(waterfall.rows[2].is_error);
+    }
+
+    fn row(
+        parent: Option<usize>,
+        depth: usize,
+        is_last: bool,
+        offset: i64,
+        dur: i64,
+    ) -> WaterfallRow {
+        WaterfallRow {
+            parent,
+            depth,
+            is_last,
+            service: format!("svc{depth}"),
+            operation: "op".to_owned(),
+            offset_micros: offset,
+            duration_micros: dur,
+            is_error: depth == 2,
+        }
+    }
+
+    fn waterfall() -> Waterfall {
+        Waterfall {
+            trace_id: "t1".to_owned(),
+            duration_micros: 10_000,
+            rows: vec![
+                row(None, 0, true, 0, 10_000),
+                row(Some(0), 1, false, 1_000, 5_000),
+                row(Some(1), 2, true, 2_000, 1_000),
+                row(Some(0), 1, true, 6_000, 4_000),
+            ],
+        }
+    }
+
+    #[test]
+    fn terminal_tree() {
+        let text = waterfall().to_text(10, false);
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[2].contains("├─ svc1/op"));
+        assert!(lines[3].contains("│  └─ svc2/op"));
+        assert!(lines[3].starts_with('!'));
+        assert!(lines[4].contains("└─ svc1/op"));
+        assert!(lines[4].ends_with("|      ████|"));
+    }
+
+    #[test]
+    fn gantt_chart() {
+        let gantt = waterfall().to_gantt();
+        assert!(gantt.contains("gantt"));
+        assert!(gantt.contains("· · svc2/op (1.0 ms) :crit, s2, 2, 3"));
+    }
+}
@@ -0,0 +1,73 @@
+use super::Waterfall;
+
+const ANSI_RED: &str = "\x1b[31m";
+const ANSI_RESET: &str = "\x1b[0m";
+/// names longer than this are truncated to keep the bars aligned
+const MAX_NAME_WIDTH: usize = 80;
+
+impl Waterfall {
+    /// The tree-prefix of a row, which shows the connections to its ancestors and siblings
+    fn tree_prefix(&self, idx: usize) -> String {
+        let row = &self.rows[idx];
+        if row.depth == 0 {
+            return String::new();
+        }
+        // walk up to the top-level and collect whether each ancestor (below the top-level) has later siblings
+        let mut parts = vec![if row.is_last { "└─ " } else { "├─ " }];
+        let mut ancestor = row.parent;
+        while let Some(anc) = ancestor {
+            let anc_row = &self.rows[anc];
+            if anc_row.depth == 0 {
+                break;
+            }
+            parts.push(if anc_row.is_last { "   " } else { "│  " });
+            ancestor = anc_row.parent;
+        }
+        parts.into_iter().rev().collect()
+    }
+
+    /// Render the waterfall as a tree for the terminal, with a bar of 'width' characters per span on a shared time-axis.
+    /// Spans with errors are marked with a '!' and are colored red when 'ansi' is set.
+    pub fn to_text(&self, width: usize, ansi: bool) -> String {
+        let names: Vec<_> = (0..self.rows.len())
+            .map(|idx| {
+                let row = &self.rows[idx];
+                let name = format!("{}{}/{}", self.tree_prefix(idx), row.service, row.operation);
+                if name.chars().count() > MAX_NAME_WIDTH {
+                    name.chars().take(MAX_NAME_WIDTH - 3).collect::<String>() + "..."
+                } else {
+                    name
+                }
+            })
+            .collect();
+        let name_width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+        let scale = width as f64 / self.duration_micros.max(1) as f64;
+
+        let mut lines = vec![format!(
+            "Trace {}: {} spans in {:.1} ms, {} with errors",
+            self.trace_id,
+            self.rows.len(),
+            self.duration_micros as f64 / 1000.0,
+            self.num_errors()
+        )];
+        self.rows.iter().zip(names).for_each(|(row, name)| {
+            let start = ((row.offset_micros as f64 * scale).floor() as usize).min(width);
+            let end = (((row.offset_micros + row.duration_micros) as f64 * scale).ceil() as usize)
+                .clamp((start + 1).min(width), width);
+            let bar = " ".repeat(start) + &"█".repeat(end - start) + &" ".repeat(width - end);
+            let line = format!(
+                "{} {:<name_width$}  {:>9.1} ms {:>9.1} ms  |{bar}|",
+                if row.is_error { '!' } else { ' ' },
+                name,
+                row.offset_micros as f64 / 1000.0,
+                row.duration_micros as f64 / 1000.0,
+            );
+            lines.push(if ansi && row.is_error {
+                format!("{ANSI_RED}{line}{ANSI_RESET}")
+            } else {
+                line
+            });
+        });
+        lines.join("\n")
+    }
+}
@@ -3,8 +3,15 @@ mod process_map;
 mod span;
 mod trace;
 mod unify_operation;
+mod virtual_nodes;
+
+#[cfg(test)]
+pub(crate) mod test_traces;
+#[cfg(test)]
+pub(crate) use self::span::Position;
 
 pub use self::{
-    span::{Span, Spans},
+    span::{GapCall, Span, Spans},
     trace::{extract_traces, Trace},
+    virtual_nodes::{get_peer_name, is_inferred_service, set_infer_virtual_nodes},
 };
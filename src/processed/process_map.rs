@@ -16,6 +16,8 @@ pub struct Process {
     pub telemetry_sdk_language: String,
     pub telemetry_sdk_name: String,
     pub telemetry_sdk_version: String,
+    /// the remaining (unknown) tags of the process
+    pub other_tags: HashMap<String, String>,
 }
 
 impl Process {
@@ -48,10 +50,13 @@ impl Process {
                         "telemetry.sdk.language" => self.telemetry_sdk_language = val,
                         "telemetry.sdk.name" => self.telemetry_sdk_name = val,
                         "telemetry.sdk.version" => self.telemetry_sdk_version = val,
-                        _ => utils::report(
-                            Chapter::Issues,
-                            format!("Ignored Unknown key {key}='{val}' for process {proc_key}"),
-                        ),
+                        _ => {
+                            utils::report(
+                                Chapter::Issues,
+                                format!("Unknown key {key}='{val}' for process {proc_key}"),
+                            );
+                            self.other_tags.insert(key.to_owned(), val);
+                        }
                     }
                 }
             }
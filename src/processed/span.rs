@@ -2,6 +2,7 @@
 use super::{
     process_map::{build_process_map, Process, ProcessMap},
     unify_operation::unified_operation_name,
+    virtual_nodes::add_virtual_spans,
 };
 use crate::{
     micros_to_datetime,
@@ -15,6 +16,9 @@ use std::{collections::HashMap, iter, sync::Mutex};
 
 static MAX_LOG_MSG_LENGTH: Mutex<usize> = Mutex::new(100);
 
+/// attribute of the spans that are inserted by the repair of a gap in a call-chain
+const GAP_REPAIR_TAG: &str = "gap_repair";
+
 pub fn set_max_log_msg_length(val: usize) {
     let mut guard = MAX_LOG_MSG_LENGTH.lock().unwrap();
     *guard = val
@@ -40,6 +44,7 @@ pub struct Span {
     pub position: Position,
     pub is_leaf: bool,
     pub rooted: bool, // does this span trace back to the real root? (default = false)
+    pub follows_from: bool, // the reference to the parent is a FOLLOWS_FROM reference, so the parent does not wait for this span
     pub span_id: String,
     pub operation_name: String,
     pub full_operation_name: Option<String>,
@@ -152,6 +157,11 @@ impl Span {
         }
     }
 
+    /// true if this span has been inserted by the repair of a gap in the call-chain (see join_orphans), so it was not observed
+    pub fn is_gap_repair(&self) -> bool {
+        self.attributes.contains_key(GAP_REPAIR_TAG)
+    }
+
     // //. get_process_name returns the name of the process of this span (without the operation (method) that is called)
     // pub fn get_process_name(&self) -> String {
     //     self.get_process_str().to_owned()
@@ -164,6 +174,14 @@ pub struct Spans {
     pub root_idx: Option<usize>,
 }
 
+/// A call that is missing in a trace and is inserted as a span to close a gap between an orphan span and its ancestor
+#[derive(Debug, Clone)]
+pub struct GapCall {
+    pub service: String,
+    pub operation: String,
+    pub span_kind: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct Log {
     pub timestamp: i64,
@@ -185,6 +203,7 @@ fn add_parents(spans: &mut Vec<Span>, jspans: &Vec<JaegerSpan>) -> Vec<String> {
                 for (idx, js) in jspans.iter().enumerate() {
                     if js.spanID[..] == parentID[..] {
                         span.position = Position::Parent(idx);
+                        span.follows_from = jspan.references[0].refType == "FOLLOWS_FROM";
                         parent_found = true;
                         break;
                     }
@@ -292,9 +311,119 @@ impl Spans {
 
         spans.mark_rooted();
 
+        add_virtual_spans(&mut spans);
+
         (spans, missing_span_ids)
     }
 
+    /// The orphan spans (spans with a missing parent) combined with the span-id of their missing parent.
+    /// The 'missing_span_ids' are collected by 'add_parents' in span-order, so the n-th id belongs to the n-th orphan.
+    pub fn orphans<'a>(&self, missing_span_ids: &'a [String]) -> Vec<(usize, &'a str)> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter(|(_, span)| matches!(span.position, Position::MissingParent))
+            .map(|(idx, _)| idx)
+            .zip(missing_span_ids.iter().map(|id| &id[..]))
+            .collect()
+    }
+
+    /// Join the 'orphans' to the span at 'parent_idx' via a chain of inserted spans for the 'missing' calls (ordered top-down).
+    /// The lowest inserted span gets the id of the missing span, and the inserted spans cover the time-range of the orphans.
+    /// Afterwards the leaf and rooted markers of all spans are recomputed.
+    pub fn join_orphans(
+        &mut self,
+        orphans: &[usize],
+        parent_idx: usize,
+        missing: Vec<GapCall>,
+        missing_span_id: &str,
+    ) {
+        let start_micros = orphans
+            .iter()
+            .map(|idx| self.items[*idx].start_dt.and_utc().timestamp_micros())
+            .min()
+            .unwrap_or_default();
+        let end_micros = orphans
+            .iter()
+            .map(|idx| {
+                let span = &self.items[*idx];
+                span.start_dt.and_utc().timestamp_micros() + span.duration_micros
+            })
+            .max()
+            .unwrap_or_default();
+        let num_missing = missing.len();
+        let mut parent = parent_idx;
+        missing.into_iter().enumerate().for_each(|(i, gc)| {
+            let span_id = if i + 1 == num_missing {
+                missing_span_id.to_owned()
+            } else {
+                format!("{missing_span_id}-gap-{i}")
+            };
+            self.items.push(Span {
+                process: Some(Process {
+                    name: gc.service,
+                    ..Default::default()
+                }),
+                position: Position::Parent(parent),
+                is_leaf: false,
+                rooted: false,
+                follows_from: false,
+                span_id,
+                operation_name: gc.operation,
+                full_operation_name: None,
+                start_dt: micros_to_datetime(start_micros),
+                duration_micros: end_micros - start_micros,
+                span_kind: gc.span_kind,
+                http_status_code: None,
+                attributes: [(GAP_REPAIR_TAG.to_owned(), "true".to_owned())].into(),
+                logs: Vec::new(),
+            });
+            parent = self.items.len() - 1;
+        });
+        orphans
+            .iter()
+            .for_each(|idx| self.items[*idx].position = Position::Parent(parent));
+
+        self.items.iter_mut().for_each(|span| span.rooted = false);
+        self.mark_leafs();
+        self.mark_rooted();
+    }
+
+    /// get_children returns for each span the indices of its direct children (in span-order)
+    pub fn get_children(&self) -> Vec<Vec<usize>> {
+        let mut children = vec![Vec::new(); self.items.len()];
+        self.items
+            .iter()
+            .enumerate()
+            .for_each(|(idx, span)| match span.position {
+                Position::Root | Position::MissingParent => (),
+                Position::Parent(par) => children[par].push(idx),
+            });
+        children
+    }
+
+    /// get the children of each span ordered by their start-time
+    pub fn get_ordered_children(&self) -> Vec<Vec<usize>> {
+        let mut children = self.get_children();
+        children
+            .iter_mut()
+            .for_each(|ch| ch.sort_by_key(|idx| self.items[*idx].start_dt));
+        children
+    }
+
+    /// get the top-level spans, which are the root and the spans with a missing parent, ordered by their start-time
+    pub fn get_top_spans(&self) -> Vec<usize> {
+        let mut tops: Vec<_> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, span)| !matches!(span.position, Position::Parent(_)))
+            .map(|(idx, _)| idx)
+            .collect();
+        tops.sort_by_key(|idx| self.items[*idx].start_dt);
+        tops
+    }
+
     /// chain_apply_forward is used to run over a call-chain and apply the 'process' to each span in order to get a Vec<T>
     pub fn chain_apply_forward<T>(&self, idx: usize, process: &dyn Fn(&Span) -> T) -> Vec<T> {
         //        let chain_apply_forward_aux = |
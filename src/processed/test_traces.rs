@@ -0,0 +1,118 @@
+//! Build small Jaeger traces for the unit-tests, such that the tests do not depend on trace-files.
+use super::Trace;
+use crate::raw::{JaegerTrace, FILE_TRACKER};
+use serde_json::{json, Value};
+
+/// start-time of all test-traces (2023-11-14 22:13:20 UTC)
+pub const START_MICROS: i64 = 1_700_000_000_000_000;
+
+/// A span of a test-trace. Spans are server-spans with HTTP status 200 unless tags are added.
+pub struct TestSpan {
+    span_id: &'static str,
+    parent: Option<&'static str>,
+    service: &'static str,
+    operation: &'static str,
+    offset_micros: i64,
+    duration_micros: i64,
+    tags: Vec<(&'static str, Value)>,
+}
+
+impl TestSpan {
+    pub fn new(
+        span_id: &'static str,
+        parent: Option<&'static str>,
+        service_oper: &'static str,
+        offset_micros: i64,
+        duration_micros: i64,
+    ) -> Self {
+        let (service, operation) = service_oper.split_once('/').unwrap();
+        Self {
+            span_id,
+            parent,
+            service,
+            operation,
+            offset_micros,
+            duration_micros,
+            tags: vec![
+                ("span.kind", json!("server")),
+                ("http.status_code", json!(200)),
+            ],
+        }
+    }
+
+    /// add a tag, or replace the value of an existing tag
+    pub fn tag(mut self, key: &'static str, value: Value) -> Self {
+        self.tags.retain(|(k, _)| *k != key);
+        self.tags.push((key, value));
+        self
+    }
+
+    fn to_json(&self, trace_id: &str, processes: &[&str]) -> Value {
+        let process_idx = processes.iter().position(|s| *s == self.service).unwrap();
+        let references: Vec<_> = self
+            .parent
+            .iter()
+            .map(|parent| json!({"refType": "CHILD_OF", "traceID": trace_id, "spanID": parent}))
+            .collect();
+        let tags: Vec<_> = self
+            .tags
+            .iter()
+            .map(|(key, value)| {
+                let type_id = if value.is_i64() { "int64" } else { "string" };
+                json!({"key": key, "type": type_id, "value": value})
+            })
+            .collect();
+        json!({
+            "traceID": trace_id,
+            "spanID": self.span_id,
+            "flags": 1,
+            "operationName": self.operation,
+            "references": references,
+            "startTime": START_MICROS + self.offset_micros,
+            "duration": self.duration_micros,
+            "tags": tags,
+            "logs": [],
+            "processID": format!("p{process_idx}"),
+            "warnings": null
+        })
+    }
+}
+
+/// Build a JaegerTrace that contains a single trace with the spans
+pub fn jaeger_trace(trace_id: &str, spans: &[TestSpan]) -> JaegerTrace {
+    let mut services: Vec<&str> = Vec::new();
+    spans.iter().for_each(|span| {
+        if !services.contains(&span.service) {
+            services.push(span.service)
+        }
+    });
+    let processes: serde_json::Map<_, _> = services
+        .iter()
+        .enumerate()
+        .map(|(idx, service)| {
+            (
+                format!("p{idx}"),
+                json!({"serviceName": service, "tags": []}),
+            )
+        })
+        .collect();
+    let spans: Vec<_> = spans
+        .iter()
+        .map(|span| span.to_json(trace_id, &services))
+        .collect();
+    serde_json::from_value(json!({
+        "data": [{"traceID": trace_id, "spans": spans, "processes": processes, "warnings": null}],
+        "total": 0, "limit": 0, "offset": 0, "errors": null
+    }))
+    .unwrap()
+}
+
+/// Build the processed trace with the spans. Each trace gets its own (virtual) source-file.
+pub fn build_trace(trace_id: &str, spans: &[TestSpan]) -> Trace {
+    let source_file_id = {
+        let mut tracker = FILE_TRACKER.lock().unwrap();
+        tracker.add_file(format!("{trace_id}.json"));
+        tracker.get_last_idx()
+    };
+    Trace::new(&jaeger_trace(trace_id, spans), 0, source_file_id)
+}
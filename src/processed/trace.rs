@@ -49,6 +49,16 @@ impl Trace {
         }
     }
 
+    /// Find the trace with 'trace_id' in a set of raw Jaeger traces and build it (None if the trace is not found)
+    pub fn find_in_jaeger(jaeger_traces: &[JaegerTrace], trace_id: &str) -> Option<Self> {
+        jaeger_traces.iter().find_map(|jt| {
+            jt.data
+                .iter()
+                .position(|item| item.traceID == trace_id)
+                .map(|idx| Trace::new(jt, idx, 0))
+        })
+    }
+
     /// get the nane of this trace as a CSV-file
     pub fn base_name(&self, folder: &Path) -> OsString {
         let mut folder = folder.to_path_buf();
@@ -0,0 +1,209 @@
+//! Inference of virtual nodes for uninstrumented dependencies, such as databases and external API's.
+//! A leaf client-span ends the call-chain at the caller. When the tags of such a span identify the peer that is called a virtual (inferred) span
+//! is added as a child, such that the dependency shows up in the call-chains, statistics and diagrams. Inferred services are marked with INFERRED_MARKER.
+use super::{
+    process_map::Process,
+    span::{Position, Span, Spans},
+};
+use std::sync::Mutex;
+
+static INFER_VIRTUAL_NODES: Mutex<bool> = Mutex::new(false);
+
+/// Enable (or disable) the inference of virtual nodes for uninstrumented dependencies.
+pub fn set_infer_virtual_nodes(val: bool) {
+    let mut guard = INFER_VIRTUAL_NODES.lock().unwrap();
+    *guard = val
+}
+
+/// The marker appended to the name of an inferred service.
+/// It should not contain any of the separators used in call-chain keys ('/', '|', '&' and '[').
+pub const INFERRED_MARKER: &str = " (inferred)";
+
+/// Characters that can not be part of a service-name as these are used as separators in the call-chain keys.
+const INVALID_CHARS: [char; 5] = ['/', '|', '&', '[', ']'];
+
+/// The network tags that can identify the peer (downstream service) of a client span, in order of preference.
+const NET_PEER_TAGS: [&str; 2] = ["net.peer.name", "server.address"];
+/// The span tags that contain an url from which the host can be derived.
+const URL_TAGS: [&str; 2] = ["http.url", "url.full"];
+
+/// returns true if the service (or service/operation) is inferred.
+pub fn is_inferred_service(service: &str) -> bool {
+    service.contains(INFERRED_MARKER)
+}
+
+/// extract the host from an url such as 'http://user@host:8080/path'
+fn url_host(url: &str) -> Option<&str> {
+    let rest = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let host = rest.split(['/', '?', '#']).next()?;
+    let host = host.rsplit_once('@').map(|(_, h)| h).unwrap_or(host);
+    let host = host.split(':').next()?;
+    if host.is_empty() {
+        None
+    } else {
+        Some(host)
+    }
+}
+
+/// Get the name of the peer that is called by a client-span based on the tags of the span, using 'peer.service', 'db.system'/'db.type' + 'db.instance', 'net.peer.name' or the host of 'http.url' (in that order).
+pub fn get_peer_name(span: &Span) -> Option<String> {
+    let attr = |key: &str| span.attributes.get(key).filter(|v| !v.is_empty());
+    let db_peer = || {
+        let system = attr("db.system").or_else(|| attr("db.type"));
+        match (system, attr("db.instance")) {
+            (Some(system), Some(instance)) => Some(format!("{system}-{instance}")),
+            (Some(system), None) => Some(system.to_owned()),
+            (None, Some(instance)) => Some(instance.to_owned()),
+            (None, None) => None,
+        }
+    };
+    attr("peer.service")
+        .cloned()
+        .or_else(db_peer)
+        .or_else(|| NET_PEER_TAGS.iter().find_map(|tag| attr(tag).cloned()))
+        .or_else(|| {
+            URL_TAGS.iter().find_map(|tag| {
+                attr(tag)
+                    .and_then(|url| url_host(url))
+                    .map(|h| h.to_owned())
+            })
+        })
+        .map(|peer| peer.replace(INVALID_CHARS, "_"))
+}
+
+/// Construct the inferred span for the leaf client-span at 'idx'
+fn inferred_span(idx: usize, client: &Span, peer: String) -> Span {
+    let process = Process {
+        name: format!("{peer}{INFERRED_MARKER}"),
+        ..Default::default()
+    };
+    let operation_name = client
+        .attributes
+        .get("db.operation")
+        .cloned()
+        .unwrap_or_else(|| client.operation_name.clone());
+    Span {
+        process: Some(process),
+        position: Position::Parent(idx),
+        is_leaf: true,
+        rooted: client.rooted,
+        follows_from: false,
+        span_id: format!("{}-inferred", client.span_id),
+        operation_name,
+        full_operation_name: None,
+        start_dt: client.start_dt,
+        duration_micros: client.duration_micros,
+        span_kind: Some("server".to_owned()),
+        http_status_code: client.http_status_code,
+        attributes: [("inferred".to_owned(), "true".to_owned())].into(),
+        logs: Vec::new(),
+    }
+}
+
+/// Add inferred spans for all leaf client-spans that refer to an identifiable peer (only when enabled via set_infer_virtual_nodes).
+pub fn add_virtual_spans(spans: &mut Spans) {
+    if *INFER_VIRTUAL_NODES.lock().unwrap() {
+        insert_virtual_spans(spans)
+    }
+}
+
+/// Add an inferred span as child of each leaf client-span that refers to an identifiable peer.
+fn insert_virtual_spans(spans: &mut Spans) {
+    let inferred: Vec<_> = spans
+        .items
+        .iter()
+        .enumerate()
+        .filter(|(_, span)| {
+            span.is_leaf && matches!(span.span_kind.as_deref(), Some("client") | Some("producer"))
+        })
+        .filter_map(|(idx, span)| get_peer_name(span).map(|peer| inferred_span(idx, span, peer)))
+        .collect();
+    inferred.into_iter().for_each(|span| {
+        if let Position::Parent(parent) = span.position {
+            spans.items[parent].is_leaf = false;
+        }
+        spans.items.push(span);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_peer_name, insert_virtual_spans, url_host, INFERRED_MARKER};
+    use crate::processed::{Position, Span, Spans};
+
+    fn span(span_kind: &str, attributes: &[(&str, &str)]) -> Span {
+        Span {
+            span_id: "s1".to_owned(),
+            operation_name: "call".to_owned(),
+            is_leaf: true,
+            rooted: true,
+            span_kind: Some(span_kind.to_owned()),
+            attributes: attributes
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn host_of_url() {
+        assert_eq!(url_host("http://user@host:8080/path?q=1"), Some("host"));
+        assert_eq!(
+            url_host("https://api.example.com#top"),
+            Some("api.example.com")
+        );
+        assert_eq!(url_host("host/path"), Some("host"));
+        assert_eq!(url_host("http:///path"), None);
+    }
+
+    #[test]
+    fn peer_name_from_tags() {
+        let peer = |attributes: &[(&str, &str)]| get_peer_name(&span("client", attributes));
+        assert_eq!(
+            peer(&[("peer.service", "billing"), ("db.system", "redis")]),
+            Some("billing".to_owned())
+        );
+        // database peers are named 'system-instance'
+        assert_eq!(
+            peer(&[("db.system", "postgresql"), ("db.instance", "orders")]),
+            Some("postgresql-orders".to_owned())
+        );
+        assert_eq!(peer(&[("db.type", "sql")]), Some("sql".to_owned()));
+        assert_eq!(
+            peer(&[("server.address", "mq"), ("http.url", "http://api:80/x")]),
+            Some("mq".to_owned())
+        );
+        // separators of the call-chain keys are replaced
+        assert_eq!(
+            peer(&[("url.full", "https://a|b/x")]),
+            Some("a_b".to_owned())
+        );
+        assert_eq!(peer(&[("peer.service", "")]), None);
+    }
+
+    #[test]
+    fn virtual_span_below_leaf_client() {
+        let mut spans = Spans {
+            items: vec![
+                span("client", &[("db.system", "redis"), ("db.operation", "GET")]),
+                span("server", &[("peer.service", "billing")]),
+                span("client", &[]),
+            ],
+            root_idx: Some(0),
+        };
+        insert_virtual_spans(&mut spans);
+
+        assert_eq!(spans.items.len(), 4);
+        assert!(!spans.items[0].is_leaf);
+        assert!(spans.items[1].is_leaf && spans.items[2].is_leaf);
+        let inferred = &spans.items[3];
+        assert!(matches!(inferred.position, Position::Parent(0)));
+        assert_eq!(
+            inferred.process.as_ref().unwrap().name,
+            format!("redis{INFERRED_MARKER}")
+        );
+        assert_eq!(inferred.operation_name, "GET");
+        assert!(inferred.is_leaf && inferred.rooted);
+    }
+}
@@ -20,7 +20,6 @@ impl FileTracker {
         self.files.len() - 1
     }
 
-    #[allow(dead_code)]
     pub fn get_file_name(&self, idx: usize) -> String {
         assert!(idx < self.files.len(), "Index out of bounds");
         self.files[idx].to_owned()
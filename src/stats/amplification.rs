@@ -0,0 +1,255 @@
+//! Load amplification from the endpoints to the backend Service/Operations, derived from the rooted call-chains.
+//! For each endpoint (the Service/Operation of the root-span) and each Service/Operation that is called (directly or indirectly) by this endpoint,
+//! the matrix contains the average number of calls, the self-time and the number of calls with an HTTP-error per call of the endpoint.
+//! The matrix is used to project the load on the backends for a target mix of endpoint rates (what-if analysis).
+use super::{
+    call_chain::{Call, CallChain},
+    StatsRec,
+};
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    path::Path,
+};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct AmplificationValue {
+    /// average number of calls of the backend per call of the endpoint
+    pub calls_per_request: f64,
+    /// Average self-time in milliseconds of the backend per call of the endpoint. The self-time is the duration minus the duration
+    /// of the direct child-calls (clamped at zero), so it is underestimated when the children are called in parallel.
+    pub self_millis_per_request: f64,
+    /// average number of backend calls per call of the endpoint for which the call-chain (from endpoint to backend) contains an HTTP-error
+    pub errors_per_request: f64,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct AmplificationEntry {
+    pub endpoint: String,
+    /// the backend Service/Operation
+    pub backend: String,
+    pub value: AmplificationValue,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct AmplificationMatrix {
+    /// number of calls (traces) per endpoint in the analyzed data
+    pub endpoint_calls: Vec<(String, usize)>,
+    /// ordered by endpoint and backend
+    pub entries: Vec<AmplificationEntry>,
+}
+
+/// accumulated totals of all call-chains from an endpoint to a backend
+#[derive(Default)]
+struct Totals {
+    calls: usize,
+    self_millis: f64,
+    errors: usize,
+}
+
+impl AmplificationMatrix {
+    /// Derive the matrix from the rooted call-chains of 'stats_rec'
+    pub fn new(stats_rec: &StatsRec) -> Self {
+        let mut endpoint_calls: BTreeMap<String, usize> = BTreeMap::new();
+        stats_rec
+            .root_call
+            .iter()
+            .for_each(|rc| *endpoint_calls.entry(rc.to_owned()).or_default() += 1);
+
+        // total duration per call-chain (the leaf and non-leaf variant of a chain are joined) to compute the self-time
+        let call_chains: Vec<_> = stats_rec
+            .stats
+            .values()
+            .flat_map(|os| os.call_chain.0.iter())
+            .filter(|(cck, ccv)| ccv.rooted && cck.call_chain.len() > 1)
+            .collect();
+        let mut total_millis: HashMap<&CallChain, f64> = HashMap::new();
+        call_chains.iter().for_each(|(cck, ccv)| {
+            *total_millis.entry(&cck.call_chain).or_default() +=
+                ccv.get_avg_millis() * ccv.count as f64
+        });
+        // total duration of the direct children, keyed by the call-chain of the parent
+        let mut child_millis: HashMap<&[Call], f64> = HashMap::new();
+        total_millis.iter().for_each(|(call_chain, millis)| {
+            *child_millis
+                .entry(&call_chain[..call_chain.len() - 1])
+                .or_default() += millis
+        });
+
+        let mut totals: BTreeMap<(String, String), Totals> = BTreeMap::new();
+        call_chains.iter().for_each(|(cck, ccv)| {
+            let endpoint = cck.call_chain[0].get_process_method();
+            let backend = cck.call_chain[cck.call_chain.len() - 1].get_process_method();
+            let tot = totals.entry((endpoint, backend)).or_default();
+            tot.calls += ccv.count;
+            tot.errors += ccv.cc_not_http_ok as usize;
+        });
+        // the self-time is computed per unique call-chain, as the leaf and non-leaf variants share their children
+        total_millis.iter().for_each(|(call_chain, millis)| {
+            let endpoint = call_chain[0].get_process_method();
+            let backend = call_chain[call_chain.len() - 1].get_process_method();
+            if let Some(tot) = totals.get_mut(&(endpoint, backend)) {
+                let children = child_millis.get(&call_chain[..]).unwrap_or(&0.0);
+                tot.self_millis += (millis - children).max(0.0);
+            }
+        });
+
+        let entries = totals
+            .into_iter()
+            .filter_map(|((endpoint, backend), tot)| {
+                let num_calls = *endpoint_calls.get(&endpoint)? as f64;
+                Some(AmplificationEntry {
+                    endpoint,
+                    backend,
+                    value: AmplificationValue {
+                        calls_per_request: tot.calls as f64 / num_calls,
+                        self_millis_per_request: tot.self_millis / num_calls,
+                        errors_per_request: tot.errors as f64 / num_calls,
+                    },
+                })
+            })
+            .collect();
+
+        Self {
+            endpoint_calls: endpoint_calls.into_iter().collect(),
+            entries,
+        }
+    }
+
+    /// Project the request rate per backend Service/Operation for a mix of endpoint rates (requests per second).
+    /// Endpoints that are not present in the matrix are reported as an Err.
+    pub fn project(&self, endpoint_rates: &[(String, f64)]) -> Result<Vec<(String, f64)>, String> {
+        let unknown: Vec<_> = endpoint_rates
+            .iter()
+            .filter(|(ep, _)| !self.endpoint_calls.iter().any(|(k, _)| k == ep))
+            .map(|(ep, _)| ep.as_str())
+            .collect();
+        if !unknown.is_empty() {
+            return Err(format!("Unknown endpoint(s): {}", unknown.join(", ")));
+        }
+        let mut backend_rates: BTreeMap<&str, f64> = BTreeMap::new();
+        endpoint_rates.iter().for_each(|(ep, rate)| {
+            self.entries
+                .iter()
+                .filter(|entry| entry.endpoint == *ep)
+                .for_each(|entry| {
+                    *backend_rates.entry(&entry.backend).or_default() +=
+                        rate * entry.value.calls_per_request
+                })
+        });
+        Ok(backend_rates
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v))
+            .collect())
+    }
+
+    /// header for report_stats_line output in ';'-separated csv-format
+    pub fn report_stats_line_header_str() -> &'static str {
+        "Endpoint; Backend; Endpoint_calls; Calls_per_request; Self_millis_per_request; Errors_per_request"
+    }
+
+    pub fn write_csv(&self, path: &Path) {
+        let mut csv = utils::CsvFileBuffer::new();
+        csv.add_section("Load amplification: calls, self-time and HTTP-errors of each backend Service/Operation per call of the endpoint");
+        csv.add_line(Self::report_stats_line_header_str().to_owned());
+        let endpoint_calls: HashMap<_, _> = self.endpoint_calls.iter().cloned().collect();
+        self.entries.iter().for_each(|entry| {
+            csv.add_line(format!(
+                "{}; {}; {}; {}; {}; {}",
+                entry.endpoint,
+                entry.backend,
+                endpoint_calls.get(&entry.endpoint).unwrap_or(&0),
+                utils::format_float(entry.value.calls_per_request),
+                utils::format_float(entry.value.self_millis_per_request),
+                utils::format_float(entry.value.errors_per_request),
+            ))
+        });
+        csv.write_file(path);
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let json = serde_json::to_string_pretty(self)?;
+        utils::write_string_to_file(path.to_str().expect("invalid file-path"), json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AmplificationMatrix;
+    use crate::{
+        processed::test_traces::{build_trace, TestSpan},
+        stats::{BasicStatsRec, StatsRec},
+    };
+    use serde_json::json;
+
+    /// two calls of 'gw/GET' with in total two calls of 'a/get' and three calls of 'b/get' (of which one fails)
+    fn stats_rec() -> StatsRec {
+        let traces = [
+            build_trace(
+                "amp1",
+                &[
+                    TestSpan::new("r", None, "gw/GET", 0, 10_000),
+                    TestSpan::new("a", Some("r"), "a/get", 1_000, 6_000),
+                    TestSpan::new("b1", Some("a"), "b/get", 2_000, 2_000),
+                    TestSpan::new("b2", Some("a"), "b/get", 4_500, 1_000),
+                ],
+            ),
+            build_trace(
+                "amp2",
+                &[
+                    TestSpan::new("r", None, "gw/GET", 0, 10_000),
+                    TestSpan::new("a", Some("r"), "a/get", 1_000, 4_000),
+                    TestSpan::new("b", Some("a"), "b/get", 2_000, 3_000)
+                        .tag("http.status_code", json!(500)),
+                ],
+            ),
+        ];
+        let mut sr = StatsRec::new(BasicStatsRec::default());
+        traces
+            .iter()
+            .for_each(|trace| sr.extend_statistics(trace, false));
+        sr
+    }
+
+    #[test]
+    fn amplification_per_request() {
+        let matrix = AmplificationMatrix::new(&stats_rec());
+        assert_eq!(matrix.endpoint_calls, vec![("gw/GET".to_owned(), 2)]);
+        let entries: Vec<_> = matrix
+            .entries
+            .iter()
+            .map(|e| (e.endpoint.as_str(), e.backend.as_str(), &e.value))
+            .collect();
+        assert_eq!(entries.len(), 2);
+
+        let (_, backend, a) = entries[0];
+        assert_eq!(backend, "a/get");
+        assert_eq!(a.calls_per_request, 1.0);
+        // 10 ms in 'a/get' of which 6 ms is spent in 'b/get'
+        assert!((a.self_millis_per_request - 2.0).abs() < 1e-9);
+        assert_eq!(a.errors_per_request, 0.0);
+
+        let (_, backend, b) = entries[1];
+        assert_eq!(backend, "b/get");
+        assert_eq!(b.calls_per_request, 1.5);
+        assert!((b.self_millis_per_request - 3.0).abs() < 1e-9);
+        assert_eq!(b.errors_per_request, 0.5);
+    }
+
+    #[test]
+    fn project_endpoint_rates() {
+        let matrix = AmplificationMatrix::new(&stats_rec());
+        let rates = matrix.project(&[("gw/GET".to_owned(), 10.0)]).unwrap();
+        assert_eq!(
+            rates,
+            vec![("a/get".to_owned(), 10.0), ("b/get".to_owned(), 15.0)]
+        );
+
+        let err = matrix
+            .project(&[("gw/GET".to_owned(), 10.0), ("gw/POST".to_owned(), 1.0)])
+            .unwrap_err();
+        assert_eq!(err, "Unknown endpoint(s): gw/POST");
+    }
+}
@@ -0,0 +1,185 @@
+//! Cache hit-ratios of the caching processes (the processes passed via '--caching-process').
+//! A call of a caching process that does not make any downstream calls (a '*LEAF*' call-chain) is counted as a cache-hit,
+//! and a call with downstream calls is counted as a miss. The statistics are computed per call-chain that ends in the caching process,
+//! such that the hit-ratio is available per path towards the cache.
+use super::{
+    call_chain::{CallChain, CallDirection},
+    StatsRec,
+};
+use crate::utils;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Default, Clone)]
+pub struct CacheStatsValue {
+    /// number of calls that did not result in downstream calls
+    pub hits: usize,
+    /// number of calls that resulted in downstream calls
+    pub misses: usize,
+    /// number of downstream calls (all spans below the caching process) caused by the misses
+    pub downstream_calls: usize,
+}
+
+impl CacheStatsValue {
+    pub fn count(&self) -> usize {
+        self.hits + self.misses
+    }
+
+    pub fn hit_ratio(&self) -> Option<f64> {
+        let count = self.count();
+        (count > 0).then(|| self.hits as f64 / count as f64)
+    }
+
+    /// average number of downstream calls per cache-miss
+    pub fn downstream_per_miss(&self) -> Option<f64> {
+        (self.misses > 0).then(|| self.downstream_calls as f64 / self.misses as f64)
+    }
+
+    /// Estimated number of downstream calls when the cache is flushed, such that each call is a miss.
+    /// None when there are no misses, as the downstream load per miss is unknown in that case.
+    pub fn flushed_downstream_calls(&self) -> Option<f64> {
+        self.downstream_per_miss()
+            .map(|per_miss| per_miss * self.count() as f64)
+    }
+
+    /// header for report_stats_line output in ';'-separated csv-format
+    pub fn report_stats_line_header_str() -> &'static str {
+        "Call_chain; Count; Hits; Misses; Hit_ratio; Downstream_calls; Downstream_per_miss; Flushed_downstream_calls"
+    }
+
+    /// reports the statistics for a single line in ';'-separated csv-format
+    pub fn report_stats_line(&self, key: &str) -> String {
+        format!(
+            "{key}; {}; {}; {}; {}; {}; {}; {}",
+            self.count(),
+            self.hits,
+            self.misses,
+            utils::format_float_opt(self.hit_ratio()),
+            self.downstream_calls,
+            utils::format_float_opt(self.downstream_per_miss()),
+            utils::format_float_opt(self.flushed_downstream_calls()),
+        )
+    }
+}
+
+/// the key of a (partial) call-chain, being the calls separated by ' | ' (without the caching and leaf labels)
+fn call_chain_prefix_key(call_chain: &CallChain, len: usize) -> String {
+    call_chain[..len]
+        .iter()
+        .map(|call| call.to_string())
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// Cache statistics keyed by the call-chain that ends in the caching process.
+#[derive(Debug, Default, Clone)]
+pub struct CacheStats(pub BTreeMap<String, CacheStatsValue>);
+
+impl CacheStats {
+    /// Derive the cache statistics from the call-chains of 'stats_rec'. Returns an empty set when no caching processes are defined.
+    pub fn new(stats_rec: &StatsRec) -> Self {
+        let caching = &stats_rec.caching_processes;
+        let mut cache_stats = BTreeMap::new();
+        if caching.is_empty() {
+            return Self(cache_stats);
+        }
+        let call_chains: Vec<_> = stats_rec
+            .stats
+            .values()
+            .flat_map(|os| os.call_chain.0.iter())
+            .collect();
+
+        // the inbound calls of a caching process are the cache-lookups (outbound calls of this process are the downstream calls)
+        call_chains.iter().for_each(|(cck, ccv)| {
+            let Some(last) = cck.call_chain.last() else {
+                return;
+            };
+            if last.call_direction != CallDirection::Outbound && caching.contains(&last.service) {
+                let csv: &mut CacheStatsValue = cache_stats
+                    .entry(call_chain_prefix_key(&cck.call_chain, cck.call_chain.len()))
+                    .or_default();
+                if cck.is_leaf {
+                    csv.hits += ccv.count
+                } else {
+                    csv.misses += ccv.count
+                }
+            }
+        });
+
+        // each call-chain that extends a cache-lookup is a downstream call of this lookup
+        let mut downstream: HashMap<String, usize> = HashMap::new();
+        call_chains.iter().for_each(|(cck, ccv)| {
+            (1..cck.call_chain.len()).for_each(|len| {
+                let key = call_chain_prefix_key(&cck.call_chain, len);
+                if cache_stats.contains_key(&key) {
+                    *downstream.entry(key).or_default() += ccv.count;
+                }
+            })
+        });
+        downstream.into_iter().for_each(|(key, cnt)| {
+            if let Some(csv) = cache_stats.get_mut(&key) {
+                csv.downstream_calls = cnt
+            }
+        });
+
+        Self(cache_stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CacheStats;
+    use crate::{
+        processed::test_traces::{build_trace, TestSpan},
+        stats::{BasicStatsRec, StatsRec},
+    };
+
+    #[test]
+    fn hits_misses_and_downstream_calls() {
+        // two lookups are served by the cache, two lookups miss and result in one and two downstream calls
+        let hit = |trace_id| {
+            build_trace(
+                trace_id,
+                &[
+                    TestSpan::new("s1", None, "gw/GET", 0, 10_000),
+                    TestSpan::new("s2", Some("s1"), "cache/get", 1_000, 1_000),
+                ],
+            )
+        };
+        let miss = build_trace(
+            "t3",
+            &[
+                TestSpan::new("s1", None, "gw/GET", 0, 10_000),
+                TestSpan::new("s2", Some("s1"), "cache/get", 1_000, 8_000),
+                TestSpan::new("s3", Some("s2"), "db/query", 2_000, 5_000),
+            ],
+        );
+        let double_miss = build_trace(
+            "t4",
+            &[
+                TestSpan::new("s1", None, "gw/GET", 0, 10_000),
+                TestSpan::new("s2", Some("s1"), "cache/get", 1_000, 8_000),
+                TestSpan::new("s3", Some("s2"), "db/query", 2_000, 2_000),
+                TestSpan::new("s4", Some("s2"), "db/count", 5_000, 2_000),
+            ],
+        );
+        let mut sr = StatsRec::new(BasicStatsRec {
+            caching_processes: vec!["cache".to_string()],
+            ..Default::default()
+        });
+        [hit("t1"), hit("t2"), miss, double_miss]
+            .iter()
+            .for_each(|trace| sr.extend_statistics(trace, false));
+
+        let cache_stats = CacheStats::new(&sr);
+        assert_eq!(cache_stats.0.len(), 1);
+        let csv = &cache_stats.0["gw/GET [Inbound] | cache/get [Inbound]"];
+        assert_eq!((csv.hits, csv.misses, csv.downstream_calls), (2, 2, 3));
+        assert_eq!(csv.hit_ratio(), Some(0.5));
+        assert_eq!(csv.downstream_per_miss(), Some(1.5));
+        assert_eq!(csv.flushed_downstream_calls(), Some(6.0));
+
+        // without caching processes there are no cache statistics
+        sr.caching_processes.clear();
+        assert!(CacheStats::new(&sr).0.is_empty());
+    }
+}
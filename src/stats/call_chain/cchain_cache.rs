@@ -1,62 +1,257 @@
 use super::{
-    cchain_stats::CChainStatsKey,
-    file::{cchain_filename, read_cchain_file},
+    cchain_stats::{CChainStatsKey, CChainStatsValue},
+    file::{
+        find_cchain_file, read_cchain_file, write_cchain_file_locked, CCHAIN_EXT, PLAIN_CCHAIN_EXT,
+    },
 };
-use crate::utils;
+use crate::utils::{self, Chapter};
+use chrono::{DateTime, Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fs, mem,
+    path::{Path, PathBuf},
+};
+
+/// Metadata of a call-chain in the call-chain cache.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ChainInfo {
+    /// number of observations of this call-chain (summed over the source datasets)
+    pub count: usize,
+    pub first_seen: Option<NaiveDateTime>,
+    pub last_seen: Option<NaiveDateTime>,
+    /// the number of observations per source dataset (data-folder). A re-analysis of the same dataset keeps the largest of its counts (see 'merge').
+    #[serde(default)]
+    pub sources: BTreeMap<String, usize>,
+}
+
+impl ChainInfo {
+    /// the metadata of a call-chain observed in a statistics-value (without a source)
+    pub fn observed(ccv: &CChainStatsValue) -> Self {
+        // for compact values the start of the first and last time-bucket are used
+        let (first_micros, last_micros) = match &ccv.start_dt_buckets {
+            Some(tb) if ccv.start_dt_micros.is_empty() => (tb.first_micros(), tb.last_micros()),
+            _ => (
+                ccv.start_dt_micros.iter().min().copied(),
+                ccv.start_dt_micros.iter().max().copied(),
+            ),
+        };
+        let to_dt = |micros: Option<i64>| {
+            micros.and_then(|m| DateTime::from_timestamp_micros(m).map(|dt| dt.naive_utc()))
+        };
+        Self {
+            count: ccv.count,
+            first_seen: to_dt(first_micros),
+            last_seen: to_dt(last_micros),
+            sources: BTreeMap::new(),
+        }
+    }
 
-use std::{collections::HashMap, mem, path::PathBuf};
+    /// attribute the count of this call-chain to the source dataset
+    fn with_source(mut self, source: &str) -> Self {
+        if !source.is_empty() {
+            self.sources.insert(source.to_owned(), self.count);
+        }
+        self
+    }
+
+    /// Merge the metadata of 'other' into self. For a source that is present in both, the largest count is kept such that
+    /// re-analyzing or re-merging the same dataset does not inflate the counts, and merging is idempotent.
+    pub fn merge(&mut self, other: &ChainInfo) {
+        if self.sources.is_empty() && other.sources.is_empty() {
+            self.count = self.count.max(other.count);
+        } else {
+            other.sources.iter().for_each(|(source, count)| {
+                let curr = self.sources.entry(source.clone()).or_default();
+                *curr = (*curr).max(*count);
+            });
+            self.count = self.sources.values().sum();
+        }
+        self.first_seen = match (self.first_seen, other.first_seen) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.last_seen = self.last_seen.max(other.last_seen);
+    }
+
+    /// Call-chains from a plain cchain-file have no metadata
+    pub fn has_metadata(&self) -> bool {
+        self.last_seen.is_some() || !self.sources.is_empty()
+    }
+}
+
+/// Policy to prune call-chains from the cache, for example chains of services that have been decommissioned.
+/// Call-chains without metadata (read from plain cchain-files) are never pruned.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PrunePolicy {
+    /// remove call-chains that have not been seen during this number of days before the reference date
+    pub max_age_days: Option<i64>,
+    /// remove call-chains that have been observed less than this number of times
+    pub min_count: Option<usize>,
+}
+
+impl PrunePolicy {
+    fn keep(&self, info: &ChainInfo, reference: NaiveDateTime) -> bool {
+        if !info.has_metadata() {
+            return true;
+        }
+        let too_old = match (self.max_age_days, info.last_seen) {
+            (Some(days), Some(last_seen)) => last_seen < reference - Duration::days(days),
+            _ => false,
+        };
+        let too_rare = self.min_count.is_some_and(|min| info.count < min);
+        !too_old && !too_rare
+    }
+}
 
 /// An end-point has a set of call-chains that originate from this endpoint (each represented by a CChainStatsKey)
-//pub type EndPointCChain = Vec<CChainStatsKey>;
 pub struct EndPointCChains {
     dirty: bool,
     pub chains: Vec<CChainStatsKey>,
+    info: HashMap<CChainStatsKey, ChainInfo>,
 }
 
 impl EndPointCChains {
+    /// create an entry for call-chains without metadata (plain cchain-file)
     pub fn new(chains: Vec<CChainStatsKey>) -> Self {
-        let dirty = false;
-        Self { dirty, chains }
+        Self::with_info(
+            chains
+                .into_iter()
+                .map(|cck| (cck, ChainInfo::default()))
+                .collect(),
+        )
     }
 
-    pub fn new_dirty(chains: Vec<CChainStatsKey>) -> Option<Self> {
-        let dirty = true;
-        Some(Self { dirty, chains })
+    /// create an entry for call-chains with their metadata. Duplicate call-chains are merged.
+    pub fn with_info(chains: Vec<(CChainStatsKey, ChainInfo)>) -> Self {
+        let mut epcc = Self {
+            dirty: false,
+            chains: Vec::new(),
+            info: HashMap::new(),
+        };
+        epcc.update_chains(chains);
+        epcc.dirty = false;
+        epcc
     }
 
-    /// Update the entry with the provided chains. If updates are needed set dirty-flag to false
-    pub fn update_chains(&mut self, check_chains: Vec<CChainStatsKey>) {
-        let new_entries: Vec<_> = check_chains
+    pub fn new_dirty(chains: Vec<(CChainStatsKey, ChainInfo)>) -> Option<Self> {
+        let mut epcc = Self::with_info(chains);
+        epcc.dirty = true;
+        Some(epcc)
+    }
+
+    /// Update the entry with the provided chains. New chains are added and the metadata of known chains is merged.
+    /// If updates are needed the dirty-flag is set.
+    pub fn update_chains(&mut self, check_chains: Vec<(CChainStatsKey, ChainInfo)>) {
+        check_chains
             .into_iter()
-            .filter(|check_key| self.chains.iter().any(|curr_key| *curr_key == *check_key))
-            .collect();
-        if !new_entries.is_empty() {
-            self.chains.extend(new_entries);
+            .for_each(|(cck, info)| match self.info.get_mut(&cck) {
+                Some(curr) => {
+                    let orig = curr.clone();
+                    curr.merge(&info);
+                    if *curr != orig {
+                        self.dirty = true;
+                    }
+                }
+                None => {
+                    self.chains.push(cck.clone());
+                    self.info.insert(cck, info);
+                    self.dirty = true;
+                }
+            });
+    }
+
+    /// merge the call-chains of another entry (for example of another cache-folder) into this entry
+    pub fn merge(&mut self, other: &EndPointCChains) {
+        self.update_chains(
+            other
+                .entries()
+                .map(|(cck, info)| (cck.clone(), info.clone()))
+                .collect(),
+        )
+    }
+
+    /// Remove the call-chains that should not be kept according to the policy and return the number of removed chains.
+    /// The age of a call-chain is determined relative to the 'reference' date.
+    pub fn prune(&mut self, policy: &PrunePolicy, reference: NaiveDateTime) -> usize {
+        let info = &self.info;
+        let (keep, remove): (Vec<_>, Vec<_>) = mem::take(&mut self.chains)
+            .into_iter()
+            .partition(|cck| policy.keep(&info[cck], reference));
+        remove.iter().for_each(|cck| _ = self.info.remove(cck));
+        self.chains = keep;
+        if !remove.is_empty() {
             self.dirty = true;
         }
+        remove.len()
+    }
+
+    /// the call-chains with their metadata
+    pub fn entries(&self) -> impl Iterator<Item = (&CChainStatsKey, &ChainInfo)> {
+        self.chains.iter().map(|cck| (cck, &self.info[cck]))
+    }
+
+    pub fn get_info(&self, cck: &CChainStatsKey) -> Option<&ChainInfo> {
+        self.info.get(cck)
+    }
+
+    /// the date of the most recent observation of any call-chain of this endpoint
+    pub fn last_seen(&self) -> Option<NaiveDateTime> {
+        self.info.values().filter_map(|info| info.last_seen).max()
     }
 
     pub fn is_dirty(&self) -> bool {
         self.dirty
     }
 
-    pub fn write_call_chain(&self, mut cchain_file_base: PathBuf, k: &str) {
-        // extract call-chains and write to file
-        cchain_file_base.push(cchain_filename(k));
-        let file_name = cchain_file_base.to_str().unwrap();
-        let cchain_str = self
-            .chains
-            .iter()
-            .map(|cc| cc.call_chain_key())
-            .collect::<Vec<_>>()
-            .join("\n");
-        utils::write_string_to_file(file_name, cchain_str).expect("Failed to write cchain-files.");
+    /// write the call-chains to the cache-folder, merged with the call-chains written by other analyses in the meantime
+    pub fn write_call_chain(&self, cchain_folder: &Path, k: &str) -> Result<(), Box<dyn Error>> {
+        write_cchain_file_locked(cchain_folder, k, self, true)
+    }
+}
+
+/// Read all cchain-files in a folder. When both a structured and a plain cchain-file exist for an endpoint the structured file is used.
+pub fn read_cchain_folder(
+    path: &Path,
+) -> Result<BTreeMap<String, EndPointCChains>, Box<dyn Error>> {
+    let mut files: BTreeMap<String, PathBuf> = BTreeMap::new();
+    for entry in fs::read_dir(path)? {
+        let file_path = entry?.path();
+        let file_name = file_path
+            .file_name()
+            .map(|f| f.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if let Some(key) = file_name.strip_suffix(CCHAIN_EXT) {
+            files.insert(key.to_owned(), file_path);
+        } else if let Some(key) = file_name.strip_suffix(PLAIN_CCHAIN_EXT) {
+            files.entry(key.to_owned()).or_insert(file_path);
+        }
     }
+    files
+        .into_iter()
+        .map(|(key, file_path)| Ok((key, read_cchain_file(&file_path)?)))
+        .collect()
+}
+
+/// Write the dirty entries as structured cchain-files to the folder. With 'merge_on_write' the entries are merged with the current
+/// files (see write_cchain_file_locked), otherwise the files are replaced, which is needed to remove call-chains.
+pub fn write_cchain_folder(
+    path: &Path,
+    folder: &BTreeMap<String, EndPointCChains>,
+    merge_on_write: bool,
+) -> Result<(), Box<dyn Error>> {
+    folder
+        .iter()
+        .filter(|(_, epcc)| epcc.is_dirty())
+        .try_for_each(|(key, epcc)| write_cchain_file_locked(path, key, epcc, merge_on_write))
 }
 
 pub struct CChainEndPointCache {
     path: PathBuf,
     cache: HashMap<String, Option<EndPointCChains>>,
+    /// the source dataset of the call-chains that are added to the cache
+    source: String,
 }
 
 impl CChainEndPointCache {
@@ -65,15 +260,19 @@ impl CChainEndPointCache {
         Self {
             path,
             cache: HashMap::new(),
+            source: String::new(),
         }
     }
 
+    /// set the source dataset (data-folder) that is recorded for the call-chains added to the cache
+    pub fn set_source(&mut self, source: &str) {
+        self.source = source.to_owned();
+    }
+
     /// internatal function that returns a mutable reference
     fn get_cchain_key_aux(&mut self, key: &str) -> &mut Option<EndPointCChains> {
         self.cache.entry(key.to_string()).or_insert_with(|| {
-            let mut path = self.path.clone();
-            path.push(cchain_filename(key));
-            if path.is_file() {
+            if let Some(path) = find_cchain_file(&self.path, key) {
                 match read_cchain_file(&path) {
                     Ok(cchain_key) => Some(cchain_key),
                     Err(err) => {
@@ -88,8 +287,8 @@ impl CChainEndPointCache {
                 utils::report(
                     utils::Chapter::Details,
                     format!(
-                        "Could not find '{}' so no call-chain available",
-                        path.display()
+                        "Could not find a cchain-file for '{key}' in '{}' so no call-chain available",
+                        self.path.display()
                     ),
                 );
                 None
@@ -106,7 +305,11 @@ impl CChainEndPointCache {
     }
 
     /// Create a new entry of update an existing entry with the provided cchains
-    pub fn create_update_entry(&mut self, key: &str, cchains: Vec<CChainStatsKey>) {
+    pub fn create_update_entry(&mut self, key: &str, cchains: Vec<(CChainStatsKey, ChainInfo)>) {
+        let cchains = cchains
+            .into_iter()
+            .map(|(cck, info)| (cck, info.with_source(&self.source)))
+            .collect();
         match self.get_cchain_key_aux(key) {
             Some(entry) => entry.update_chains(cchains),
             None => {
@@ -124,9 +327,157 @@ impl Drop for CChainEndPointCache {
         mem::take(&mut self.cache).into_iter().for_each(|(k, v)| {
             if let Some(v) = v {
                 if v.is_dirty() {
-                    v.write_call_chain(self.path.clone(), &k);
+                    // a panic in drop would abort the analysis, so the failure is reported instead
+                    if let Err(err) = v.write_call_chain(&self.path, &k) {
+                        utils::report(
+                            Chapter::Issues,
+                            format!("Failed to write the cchain-file of '{k}'. Error: {err}"),
+                        );
+                    }
                 }
             }
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ChainInfo, EndPointCChains, PrunePolicy};
+    use crate::stats::call_chain::{CChainStatsKey, CChainStatsValue};
+    use chrono::{Duration, NaiveDate, NaiveDateTime};
+    use std::collections::BTreeMap;
+
+    fn date(day: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 3, day)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    }
+
+    fn info(sources: &[(&str, usize)], first: u32, last: u32) -> ChainInfo {
+        let sources: BTreeMap<_, _> = sources.iter().map(|(s, c)| (s.to_string(), *c)).collect();
+        ChainInfo {
+            count: sources.values().sum(),
+            first_seen: Some(date(first)),
+            last_seen: Some(date(last)),
+            sources,
+        }
+    }
+
+    fn cck(service: &str) -> CChainStatsKey {
+        CChainStatsKey::parse(&format!(
+            "gateway/GET /api [Inbound] | {service}/handle [Inbound] & & "
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn observed_from_compact_value() {
+        let micros = |dt: NaiveDateTime| dt.and_utc().timestamp_micros();
+        let mut ccv = CChainStatsValue::new(2, Vec::new(), true);
+        ccv.count = 3;
+        ccv.duration_micros = vec![1_000, 2_000, 3_000];
+        ccv.start_dt_micros = vec![
+            micros(date(7) + Duration::seconds(3)),
+            micros(date(5)),
+            micros(date(6)),
+        ];
+        let plain = ChainInfo::observed(&ccv);
+        assert_eq!(plain.first_seen, Some(date(5)));
+        assert_eq!(plain.last_seen, Some(date(7) + Duration::seconds(3)));
+
+        // the compact value only knows the start of the time-buckets (of 10 seconds)
+        ccv.compact();
+        assert!(ccv.start_dt_micros.is_empty());
+        let compact = ChainInfo::observed(&ccv);
+        assert_eq!(compact.count, 3);
+        assert_eq!(compact.first_seen, Some(date(5)));
+        assert_eq!(compact.last_seen, Some(date(7)));
+    }
+
+    #[test]
+    fn merge_chain_info() {
+        let mut curr = info(&[("week1", 10)], 2, 5);
+        curr.merge(&info(&[("week1", 12), ("week2", 5)], 8, 12));
+        assert_eq!(curr, info(&[("week1", 12), ("week2", 5)], 2, 12));
+
+        // merging the same source twice does not change the result
+        let once = curr.clone();
+        curr.merge(&info(&[("week2", 5)], 8, 12));
+        curr.merge(&info(&[("week2", 5)], 8, 12));
+        assert_eq!(curr, once);
+
+        // without sources the largest count is kept
+        let mut plain = ChainInfo {
+            count: 3,
+            ..Default::default()
+        };
+        plain.merge(&ChainInfo {
+            count: 2,
+            ..Default::default()
+        });
+        assert_eq!(plain.count, 3);
+        assert!(!plain.has_metadata());
+    }
+
+    #[test]
+    fn merge_endpoint_is_idempotent() {
+        let mut epcc = EndPointCChains::with_info(vec![(cck("a"), info(&[("week1", 10)], 2, 5))]);
+        let other = EndPointCChains::with_info(vec![
+            (cck("a"), info(&[("week2", 4)], 8, 9)),
+            (cck("b"), info(&[("week2", 1)], 9, 9)),
+        ]);
+        assert!(!epcc.is_dirty());
+        epcc.merge(&other);
+        assert!(epcc.is_dirty());
+        assert_eq!(epcc.chains, vec![cck("a"), cck("b")]);
+        assert_eq!(epcc.get_info(&cck("a")).unwrap().count, 14);
+
+        let mut again = EndPointCChains::with_info(
+            epcc.entries()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        );
+        again.merge(&other);
+        assert!(!again.is_dirty());
+        assert_eq!(again.get_info(&cck("a")).unwrap().count, 14);
+        assert_eq!(again.last_seen(), Some(date(9)));
+    }
+
+    #[test]
+    fn prune_by_age_and_count() {
+        let by_age = PrunePolicy {
+            max_age_days: Some(7),
+            min_count: None,
+        };
+        let by_count = PrunePolicy {
+            max_age_days: None,
+            min_count: Some(5),
+        };
+        let old = info(&[("week1", 10)], 1, 2);
+        let rare = info(&[("week2", 2)], 20, 20);
+        assert!(!by_age.keep(&old, date(20)));
+        assert!(by_age.keep(&rare, date(20)));
+        assert!(by_count.keep(&old, date(20)));
+        assert!(!by_count.keep(&rare, date(20)));
+        // call-chains without metadata are never pruned
+        assert!(by_age.keep(&ChainInfo::default(), date(20)));
+        assert!(by_count.keep(&ChainInfo::default(), date(20)));
+
+        let mut epcc = EndPointCChains::with_info(vec![
+            (cck("old"), old),
+            (cck("rare"), rare),
+            (cck("plain"), ChainInfo::default()),
+            (cck("recent"), info(&[("week2", 8)], 15, 20)),
+        ]);
+        let both = PrunePolicy {
+            max_age_days: Some(7),
+            min_count: Some(5),
+        };
+        assert_eq!(epcc.prune(&both, date(20)), 2);
+        assert!(epcc.is_dirty());
+        assert_eq!(epcc.chains, vec![cck("plain"), cck("recent")]);
+        assert!(epcc.get_info(&cck("old")).is_none());
+        assert_eq!(epcc.prune(&both, date(20)), 0);
+    }
+}
@@ -4,13 +4,18 @@ use super::{
     cchain_cache::EndPointCChains,
     expected_roots::ExpectedRoots,
     file::{call_chain_key, LEAF_LABEL},
+    repair::{apportion, distribute, split_counted},
 };
 use crate::{
+    stats::{
+        proc_oper_stats::{compact_values, merge_compact},
+        FileWindows,
+    },
     string_hash,
-    utils::{self, Chapter, Counted, TimeStats},
+    utils::{self, Chapter, Counted, DurationSketch, TimeBuckets, TimeStats},
 };
 use serde::{Deserialize, Serialize};
-use std::{cmp::Ordering, collections::HashMap, error::Error};
+use std::{cmp::Ordering, collections::HashMap, error::Error, mem};
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct CChainStatsValue {
@@ -25,6 +30,19 @@ pub struct CChainStatsValue {
     pub cc_with_error_logs: i32, // count of the number of call chanis that has one of more ERROR log-lines somewhere along the chain
     pub http_not_ok: Counted<i16>,
     pub error_logs: Counted<String>,
+    /// Compact representation of duration_micros. Only present in compacted statistics, in which case duration_micros is empty.
+    #[serde(default)]
+    pub duration_sketch: Option<DurationSketch>,
+    /// Compact representation of start_dt_micros. Only present in compacted statistics, in which case start_dt_micros is empty.
+    #[serde(default)]
+    pub start_dt_buckets: Option<TimeBuckets>,
+    /// number of calls that were attributed to this call-chain by repairing a non-rooted call-chain
+    #[serde(default)]
+    pub num_repaired: usize,
+    /// Average confidence of these repairs. A repair based on a unique match has confidence 1.0, while a probabilistic repair
+    /// has the observed share of the chosen call-chain among the candidates as confidence.
+    #[serde(default)]
+    pub repair_confidence: Option<f64>,
 }
 
 /// Key for the CChain containing part of the CChain-values
@@ -136,11 +154,10 @@ impl CChainStatsKey {
         })
     }
 
-    /// try to remap a non-rooted call-chain based on expected call chains and return whether the remapping succeeded.
-    /// TODO: also return the missing prefix such that it can be corrected later in the overall stats?
-    pub fn remap_callchain(&mut self, expected_cc: &EndPointCChains) -> bool {
+    /// the expected call-chains that end with the calls of this (non-rooted) call-chain
+    fn matching_chains<'a>(&self, expected_cc: &'a EndPointCChains) -> Vec<&'a CChainStatsKey> {
         let cc_len = self.call_chain.len();
-        let matches: Vec<_> = expected_cc
+        expected_cc
             .chains
             .iter()
             .filter(|ecc| {
@@ -153,7 +170,13 @@ impl CChainStatsKey {
                     self.call_chain.iter().cmp(ecc_iter) == Ordering::Equal
                 }
             })
-            .collect();
+            .collect()
+    }
+
+    /// try to remap a non-rooted call-chain based on expected call chains and return whether the remapping succeeded.
+    /// TODO: also return the missing prefix such that it can be corrected later in the overall stats?
+    pub fn remap_callchain(&mut self, expected_cc: &EndPointCChains) -> bool {
+        let matches = self.matching_chains(expected_cc);
         let the_match = match matches.len() {
             0 => None,
             1 => Some(matches[0]),
@@ -183,6 +206,35 @@ impl CChainStatsKey {
             false
         }
     }
+
+    /// The candidate call-chains for a repair of this non-rooted call-chain, with their share of the observed counts of the rooted
+    /// call-chains in 'observed'. Candidates that are not observed are dropped, and the result is ordered by decreasing share.
+    pub fn weighted_candidates(
+        &self,
+        expected_cc: &EndPointCChains,
+        observed: &HashMap<CallChain, usize>,
+    ) -> Vec<(CallChain, f64)> {
+        let mut candidates: Vec<_> = self
+            .matching_chains(expected_cc)
+            .into_iter()
+            .map(|ecc| &ecc.call_chain)
+            .collect();
+        // the leaf and the non-leaf variant of an expected chain are a single candidate
+        candidates.sort_unstable();
+        candidates.dedup();
+        let counts: Vec<_> = candidates
+            .into_iter()
+            .filter_map(|cc| observed.get(cc).map(|cnt| (cc, *cnt)))
+            .filter(|(_, cnt)| *cnt > 0)
+            .collect();
+        let total = counts.iter().map(|(_, cnt)| cnt).sum::<usize>() as f64;
+        let mut weighted: Vec<_> = counts
+            .into_iter()
+            .map(|(cc, cnt)| (cc.clone(), cnt as f64 / total))
+            .collect();
+        weighted.sort_by(|a, b| b.1.total_cmp(&a.1));
+        weighted
+    }
 }
 
 impl ToString for CChainStatsKey {
@@ -192,6 +244,103 @@ impl ToString for CChainStatsKey {
 }
 
 impl CChainStatsValue {
+    /// merge the statistics of 'other', which should have the same CChainStatsKey, into this value
+    pub fn merge(&mut self, mut other: CChainStatsValue) {
+        self.count += other.count;
+        self.depth = self.depth.max(other.depth);
+        if self.is_compact() || other.is_compact() {
+            self.compact();
+            other.compact();
+            merge_compact(
+                (&mut self.duration_sketch, &mut self.start_dt_buckets),
+                (other.duration_sketch, other.start_dt_buckets),
+            );
+        } else {
+            self.duration_micros.append(&mut other.duration_micros);
+            self.start_dt_micros.append(&mut other.start_dt_micros);
+        }
+        other.looped.into_iter().for_each(|l| {
+            if !self.looped.contains(&l) {
+                self.looped.push(l)
+            }
+        });
+        self.rooted = self.rooted || other.rooted;
+        self.expect_root.merge(other.expect_root);
+        self.cc_not_http_ok += other.cc_not_http_ok;
+        self.cc_with_error_logs += other.cc_with_error_logs;
+        self.http_not_ok.merge(other.http_not_ok);
+        self.error_logs.merge(other.error_logs);
+        self.repair_confidence = match (self.repair_confidence, other.repair_confidence) {
+            (Some(conf), Some(other_conf)) => {
+                let n = (self.num_repaired + other.num_repaired) as f64;
+                Some((conf * self.num_repaired as f64 + other_conf * other.num_repaired as f64) / n)
+            }
+            (conf, other_conf) => conf.or(other_conf),
+        };
+        self.num_repaired += other.num_repaired;
+    }
+
+    /// mark all calls of this value as repaired with the given confidence
+    pub fn set_repaired(&mut self, confidence: f64) {
+        self.num_repaired = self.count;
+        self.repair_confidence = Some(confidence);
+    }
+
+    /// Split the value in parts in proportion to 'weights' (used for a proportional repair). The durations, start-times and
+    /// error-counts are divided over the parts, while the expected roots are kept by the first part.
+    /// Returns None for compact values, as a sketch can not be split.
+    pub fn split(mut self, weights: &[f64]) -> Option<Vec<CChainStatsValue>> {
+        if self.is_compact() {
+            return None;
+        }
+        let counts = apportion(self.count, weights);
+        let durations = distribute(
+            &self.duration_micros,
+            &apportion(self.duration_micros.len(), weights),
+        );
+        let start_dts = distribute(
+            &self.start_dt_micros,
+            &apportion(self.start_dt_micros.len(), weights),
+        );
+        let not_http_ok = apportion(self.cc_not_http_ok as usize, weights);
+        let with_error_logs = apportion(self.cc_with_error_logs as usize, weights);
+        let http_not_ok = split_counted(&self.http_not_ok, weights);
+        let error_logs = split_counted(&self.error_logs, weights);
+        let mut expect_root = Some(mem::take(&mut self.expect_root));
+
+        Some(
+            counts
+                .into_iter()
+                .zip(durations)
+                .zip(start_dts)
+                .zip(not_http_ok.into_iter().zip(with_error_logs))
+                .zip(http_not_ok.into_iter().zip(error_logs))
+                .map(
+                    |(
+                        (
+                            ((count, duration_micros), start_dt_micros),
+                            (not_http_ok, with_error_logs),
+                        ),
+                        (http_not_ok, error_logs),
+                    )| CChainStatsValue {
+                        count,
+                        depth: self.depth,
+                        duration_micros,
+                        start_dt_micros,
+                        looped: self.looped.clone(),
+                        rooted: self.rooted,
+                        expect_root: expect_root.take().unwrap_or_default(),
+                        cc_not_http_ok: not_http_ok as i32,
+                        cc_with_error_logs: with_error_logs as i32,
+                        http_not_ok,
+                        error_logs,
+                        ..Default::default()
+                    },
+                )
+                .collect(),
+        )
+    }
+
     pub fn new(depth: usize, looped: Vec<String>, rooted: bool) -> Self {
         Self {
             depth,
@@ -201,53 +350,87 @@ impl CChainStatsValue {
         }
     }
 
+    /// true if the durations and start-times are stored in the compact format
+    pub fn is_compact(&self) -> bool {
+        self.duration_sketch.is_some()
+    }
+
+    /// replace the durations and start-times by a sketch and time-buckets (a no-op if the value is already compact)
+    pub fn compact(&mut self) {
+        if !self.is_compact() {
+            let (sketch, buckets) =
+                compact_values(&mut self.duration_micros, &mut self.start_dt_micros);
+            self.duration_sketch = Some(sketch);
+            self.start_dt_buckets = Some(buckets);
+        }
+    }
+
     pub fn get_min_millis(&self) -> f64 {
-        TimeStats(&self.duration_micros).get_min_millis()
+        match &self.duration_sketch {
+            Some(ds) => ds.get_min_millis(),
+            None => TimeStats(&self.duration_micros).get_min_millis(),
+        }
     }
 
     pub fn get_min_millis_str(&self) -> String {
-        TimeStats(&self.duration_micros).get_min_millis_str()
+        utils::format_float(self.get_min_millis())
     }
 
     pub fn get_avg_millis(&self) -> f64 {
-        TimeStats(&self.duration_micros).get_avg_millis()
+        match &self.duration_sketch {
+            Some(ds) => ds.get_avg_millis(),
+            None => TimeStats(&self.duration_micros).get_avg_millis(),
+        }
     }
 
     pub fn get_avg_millis_str(&self) -> String {
-        TimeStats(&self.duration_micros).get_avg_millis_str()
+        utils::format_float(self.get_avg_millis())
     }
 
     pub fn get_median_millis(&self) -> Option<f64> {
-        TimeStats(&self.duration_micros).get_median_millis()
+        match &self.duration_sketch {
+            Some(ds) => ds.get_median_millis(),
+            None => TimeStats(&self.duration_micros).get_median_millis(),
+        }
     }
 
     pub fn get_median_millis_str(&self) -> String {
-        TimeStats(&self.duration_micros).get_median_millis_str()
+        utils::format_float_opt(self.get_median_millis())
     }
 
     /// get the P-percentile over the values
     pub fn get_p_millis(&self, p: f64) -> Option<f64> {
-        TimeStats(&self.duration_micros).get_p_millis(p)
+        match &self.duration_sketch {
+            Some(ds) => ds.get_p_millis(p),
+            None => TimeStats(&self.duration_micros).get_p_millis(p),
+        }
     }
 
     pub fn get_p_millis_str(&self, p: f64) -> String {
-        TimeStats(&self.duration_micros).get_p_millis_str(p)
+        utils::format_float_opt(self.get_p_millis(p))
     }
 
     pub fn get_max_millis(&self) -> f64 {
-        TimeStats(&self.duration_micros).get_max_millis()
+        match &self.duration_sketch {
+            Some(ds) => ds.get_max_millis(),
+            None => TimeStats(&self.duration_micros).get_max_millis(),
+        }
     }
 
     pub fn get_max_millis_str(&self) -> String {
-        TimeStats(&self.duration_micros).get_max_millis_str()
+        utils::format_float(self.get_max_millis())
     }
 
-    pub fn get_avg_rate(&self, num_files: i32) -> Option<f64> {
-        TimeStats(&self.start_dt_micros).get_avg_rate(num_files)
+    /// average rate based on the time-windows of the input-files (num_files is only used when these windows are missing)
+    pub fn get_avg_rate(&self, file_windows: &FileWindows, num_files: i32) -> Option<f64> {
+        match &self.start_dt_buckets {
+            Some(tb) => file_windows.get_bucket_rate(tb, num_files),
+            None => file_windows.get_rate(&self.start_dt_micros, num_files),
+        }
     }
 
-    pub fn get_avg_rate_str(&self, num_files: i32) -> String {
-        TimeStats(&self.start_dt_micros).get_avg_rate_str(num_files)
+    pub fn get_avg_rate_str(&self, file_windows: &FileWindows, num_files: i32) -> String {
+        utils::format_float_opt(self.get_avg_rate(file_windows, num_files))
     }
 
     pub fn get_frac_not_http_ok(&self) -> f64 {
@@ -276,6 +459,7 @@ impl CChainStatsValue {
         process_key: &str,
         ps_key: &CChainStatsKey,
         n: f64,
+        file_windows: &FileWindows,
         num_files: i32,
     ) -> String {
         assert_eq!(
@@ -308,7 +492,7 @@ impl CChainStatsValue {
             self.get_avg_millis_str(),
             self.get_max_millis_str(),
             utils::format_float(percentage),
-            self.get_avg_rate_str(num_files),
+            self.get_avg_rate_str(file_windows, num_files),
             utils::format_float(expect_duration),
             utils::format_float(expect_contribution),
             self.get_frac_not_http_ok_str(),
@@ -329,7 +513,21 @@ impl CChainStats {
         Self(HashMap::new())
     }
 
-    pub fn merge(&mut self, _to_merge: CChainStats) {
-        todo!()
+    /// merge the call-chain statistics of 'to_merge' into these statistics
+    pub fn merge(&mut self, to_merge: CChainStats) {
+        to_merge
+            .0
+            .into_iter()
+            .for_each(|(k, v)| match self.0.get_mut(&k) {
+                Some(curr) => curr.merge(v),
+                None => {
+                    self.0.insert(k, v);
+                }
+            })
+    }
+
+    /// store the durations and start-times of all call-chains in compact form
+    pub fn compact(&mut self) {
+        self.0.values_mut().for_each(|ccv| ccv.compact());
     }
 }
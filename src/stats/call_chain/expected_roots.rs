@@ -33,6 +33,20 @@ impl ExpectedRoots {
         }
     }
 
+    /// merge the roots (and their counts) of 'other' into this list
+    pub fn merge(&mut self, other: ExpectedRoots) {
+        other.0.into_iter().for_each(|other_root| {
+            match self
+                .0
+                .iter_mut()
+                .find(|er| er.proc_oper == other_root.proc_oper)
+            {
+                Some(er) => er.count += other_root.count,
+                None => self.0.push(other_root),
+            }
+        })
+    }
+
     /// Find the most frequent end-point out of a list
     pub fn get_frequent_endpoint(&mut self) -> Option<String> {
         match self.0.len() {
@@ -51,3 +65,28 @@ impl ExpectedRoots {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ExpectedRoots;
+
+    #[test]
+    fn merge_sums_counts_per_root() {
+        let mut roots = ExpectedRoots::default();
+        roots.add_root("gw/a");
+        roots.add_root("gw/a");
+        let mut other = ExpectedRoots::default();
+        other.add_root("gw/b");
+        other.add_root("gw/a");
+        other.add_root("gw/b");
+        other.add_root("gw/b");
+        roots.merge(other);
+        let counts: Vec<_> = roots
+            .0
+            .iter()
+            .map(|er| (er.proc_oper.as_str(), er.count))
+            .collect();
+        assert_eq!(counts, vec![("gw/a", 3), ("gw/b", 3)]);
+        assert_eq!(roots.get_frequent_endpoint(), Some("gw/a".to_string()));
+    }
+}
@@ -1,12 +1,58 @@
 use super::{
-    call::Call, call_chain::CallChain, cchain_cache::EndPointCChains, cchain_stats::CChainStatsKey,
+    call::Call,
+    call_chain::CallChain,
+    cchain_cache::{ChainInfo, EndPointCChains, PrunePolicy},
+    cchain_stats::CChainStatsKey,
 };
-use crate::utils::read_lines;
-use std::{error::Error, path::Path};
+use crate::utils::{read_lines, write_atomic, FileLock};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Version of the structured cchain-file format. Files with a higher version are rejected.
+pub const CCHAIN_FORMAT_VERSION: u16 = 1;
+
+/// extension of the structured (versioned) cchain-files
+pub const CCHAIN_EXT: &str = ".cchain.json";
+
+/// extension of the plain cchain-files, which only contain the call-chain-keys
+pub const PLAIN_CCHAIN_EXT: &str = ".cchain";
 
 /// get the file-name for a specific key (excluding path)
 pub fn cchain_filename(key: &str) -> String {
-    format!("{key}.cchain")
+    format!("{key}{CCHAIN_EXT}")
+}
+
+/// get the file-name of the plain (legacy) cchain-file for a specific key (excluding path)
+pub fn plain_cchain_filename(key: &str) -> String {
+    format!("{key}{PLAIN_CCHAIN_EXT}")
+}
+
+/// maximal time to wait for another process that is writing the cchain-file of the same endpoint (a crashed process releases its lock immediately)
+const LOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// get the path of the lock-file that protects the cchain-file of an endpoint
+fn cchain_lock_path(folder: &Path, key: &str) -> PathBuf {
+    let mut path = folder.to_path_buf();
+    path.push(format!("{key}.cchain.lock"));
+    path
+}
+
+/// Find the cchain-file of an endpoint in the folder. The structured file is preferred over the plain cchain-file.
+pub fn find_cchain_file(folder: &Path, key: &str) -> Option<PathBuf> {
+    [cchain_filename(key), plain_cchain_filename(key)]
+        .into_iter()
+        .map(|file_name| {
+            let mut path = folder.to_path_buf();
+            path.push(file_name);
+            path
+        })
+        .find(|path| path.is_file())
 }
 
 const LEAF_LABEL_WITH_SPACE: &str = " *LEAF*";
@@ -24,8 +70,117 @@ pub fn call_chain_key(call_chain: &CallChain, caching_process: &str, is_leaf: bo
     call_chain_str + " & " + caching_process + "& " + leaf_str // using '&' as separator as a ';' separator would break the CSV-files
 }
 
-/// read a cchain-file and parse it
+/// A call-chain with its metadata as stored in the structured cchain-file
+#[derive(Serialize, Deserialize)]
+struct CChainEntry {
+    call_chain: String,
+    #[serde(flatten)]
+    info: ChainInfo,
+}
+
+/// The structured cchain-file of an endpoint
+#[derive(Serialize, Deserialize)]
+struct CChainFile {
+    version: u16,
+    endpoint: String,
+    chains: Vec<CChainEntry>,
+}
+
+/// read a cchain-file and parse it. Files with extension '.cchain.json' are read as structured files, other files as plain cchain-files.
 pub fn read_cchain_file(path: &Path) -> Result<EndPointCChains, Box<dyn Error>> {
+    if path.to_string_lossy().ends_with(CCHAIN_EXT) {
+        read_structured_cchain_file(path)
+    } else {
+        read_plain_cchain_file(path)
+    }
+}
+
+/// read a structured cchain-file
+fn read_structured_cchain_file(path: &Path) -> Result<EndPointCChains, Box<dyn Error>> {
+    let content = fs::read_to_string(path)?;
+    let cchain_file: CChainFile = serde_json::from_str(&content)?;
+    if cchain_file.version > CCHAIN_FORMAT_VERSION {
+        Err(format!(
+            "File '{}' has cchain-format version {} while version {CCHAIN_FORMAT_VERSION} is supported",
+            path.display(),
+            cchain_file.version
+        ))?
+    }
+    let chains = cchain_file
+        .chains
+        .into_iter()
+        .map(|entry| Ok((CChainStatsKey::parse(&entry.call_chain)?, entry.info)))
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+    Ok(EndPointCChains::with_info(chains))
+}
+
+/// write the call-chains of an endpoint as a structured cchain-file
+pub fn write_cchain_file(
+    path: &Path,
+    endpoint: &str,
+    epcc: &EndPointCChains,
+) -> Result<(), Box<dyn Error>> {
+    let chains = epcc
+        .entries()
+        .map(|(cck, info)| CChainEntry {
+            call_chain: cck.call_chain_key(),
+            info: info.clone(),
+        })
+        .collect();
+    let cchain_file = CChainFile {
+        version: CCHAIN_FORMAT_VERSION,
+        endpoint: endpoint.to_owned(),
+        chains,
+    };
+    write_atomic(path, serde_json::to_string_pretty(&cchain_file)?.as_bytes())
+}
+
+/// Write the call-chains of an endpoint to the folder. The write is protected by a lock-file per endpoint and is atomic.
+/// With 'merge_on_write' the current file is read under the lock and the union with 'epcc' is written, such that concurrent
+/// analyses that share the folder converge to the union of their call-chains. Without it the file is replaced.
+pub fn write_cchain_file_locked(
+    folder: &Path,
+    key: &str,
+    epcc: &EndPointCChains,
+    merge_on_write: bool,
+) -> Result<(), Box<dyn Error>> {
+    let _lock = FileLock::acquire(&cchain_lock_path(folder, key), LOCK_TIMEOUT)?;
+    let mut path = folder.to_path_buf();
+    path.push(cchain_filename(key));
+    match find_cchain_file(folder, key).filter(|_| merge_on_write) {
+        Some(curr_path) => {
+            let mut curr = read_cchain_file(&curr_path)?;
+            curr.merge(epcc);
+            write_cchain_file(&path, key, &curr)
+        }
+        None => write_cchain_file(&path, key, epcc),
+    }
+}
+
+/// Prune the cchain-file of an endpoint and return the number of removed call-chains. The lock is held while the file is read, pruned
+/// and written, such that call-chains that are written by a concurrent analysis in the meantime are not lost.
+pub fn prune_cchain_file_locked(
+    folder: &Path,
+    key: &str,
+    policy: &PrunePolicy,
+    reference: NaiveDateTime,
+) -> Result<usize, Box<dyn Error>> {
+    let _lock = FileLock::acquire(&cchain_lock_path(folder, key), LOCK_TIMEOUT)?;
+    let Some(curr_path) = find_cchain_file(folder, key) else {
+        return Ok(0);
+    };
+    let mut epcc = read_cchain_file(&curr_path)?;
+    let removed = epcc.prune(policy, reference);
+    if removed > 0 {
+        let mut path = folder.to_path_buf();
+        path.push(cchain_filename(key));
+        write_cchain_file(&path, key, &epcc)?;
+    }
+    Ok(removed)
+}
+
+/// read a plain cchain-file that only contains call-chain-keys (one per line)
+fn read_plain_cchain_file(path: &Path) -> Result<EndPointCChains, Box<dyn Error>> {
     let epcc = read_lines(path)?
         .filter_map(|l| {
             let l = l.unwrap();
@@ -69,3 +224,146 @@ pub fn caching_process_label(caching_process: &[String], call_chain: &CallChain)
         "".to_owned()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        prune_cchain_file_locked, read_cchain_file, write_cchain_file, write_cchain_file_locked,
+        CChainStatsKey, EndPointCChains,
+    };
+    use crate::stats::call_chain::cchain_cache::{ChainInfo, PrunePolicy};
+    use chrono::NaiveDate;
+    use std::{collections::BTreeMap, fs, path::Path, thread};
+
+    fn cck(service: &str, is_leaf: bool) -> CChainStatsKey {
+        let leaf = if is_leaf { "*LEAF*" } else { "" };
+        CChainStatsKey::parse(&format!(
+            "gateway/GET /api [Inbound] | {service}/handle [Inbound] & & {leaf}"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn structured_and_legacy_files() {
+        let folder = std::env::temp_dir().join(format!("cchain_file_test_{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let seen = NaiveDate::from_ymd_opt(2024, 3, 1)
+            .unwrap()
+            .and_hms_opt(8, 30, 0)
+            .unwrap();
+        let info = ChainInfo {
+            count: 7,
+            first_seen: Some(seen),
+            last_seen: Some(seen),
+            sources: BTreeMap::from([("week1".to_owned(), 7)]),
+        };
+        let epcc = EndPointCChains::with_info(vec![
+            (cck("a", true), info.clone()),
+            (cck("b", false), ChainInfo::default()),
+        ]);
+
+        // structured round-trip
+        let path = folder.join("endpoint.cchain.json");
+        write_cchain_file(&path, "endpoint", &epcc).unwrap();
+        let read = read_cchain_file(&path).unwrap();
+        assert_eq!(read.chains, epcc.chains);
+        assert_eq!(read.get_info(&cck("a", true)), Some(&info));
+        assert_eq!(read.get_info(&cck("b", false)), Some(&ChainInfo::default()));
+
+        // a legacy cchain-file only contains the keys (comments and empty lines are skipped)
+        let legacy = folder.join("legacy.cchain");
+        let lines = [
+            "# call-chains of legacy".to_owned(),
+            cck("a", true).call_chain_key(),
+            String::new(),
+            cck("c", true).call_chain_key(),
+        ];
+        fs::write(&legacy, lines.join("\n")).unwrap();
+        let read = read_cchain_file(&legacy).unwrap();
+        assert_eq!(read.chains, vec![cck("a", true), cck("c", true)]);
+        assert!(read.entries().all(|(_, info)| !info.has_metadata()));
+
+        // merge-on-write upgrades the legacy file to a structured file that contains the union
+        write_cchain_file_locked(&folder, "legacy", &epcc, true).unwrap();
+        let read = read_cchain_file(&folder.join("legacy.cchain.json")).unwrap();
+        assert_eq!(
+            read.chains,
+            vec![cck("a", true), cck("c", true), cck("b", false)]
+        );
+        assert_eq!(read.get_info(&cck("a", true)).unwrap().count, 7);
+
+        // a newer format version is rejected
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(&path, content.replace("\"version\": 1", "\"version\": 99")).unwrap();
+        assert!(read_cchain_file(&path).is_err());
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn prune_file_under_lock() {
+        let folder = std::env::temp_dir().join(format!("cchain_prune_test_{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let seen = |month| {
+            let dt = NaiveDate::from_ymd_opt(2024, month, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap();
+            ChainInfo {
+                count: 1,
+                first_seen: Some(dt),
+                last_seen: Some(dt),
+                sources: BTreeMap::from([("week1".to_owned(), 1)]),
+            }
+        };
+        let epcc = EndPointCChains::with_info(vec![
+            (cck("old", true), seen(1)),
+            (cck("new", true), seen(3)),
+        ]);
+        write_cchain_file(&folder.join("endpoint.cchain.json"), "endpoint", &epcc).unwrap();
+
+        let policy = PrunePolicy {
+            max_age_days: Some(30),
+            min_count: None,
+        };
+        let reference = seen(3).last_seen.unwrap();
+        assert_eq!(
+            prune_cchain_file_locked(&folder, "endpoint", &policy, reference).unwrap(),
+            1
+        );
+        let read = read_cchain_file(&folder.join("endpoint.cchain.json")).unwrap();
+        assert_eq!(read.chains, vec![cck("new", true)]);
+        // an endpoint without a file has nothing to prune
+        assert_eq!(
+            prune_cchain_file_locked(&folder, "missing", &policy, reference).unwrap(),
+            0
+        );
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn concurrent_writes_converge_to_union() {
+        let folder = std::env::temp_dir().join(format!("cchain_test_{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let folder = folder.clone();
+                thread::spawn(move || {
+                    let cck = CChainStatsKey::parse(&format!(
+                        "gateway/GET /api [Inbound] | svc{i}/handle [Inbound] & & "
+                    ))
+                    .unwrap();
+                    let epcc = EndPointCChains::with_info(vec![(cck, ChainInfo::default())]);
+                    write_cchain_file_locked(&folder, "endpoint", &epcc, true).unwrap();
+                })
+            })
+            .collect();
+        handles.into_iter().for_each(|h| h.join().unwrap());
+
+        let epcc = read_cchain_file(&folder.join("endpoint.cchain.json")).unwrap();
+        assert_eq!(epcc.chains.len(), 8);
+        // no temporary files are left behind (the lock-file is kept)
+        assert_eq!(fs::read_dir(&folder).unwrap().count(), 2);
+        assert!(folder.join("endpoint.cchain.lock").is_file());
+        fs::remove_dir_all(Path::new(&folder)).unwrap();
+    }
+}
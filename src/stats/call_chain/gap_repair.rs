@@ -0,0 +1,343 @@
+//! Repair of gaps in the middle of a call-chain. When a span is lost in the middle of a trace, the sub-tree below this span
+//! becomes an orphan (its parent is missing) and the span above it seems to be a leaf. Based on the known call-chains of the endpoint
+//! the orphan sub-tree is joined back to its ancestor by inserting the missing call(s), such that all calls of the sub-tree are counted
+//! under their full call-chain. The inserted spans get a duration that covers their orphan children and have the tag 'gap_repair'.
+//! As this duration is made up, the inserted spans are not counted in the statistics of their Process/Operation (see StatsRec::extend_statistics).
+use super::{
+    call::{Call, CallDirection},
+    call_chain::{get_call_chain, CallChain},
+    cchain_cache::CChainEndPointCache,
+};
+use crate::{
+    processed::{GapCall, Trace},
+    stats::TraceExt,
+    utils::{self, Chapter},
+};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Mutex,
+};
+
+static GAP_REPAIR: Mutex<bool> = Mutex::new(false);
+
+/// Enable (or disable) the repair of gaps in the middle of the call-chains of incomplete traces.
+pub fn set_gap_repair(val: bool) {
+    let mut guard = GAP_REPAIR.lock().unwrap();
+    *guard = val
+}
+
+pub fn gap_repair_enabled() -> bool {
+    *GAP_REPAIR.lock().unwrap()
+}
+
+fn to_gap_call(call: &Call) -> GapCall {
+    let span_kind = match call.call_direction {
+        CallDirection::Inbound => Some("server".to_owned()),
+        CallDirection::Outbound => Some("client".to_owned()),
+        CallDirection::Unknown => None,
+    };
+    GapCall {
+        service: call.service.clone(),
+        operation: call.operation.clone(),
+        span_kind,
+    }
+}
+
+/// Find the unique (ancestor, missing calls) that joins the orphan at 'orphan_idx' to the rooted part of the trace.
+/// The ancestor must be a rooted span that started before and ended after the start of the orphan, and the known call-chain must
+/// continue from the ancestor via the missing calls to the call of the orphan. When multiple repairs are possible, the repairs with
+/// the smallest number of missing calls are considered, and only a unique repair is returned.
+fn find_repair(
+    trace: &Trace,
+    orphan_idx: usize,
+    known: &HashSet<CallChain>,
+) -> Option<(usize, Vec<Call>)> {
+    let spans = &trace.spans;
+    let orphan = &spans.items[orphan_idx];
+    let orphan_call = get_call_chain(orphan_idx, spans).pop()?;
+    let orphan_start = orphan.start_dt.and_utc().timestamp_micros();
+
+    let mut candidates: Vec<(usize, Vec<Call>)> = Vec::new();
+    spans
+        .items
+        .iter()
+        .enumerate()
+        .filter(|(_, span)| span.rooted)
+        .filter(|(_, span)| {
+            let start = span.start_dt.and_utc().timestamp_micros();
+            start <= orphan_start && orphan_start <= start + span.duration_micros
+        })
+        .for_each(|(idx, _)| {
+            let ancestor_cc = get_call_chain(idx, spans);
+            let len = ancestor_cc.len();
+            known
+                .iter()
+                .filter(|kc| kc.len() > len + 1 && kc.starts_with(&ancestor_cc))
+                .for_each(|kc| {
+                    (len + 1..kc.len())
+                        .filter(|pos| kc[*pos] == orphan_call)
+                        .for_each(|pos| candidates.push((idx, kc[len..pos].to_vec())))
+                })
+        });
+    candidates.sort_unstable();
+    candidates.dedup();
+    let min_gap = candidates.iter().map(|(_, missing)| missing.len()).min()?;
+    let mut candidates = candidates
+        .into_iter()
+        .filter(|(_, missing)| missing.len() == min_gap);
+    match (candidates.next(), candidates.next()) {
+        (Some(repair), None) => Some(repair),
+        (Some(_), Some(_)) => {
+            utils::report(
+                Chapter::Details,
+                format!(
+                    "NO GAP-FIX: multiple ancestors found for orphan '{}' in trace '{}'",
+                    orphan_call.get_process_method(),
+                    trace.trace_id
+                ),
+            );
+            None
+        }
+        _ => None,
+    }
+}
+
+/// Join the orphan sub-trees of a trace to their ancestors and return the number of gaps that have been closed and the number of inserted spans.
+fn repair_trace_gaps(trace: &mut Trace, known: &HashSet<CallChain>) -> (usize, usize) {
+    if trace.spans.root_idx.is_none() {
+        return (0, 0);
+    }
+    // the orphans grouped by the id of their missing parent (siblings share the inserted parent)
+    let mut by_missing: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    trace
+        .spans
+        .orphans(&trace.missing_span_ids)
+        .into_iter()
+        .for_each(|(idx, missing_id)| {
+            by_missing
+                .entry(missing_id.to_owned())
+                .or_default()
+                .push(idx)
+        });
+
+    let mut num_repairs = 0;
+    let mut num_inserted = 0;
+    by_missing.into_iter().for_each(|(missing_id, orphans)| {
+        if let Some((ancestor, missing)) = find_repair(trace, orphans[0], known) {
+            num_inserted += missing.len();
+            let missing = missing.iter().map(to_gap_call).collect();
+            trace
+                .spans
+                .join_orphans(&orphans, ancestor, missing, &missing_id);
+            num_repairs += 1;
+        }
+    });
+    (num_repairs, num_inserted)
+}
+
+/// Repair the gaps in the incomplete traces (when enabled via set_gap_repair). The known call-chains of an endpoint are the call-chains
+/// of the complete traces of this endpoint in the current dataset and the call-chains in the call-chain cache.
+/// Returns the number of traces that have been repaired.
+pub fn repair_gaps(traces: &mut [TraceExt], cchain_cache: &mut CChainEndPointCache) -> usize {
+    if gap_repair_enabled() {
+        repair_all_gaps(traces, cchain_cache)
+    } else {
+        0
+    }
+}
+
+/// Repair the gaps in the incomplete traces, irrespective of set_gap_repair (see repair_gaps).
+fn repair_all_gaps(traces: &mut [TraceExt], cchain_cache: &mut CChainEndPointCache) -> usize {
+    let mut known: HashMap<String, HashSet<CallChain>> = HashMap::new();
+    traces
+        .iter()
+        .filter(|tr| tr.trace.missing_span_ids.is_empty())
+        .for_each(|tr| {
+            let chains = known.entry(tr.get_endpoint_key()).or_default();
+            (0..tr.trace.spans.items.len()).for_each(|idx| {
+                chains.insert(get_call_chain(idx, &tr.trace.spans));
+            })
+        });
+
+    let mut cache_loaded = HashSet::new();
+    let mut num_traces = 0;
+    let mut num_gaps = 0;
+    let mut num_inserted = 0;
+    traces
+        .iter_mut()
+        .filter(|tr| !tr.trace.missing_span_ids.is_empty())
+        .for_each(|tr| {
+            let key = tr.get_endpoint_key();
+            let chains = known.entry(key.clone()).or_default();
+            if cache_loaded.insert(key.clone()) {
+                if let Some(expected) = cchain_cache.get_cchain_key(&key) {
+                    chains.extend(expected.chains.iter().map(|cck| cck.call_chain.clone()));
+                }
+            }
+            let (repairs, inserted) = repair_trace_gaps(&mut tr.trace, chains);
+            if repairs > 0 {
+                num_traces += 1;
+                num_gaps += repairs;
+                num_inserted += inserted;
+            }
+        });
+    if num_gaps > 0 {
+        utils::report(
+            Chapter::Summary,
+            format!("Repaired {num_gaps} gaps in the middle of call-chains in {num_traces} incomplete traces by joining the orphan sub-trees to their ancestor. The {num_inserted} inserted spans are not included in the Process/Operation statistics."),
+        );
+    }
+    num_traces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{call_chain::CallChain, cchain_cache::CChainEndPointCache},
+        find_repair, get_call_chain, repair_all_gaps, repair_trace_gaps,
+    };
+    use crate::{
+        processed::{
+            test_traces::{build_trace, TestSpan},
+            Position, Trace,
+        },
+        stats::{BasicStatsRec, StatsRec, TraceExt},
+    };
+    use std::{collections::HashSet, fs};
+
+    /// the call-chains of all spans of the trace
+    fn call_chains(trace: &Trace) -> HashSet<CallChain> {
+        (0..trace.spans.items.len())
+            .map(|idx| get_call_chain(idx, &trace.spans))
+            .collect()
+    }
+
+    fn complete_trace() -> Trace {
+        build_trace(
+            "complete",
+            &[
+                TestSpan::new("a0", None, "gateway/GET /api", 0, 10_000),
+                TestSpan::new("a1", Some("a0"), "svca/call", 1_000, 8_000),
+                TestSpan::new("a2", Some("a1"), "app/handle", 2_000, 2_000),
+            ],
+        )
+    }
+
+    /// the span 'svca/call' with id 'm1' is missing
+    fn gap_trace(extra: Option<TestSpan>) -> Trace {
+        let mut spans = vec![
+            TestSpan::new("g0", None, "gateway/GET /api", 0, 10_000),
+            TestSpan::new("g2", Some("m1"), "app/handle", 2_000, 2_000),
+        ];
+        spans.extend(extra);
+        build_trace("gap", &spans)
+    }
+
+    #[test]
+    fn single_missing_middle_span() {
+        let known = call_chains(&complete_trace());
+        let mut trace = gap_trace(None);
+        let (ancestor, missing) = find_repair(&trace, 1, &known).unwrap();
+        assert_eq!(ancestor, 0);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].get_process_method(), "svca/call");
+
+        assert_eq!(repair_trace_gaps(&mut trace, &known), (1, 1));
+        let spans = &trace.spans.items;
+        assert_eq!(spans.len(), 3);
+        assert!(spans[2].is_gap_repair());
+        assert_eq!(spans[2].span_id, "m1");
+        assert!(matches!(spans[2].position, Position::Parent(0)));
+        assert!(matches!(spans[1].position, Position::Parent(2)));
+        assert!(spans.iter().all(|span| span.rooted));
+        assert!(!spans[0].is_leaf && spans[1].is_leaf);
+        assert!(known.contains(&get_call_chain(1, &trace.spans)));
+    }
+
+    #[test]
+    fn siblings_share_one_missing_parent() {
+        let known = call_chains(&complete_trace());
+        let sibling = TestSpan::new("g3", Some("m1"), "app/handle", 5_000, 3_000);
+        let mut trace = gap_trace(Some(sibling));
+        assert_eq!(trace.spans.orphans(&trace.missing_span_ids).len(), 2);
+
+        assert_eq!(repair_trace_gaps(&mut trace, &known), (1, 1));
+        let spans = &trace.spans.items;
+        assert_eq!(spans.len(), 4);
+        assert!(matches!(spans[1].position, Position::Parent(3)));
+        assert!(matches!(spans[2].position, Position::Parent(3)));
+        // the inserted span covers both orphans
+        assert_eq!(spans[3].start_dt, spans[1].start_dt);
+        assert_eq!(spans[3].duration_micros, 6_000);
+    }
+
+    #[test]
+    fn ambiguous_ancestor_is_not_repaired() {
+        // 'svca/call' is called by the gateway and by 'svcb/call', and both are running when the orphan starts
+        let via_svcb = build_trace(
+            "via_svcb",
+            &[
+                TestSpan::new("b0", None, "gateway/GET /api", 0, 10_000),
+                TestSpan::new("b1", Some("b0"), "svcb/call", 500, 9_000),
+                TestSpan::new("b2", Some("b1"), "svca/call", 1_000, 8_000),
+                TestSpan::new("b3", Some("b2"), "app/handle", 2_000, 2_000),
+            ],
+        );
+        let mut known = call_chains(&complete_trace());
+        known.extend(call_chains(&via_svcb));
+        let running_svcb = TestSpan::new("g1", Some("g0"), "svcb/call", 500, 9_000);
+        let mut trace = gap_trace(Some(running_svcb));
+
+        assert_eq!(find_repair(&trace, 1, &known), None);
+        assert_eq!(repair_trace_gaps(&mut trace, &known), (0, 0));
+        assert_eq!(trace.spans.items.len(), 3);
+    }
+
+    #[test]
+    fn orphans_aligned_with_missing_span_ids() {
+        let trace = build_trace(
+            "orphans",
+            &[
+                TestSpan::new("o0", None, "gateway/GET /api", 0, 10_000),
+                TestSpan::new("o1", Some("x"), "svca/call", 1_000, 1_000),
+                TestSpan::new("o2", Some("o0"), "svcb/call", 1_000, 1_000),
+                TestSpan::new("o3", Some("y"), "app/handle", 2_000, 1_000),
+                TestSpan::new("o4", Some("x"), "app/other", 3_000, 1_000),
+            ],
+        );
+        assert_eq!(
+            trace.spans.orphans(&trace.missing_span_ids),
+            vec![(1, "x"), (3, "y"), (4, "x")]
+        );
+    }
+
+    #[test]
+    fn repaired_traces_and_statistics() {
+        let folder = std::env::temp_dir().join(format!("gap_repair_test_{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let mut traces = vec![
+            TraceExt::new(complete_trace(), &folder),
+            TraceExt::new(gap_trace(None), &folder),
+        ];
+        let mut cchain_cache = CChainEndPointCache::new(folder.clone());
+        let num_repaired = repair_all_gaps(&mut traces, &mut cchain_cache);
+        drop(cchain_cache);
+        fs::remove_dir_all(folder).unwrap();
+        assert_eq!(num_repaired, 1);
+
+        let mut sr = StatsRec::new(BasicStatsRec::default());
+        traces
+            .iter()
+            .for_each(|tr| sr.extend_statistics(&tr.trace, false));
+        // the inserted 'svca/call' is not counted, while 'app/handle' is counted twice under its full call-chain
+        assert_eq!(sr.stats["svca"].operation.0["call"].count, 1);
+        let app = &sr.stats["app"];
+        assert_eq!(app.operation.0["handle"].count, 2);
+        assert_eq!(app.call_chain.0.len(), 1);
+        assert!(app
+            .call_chain
+            .0
+            .values()
+            .all(|ccv| ccv.rooted && ccv.count == 2));
+    }
+}
@@ -5,11 +5,19 @@ mod cchain_cache;
 mod cchain_stats;
 mod expected_roots;
 mod file;
+mod gap_repair;
+mod repair;
 
 pub use self::{
     call::{Call, CallDirection},
     call_chain::{get_call_chain, CallChain},
-    cchain_cache::CChainEndPointCache,
+    cchain_cache::{
+        read_cchain_folder, write_cchain_folder, CChainEndPointCache, ChainInfo, EndPointCChains,
+        PrunePolicy,
+    },
     cchain_stats::{CChainStats, CChainStatsKey, CChainStatsValue},
-    file::{caching_process_label, call_chain_key},
+    expected_roots::ExpectedRoots,
+    file::{caching_process_label, call_chain_key, prune_cchain_file_locked},
+    gap_repair::{repair_gaps, set_gap_repair},
+    repair::{get_repair_strategy, repair_probabilistic, set_repair_strategy, RepairStrategy},
 };
@@ -0,0 +1,416 @@
+//! Strategies to repair a non-rooted call-chain when its tail matches several expected call-chains of the endpoint.
+//! The default strategy ('unique') only repairs a chain when there is a single match. The probabilistic strategies use the
+//! observed frequencies of the (rooted) candidate chains: 'most-probable' picks the most frequent candidate when its share
+//! exceeds a confidence-threshold, and 'proportional' attributes the statistics to all candidates in proportion to their frequency.
+use super::{
+    call_chain::CallChain, cchain_cache::EndPointCChains, CChainStatsKey, CChainStatsValue,
+};
+use crate::utils::{self, Chapter, Counted};
+use std::{collections::HashMap, hash::Hash, str::FromStr, sync::Mutex};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RepairStrategy {
+    #[default]
+    Unique,
+    MostProbable,
+    Proportional,
+}
+
+impl FromStr for RepairStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "unique" => Ok(Self::Unique),
+            "most-probable" => Ok(Self::MostProbable),
+            "proportional" => Ok(Self::Proportional),
+            _ => Err(format!(
+                "Unknown repair-strategy '{s}'. Options are 'unique', 'most-probable' and 'proportional'."
+            )),
+        }
+    }
+}
+
+/// the repair-strategy and the minimal confidence required for a 'most-probable' repair
+static REPAIR_STRATEGY: Mutex<(RepairStrategy, f64)> = Mutex::new((RepairStrategy::Unique, 0.8));
+
+/// Set the strategy used to repair non-rooted call-chains that match several expected call-chains.
+/// The 'min_confidence' is the minimal share of the most probable candidate for the 'most-probable' strategy.
+pub fn set_repair_strategy(strategy: RepairStrategy, min_confidence: f64) {
+    let mut guard = REPAIR_STRATEGY.lock().unwrap();
+    *guard = (strategy, min_confidence)
+}
+
+pub fn get_repair_strategy() -> (RepairStrategy, f64) {
+    *REPAIR_STRATEGY.lock().unwrap()
+}
+
+/// Repair a non-rooted call-chain that could not be remapped uniquely, based on the observed frequencies of the candidate chains.
+/// Returns whether the repair succeeded and the resulting (key, value) pairs. When the repair fails, the original pair is returned.
+/// The strategy is taken from set_repair_strategy.
+pub fn repair_probabilistic(
+    cck: CChainStatsKey,
+    ccv: CChainStatsValue,
+    expected_cc: &EndPointCChains,
+    observed: &HashMap<CallChain, usize>,
+) -> (bool, Vec<(CChainStatsKey, CChainStatsValue)>) {
+    let (strategy, min_confidence) = get_repair_strategy();
+    repair_with_strategy(cck, ccv, expected_cc, observed, strategy, min_confidence)
+}
+
+/// Repair a non-rooted call-chain with the given strategy (see repair_probabilistic)
+fn repair_with_strategy(
+    cck: CChainStatsKey,
+    mut ccv: CChainStatsValue,
+    expected_cc: &EndPointCChains,
+    observed: &HashMap<CallChain, usize>,
+    strategy: RepairStrategy,
+    min_confidence: f64,
+) -> (bool, Vec<(CChainStatsKey, CChainStatsValue)>) {
+    if strategy == RepairStrategy::Unique {
+        return (false, vec![(cck, ccv)]);
+    }
+    let candidates = cck.weighted_candidates(expected_cc, observed);
+    if candidates.is_empty() {
+        return (false, vec![(cck, ccv)]);
+    }
+    let to_key = |call_chain: CallChain| CChainStatsKey {
+        call_chain,
+        caching_process: cck.caching_process.clone(),
+        is_leaf: cck.is_leaf,
+    };
+
+    if strategy == RepairStrategy::Proportional && !ccv.is_compact() {
+        let weights: Vec<_> = candidates.iter().map(|(_, share)| *share).collect();
+        let parts = ccv
+            .split(&weights)
+            .expect("a non-compact value can be split");
+        utils::report(
+            Chapter::Details,
+            format!(
+                "PROPORTIONAL FIX: non-rooted '{}' attributed to {} candidate call-chains with shares {weights:?}",
+                cck.call_chain_key(),
+                candidates.len()
+            ),
+        );
+        let parts = parts
+            .into_iter()
+            .zip(candidates)
+            .filter(|(v, _)| v.count > 0)
+            .map(|(mut v, (call_chain, share))| {
+                v.rooted = true;
+                v.set_repaired(share);
+                (to_key(call_chain), v)
+            })
+            .collect();
+        return (true, parts);
+    }
+
+    // most probable candidate (also used for compact values that can not be split)
+    let (call_chain, share) = candidates.into_iter().next().unwrap();
+    if share >= min_confidence {
+        ccv.rooted = true;
+        ccv.set_repaired(share);
+        (true, vec![(to_key(call_chain), ccv)])
+    } else {
+        utils::report(
+            Chapter::Details,
+            format!(
+                "NO FIX: most probable call-chain for non-rooted '{}' has confidence {share:.3}, which is below {min_confidence}",
+                cck.call_chain_key()
+            ),
+        );
+        (false, vec![(cck, ccv)])
+    }
+}
+
+/// Divide 'total' over parts in proportion to the 'weights' using the largest-remainder method, such that the parts sum to 'total'.
+pub fn apportion(total: usize, weights: &[f64]) -> Vec<usize> {
+    let sum: f64 = weights.iter().sum();
+    if sum <= 0.0 || weights.is_empty() {
+        return vec![0; weights.len()];
+    }
+    let quotas: Vec<_> = weights.iter().map(|w| total as f64 * w / sum).collect();
+    let mut parts: Vec<_> = quotas.iter().map(|q| q.floor() as usize).collect();
+    let mut order: Vec<_> = (0..quotas.len()).collect();
+    order.sort_by(|a, b| {
+        let rem = |i: usize| quotas[i] - quotas[i].floor();
+        rem(*b).total_cmp(&rem(*a))
+    });
+    let remaining = total - parts.iter().sum::<usize>();
+    order
+        .into_iter()
+        .take(remaining)
+        .for_each(|i| parts[i] += 1);
+    parts
+}
+
+/// Distribute the 'values' over parts of the given sizes. The values are interleaved (each next value goes to the part that is
+/// least filled relative to its size) such that each part gets a representative share of a time-ordered series.
+pub fn distribute<T: Clone>(values: &[T], sizes: &[usize]) -> Vec<Vec<T>> {
+    let mut parts: Vec<Vec<T>> = sizes.iter().map(|s| Vec::with_capacity(*s)).collect();
+    values.iter().for_each(|v| {
+        let idx = (0..sizes.len())
+            .filter(|i| parts[*i].len() < sizes[*i])
+            .min_by(|a, b| {
+                let fill = |i: usize| parts[i].len() as f64 / sizes[i] as f64;
+                fill(*a).total_cmp(&fill(*b))
+            });
+        if let Some(idx) = idx {
+            parts[idx].push(v.clone())
+        }
+    });
+    parts
+}
+
+/// Split the counts of each item of 'counted' in proportion to the 'weights'
+pub fn split_counted<T: Hash + Eq + Clone>(
+    counted: &Counted<T>,
+    weights: &[f64],
+) -> Vec<Counted<T>> {
+    let mut parts: Vec<_> = weights.iter().map(|_| Counted::new()).collect();
+    counted.iter().for_each(|(item, cnt)| {
+        apportion(*cnt, weights)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, c)| *c > 0)
+            .for_each(|(i, c)| {
+                parts[i].add_item_count(item.clone(), c);
+            })
+    });
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apportion, distribute, repair_with_strategy, RepairStrategy};
+    use crate::{
+        stats::call_chain::{
+            call::Call, call_chain::CallChain, cchain_cache::EndPointCChains, CChainStatsKey,
+            CChainStatsValue,
+        },
+        utils::Counted,
+    };
+    use std::collections::HashMap;
+
+    fn chain(calls: &[&str]) -> CallChain {
+        calls
+            .iter()
+            .map(|c| {
+                let (service, operation) = c.split_once('/').unwrap();
+                Call {
+                    service: service.to_string(),
+                    operation: operation.to_string(),
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    fn key(call_chain: CallChain, is_leaf: bool) -> CChainStatsKey {
+        CChainStatsKey {
+            call_chain,
+            caching_process: String::new(),
+            is_leaf,
+        }
+    }
+
+    /// non-rooted chain 'c/x' that matches the expected chains via 'a' and via 'b'
+    fn ambiguous() -> (CChainStatsKey, EndPointCChains, CallChain, CallChain) {
+        let via_a = chain(&["gw/GET", "a/get", "c/x"]);
+        let via_b = chain(&["gw/GET", "b/get", "c/x"]);
+        let expected = EndPointCChains::new(vec![
+            key(via_a.clone(), true),
+            key(via_a.clone(), false),
+            key(via_b.clone(), true),
+            key(chain(&["gw/GET", "a/get"]), false),
+        ]);
+        (key(chain(&["c/x"]), true), expected, via_a, via_b)
+    }
+
+    fn value(count: usize) -> CChainStatsValue {
+        let mut http_not_ok = Counted::new();
+        http_not_ok.add_item_count(500, 3);
+        http_not_ok.add_item_count(503, 1);
+        CChainStatsValue {
+            count,
+            duration_micros: (1..=count as i64).map(|d| d * 1000).collect(),
+            start_dt_micros: (0..count as i64).collect(),
+            cc_not_http_ok: 4,
+            http_not_ok,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn apportion_sums_to_total() {
+        assert_eq!(apportion(10, &[0.5, 0.3, 0.2]), vec![5, 3, 2]);
+        let parts = apportion(7, &[1.0, 1.0, 1.0]);
+        assert_eq!(parts.iter().sum::<usize>(), 7);
+        assert!(parts.iter().all(|p| *p == 2 || *p == 3));
+        assert_eq!(apportion(5, &[0.0, 0.0]), vec![0, 0]);
+    }
+
+    #[test]
+    fn distribute_interleaves() {
+        let parts = distribute(&[1, 2, 3, 4, 5, 6], &[4, 2]);
+        assert_eq!(parts[0].len(), 4);
+        assert_eq!(parts[1].len(), 2);
+        // the smaller part does not only get the last values
+        assert!(parts[1][0] < 4);
+    }
+
+    #[test]
+    fn parse_strategy() {
+        assert_eq!(
+            "Most-Probable".parse::<RepairStrategy>(),
+            Ok(RepairStrategy::MostProbable)
+        );
+        assert!("best".parse::<RepairStrategy>().is_err());
+    }
+
+    #[test]
+    fn weighted_candidate_shares() {
+        let (cck, expected, via_a, via_b) = ambiguous();
+        let observed = HashMap::from([(via_a.clone(), 30), (via_b.clone(), 10)]);
+        // the leaf and non-leaf variant of 'via_a' are one candidate, and the shorter chain does not match
+        let candidates = cck.weighted_candidates(&expected, &observed);
+        assert_eq!(candidates, vec![(via_a.clone(), 0.75), (via_b, 0.25)]);
+
+        // candidates that were never observed are dropped
+        let observed = HashMap::from([(via_a.clone(), 5)]);
+        assert_eq!(
+            cck.weighted_candidates(&expected, &observed),
+            vec![(via_a, 1.0)]
+        );
+        assert!(cck
+            .weighted_candidates(&expected, &HashMap::new())
+            .is_empty());
+    }
+
+    #[test]
+    fn probabilistic_repairs() {
+        let (cck, expected, via_a, via_b) = ambiguous();
+        let observed = HashMap::from([(via_a.clone(), 30), (via_b.clone(), 10)]);
+
+        let (fixed, parts) = repair_with_strategy(
+            cck.clone(),
+            value(8),
+            &expected,
+            &observed,
+            RepairStrategy::Unique,
+            0.8,
+        );
+        assert!(!fixed);
+        assert_eq!(parts[0].0, cck);
+
+        // most-probable: the share of 0.75 passes a threshold of 0.7, but not a threshold of 0.8 (NO FIX)
+        let (fixed, parts) = repair_with_strategy(
+            cck.clone(),
+            value(8),
+            &expected,
+            &observed,
+            RepairStrategy::MostProbable,
+            0.7,
+        );
+        assert!(fixed);
+        assert_eq!(parts.len(), 1);
+        let (k, v) = &parts[0];
+        assert_eq!(k.call_chain, via_a);
+        assert!(k.is_leaf);
+        assert!(v.rooted);
+        assert_eq!(v.count, 8);
+        assert_eq!(v.num_repaired, 8);
+        assert_eq!(v.repair_confidence, Some(0.75));
+
+        let (fixed, parts) = repair_with_strategy(
+            cck.clone(),
+            value(8),
+            &expected,
+            &observed,
+            RepairStrategy::MostProbable,
+            0.8,
+        );
+        assert!(!fixed);
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].0, cck);
+        assert!(!parts[0].1.rooted);
+        assert_eq!(parts[0].1.num_repaired, 0);
+
+        // proportional: the statistics are conserved over the parts
+        let orig = value(8);
+        let (fixed, parts) = repair_with_strategy(
+            cck.clone(),
+            orig.clone(),
+            &expected,
+            &observed,
+            RepairStrategy::Proportional,
+            0.8,
+        );
+        assert!(fixed);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].0.call_chain, via_a);
+        assert_eq!(parts[1].0.call_chain, via_b);
+        assert_eq!(parts[0].1.count, 6);
+        assert_eq!(parts[1].1.count, 2);
+        assert_eq!(parts[0].1.repair_confidence, Some(0.75));
+        assert_eq!(parts[1].1.repair_confidence, Some(0.25));
+        assert!(parts
+            .iter()
+            .all(|(_, v)| v.rooted && v.num_repaired == v.count));
+
+        let values: Vec<_> = parts.iter().map(|(_, v)| v).collect();
+        assert_eq!(values.iter().map(|v| v.count).sum::<usize>(), orig.count);
+        let mut durations: Vec<_> = values
+            .iter()
+            .flat_map(|v| v.duration_micros.iter().copied())
+            .collect();
+        durations.sort_unstable();
+        assert_eq!(durations, orig.duration_micros);
+        assert!(values
+            .iter()
+            .all(|v| v.duration_micros.len() == v.count && v.start_dt_micros.len() == v.count));
+        assert_eq!(
+            values.iter().map(|v| v.cc_not_http_ok).sum::<i32>(),
+            orig.cc_not_http_ok
+        );
+        for code in [500, 503] {
+            let cnt: usize = values
+                .iter()
+                .filter_map(|v| v.http_not_ok.iter().find(|(c, _)| **c == code))
+                .map(|(_, cnt)| *cnt)
+                .sum();
+            let orig_cnt = orig
+                .http_not_ok
+                .iter()
+                .find(|(c, _)| **c == code)
+                .unwrap()
+                .1;
+            assert_eq!(cnt, *orig_cnt);
+        }
+    }
+
+    #[test]
+    fn merge_weights_repair_confidence() {
+        // a rooted value with 4 unrepaired calls gets 6 calls repaired with confidence 0.75 and 2 with confidence 0.5
+        let mut rooted = value(4);
+        rooted.rooted = true;
+        let mut repaired = value(6);
+        repaired.set_repaired(0.75);
+        rooted.merge(repaired);
+        assert_eq!(rooted.count, 10);
+        assert_eq!(rooted.num_repaired, 6);
+        assert_eq!(rooted.repair_confidence, Some(0.75));
+
+        let mut repaired = value(2);
+        repaired.set_repaired(0.5);
+        rooted.merge(repaired);
+        assert_eq!(rooted.count, 12);
+        assert_eq!(rooted.num_repaired, 8);
+        // weighted by the number of repaired calls, not by the total count
+        assert_eq!(
+            rooted.repair_confidence,
+            Some((6.0 * 0.75 + 2.0 * 0.5) / 8.0)
+        );
+    }
+}
@@ -0,0 +1,691 @@
+//! Comparison of two statistics-files, for example the analyses before and after a release.
+//! The Process/Operations and call-chains of both files are aligned on their key. Added and removed items are listed, and for
+//! the common items the count, rate, error-rate and percentiles are compared. The latency-distributions are compared with a
+//! Mann-Whitney U test and the error-rates with a two-proportion z-test, and the regression gates of 'CompareParameters' decide
+//! whether an item regressed.
+use super::{file_windows::FileWindows, StatsRec};
+use crate::utils::{self, CsvFileBuffer};
+use serde::Serialize;
+use std::{collections::BTreeMap, error::Error, path::Path};
+
+/// The regression gates of a comparison
+#[derive(Debug, Clone)]
+pub struct CompareParameters {
+    /// significance level of the tests
+    pub alpha: f64,
+    /// maximal accepted ratio between the median latency after and before
+    pub max_latency_ratio: f64,
+    /// maximal accepted ratio between the P95 latency after and before
+    pub max_p95_ratio: f64,
+    /// maximal accepted (absolute) increase of the error-rate
+    pub max_error_rate_increase: f64,
+    /// minimal number of calls in both files before an item is checked for regressions
+    pub min_count: usize,
+    /// a Process/Operation that is no longer observed counts as a regression
+    pub fail_on_removed: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum CompareLevel {
+    ProcOper,
+    CallChain,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CompareStatus {
+    Added,
+    Removed,
+    Common,
+}
+
+/// The statistics of an item in one of the two files
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct CompareSide {
+    pub count: usize,
+    pub rate: Option<f64>,
+    pub error_rate: f64,
+    pub median_millis: Option<f64>,
+    pub p95_millis: Option<f64>,
+    #[serde(skip)]
+    errors: usize,
+}
+
+/// The comparison of a single Process/Operation or call-chain
+#[derive(Debug, Clone, Serialize)]
+pub struct CompareItem {
+    pub level: CompareLevel,
+    pub key: String,
+    pub status: CompareStatus,
+    pub before: Option<CompareSide>,
+    pub after: Option<CompareSide>,
+    /// Probability that a call after is slower than a call before (None when the raw durations are not available, for example for compact files)
+    pub prob_superiority: Option<f64>,
+    pub latency_p_value: Option<f64>,
+    pub error_p_value: Option<f64>,
+    pub latency_regression: bool,
+    pub error_regression: bool,
+    pub removed_regression: bool,
+}
+
+/// the values of an item that are needed for the comparison
+struct Sample<'a> {
+    side: CompareSide,
+    duration_micros: &'a [i64],
+}
+
+fn delta(before: Option<f64>, after: Option<f64>) -> Option<f64> {
+    Some(after? - before?)
+}
+
+fn ratio(before: Option<f64>, after: Option<f64>) -> Option<f64> {
+    let before = before?;
+    (before > 0.0).then(|| after.map(|after| after / before))?
+}
+
+impl CompareItem {
+    fn new(
+        level: CompareLevel,
+        key: String,
+        before: Option<Sample>,
+        after: Option<Sample>,
+        pars: &CompareParameters,
+    ) -> Self {
+        let status = match (&before, &after) {
+            (Some(_), Some(_)) => CompareStatus::Common,
+            (None, Some(_)) => CompareStatus::Added,
+            _ => CompareStatus::Removed,
+        };
+        let mut item = Self {
+            level,
+            key,
+            status,
+            before: before.as_ref().map(|s| s.side.clone()),
+            after: after.as_ref().map(|s| s.side.clone()),
+            prob_superiority: None,
+            latency_p_value: None,
+            error_p_value: None,
+            latency_regression: false,
+            error_regression: false,
+            removed_regression: status == CompareStatus::Removed
+                && level == CompareLevel::ProcOper
+                && pars.fail_on_removed,
+        };
+        let (Some(before), Some(after)) = (before, after) else {
+            return item;
+        };
+        let (b, a) = (&before.side, &after.side);
+
+        let to_millis = |durations: &[i64]| -> Vec<f64> {
+            durations.iter().map(|d| *d as f64 / 1000.0).collect()
+        };
+        let latency_test = utils::mann_whitney_u(
+            &to_millis(before.duration_micros),
+            &to_millis(after.duration_micros),
+        );
+        item.prob_superiority = latency_test.map(|mw| mw.prob_superiority);
+        item.latency_p_value = latency_test.map(|mw| mw.p_value);
+        item.error_p_value = utils::two_proportion_p_value(b.count, b.errors, a.count, a.errors);
+
+        if b.count >= pars.min_count && a.count >= pars.min_count {
+            let slower = ratio(b.median_millis, a.median_millis)
+                .map(|r| r > pars.max_latency_ratio)
+                .unwrap_or(false)
+                || ratio(b.p95_millis, a.p95_millis)
+                    .map(|r| r > pars.max_p95_ratio)
+                    .unwrap_or(false);
+            // without raw durations (compact files) the gate is based on the ratios only
+            let significant = latency_test
+                .map(|mw| mw.z > 0.0 && mw.p_value < pars.alpha)
+                .unwrap_or(true);
+            item.latency_regression = slower && significant;
+            item.error_regression = a.error_rate - b.error_rate > pars.max_error_rate_increase
+                && item.error_p_value.map(|p| p < pars.alpha).unwrap_or(false);
+        }
+        item
+    }
+
+    pub fn is_regression(&self) -> bool {
+        self.latency_regression || self.error_regression || self.removed_regression
+    }
+
+    fn get<T>(side: &Option<CompareSide>, f: impl Fn(&CompareSide) -> T) -> Option<T> {
+        side.as_ref().map(f)
+    }
+
+    /// header for report_stats_line output in ';'-separated csv-format
+    pub fn report_stats_line_header_str() -> &'static str {
+        "Level; Key; Status; Count_before; Count_after; Count_delta; Rate_before; Rate_after; Rate_delta; Error_rate_before; Error_rate_after; Error_rate_delta; Median_millis_before; Median_millis_after; Median_millis_delta; P95_millis_before; P95_millis_after; P95_millis_delta; Prob_superiority; Latency_p_value; Error_p_value; Regression"
+    }
+
+    /// reports the comparison for a single line in ';'-separated csv-format
+    pub fn report_stats_line(&self) -> String {
+        let count = |side: &Option<CompareSide>| Self::get(side, |s| s.count as f64);
+        let rate = |side: &Option<CompareSide>| Self::get(side, |s| s.rate).flatten();
+        let error_rate = |side: &Option<CompareSide>| Self::get(side, |s| s.error_rate);
+        let median = |side: &Option<CompareSide>| Self::get(side, |s| s.median_millis).flatten();
+        let p95 = |side: &Option<CompareSide>| Self::get(side, |s| s.p95_millis).flatten();
+        let triple = |f: &dyn Fn(&Option<CompareSide>) -> Option<f64>| {
+            let (b, a) = (f(&self.before), f(&self.after));
+            format!(
+                "{}; {}; {}",
+                utils::format_float_opt(b),
+                utils::format_float_opt(a),
+                utils::format_float_opt(delta(b, a))
+            )
+        };
+        format!(
+            "{:?}; {}; {:?}; {}; {}; {}; {}; {}; {}; {}; {}; {}",
+            self.level,
+            self.key,
+            self.status,
+            triple(&count),
+            triple(&rate),
+            triple(&error_rate),
+            triple(&median),
+            triple(&p95),
+            utils::format_float_opt(self.prob_superiority),
+            utils::format_float_opt(self.latency_p_value),
+            utils::format_float_opt(self.error_p_value),
+            self.regression_str(),
+        )
+    }
+
+    /// the reasons for a regression, separated by a comma (empty when this item did not regress)
+    pub fn regression_str(&self) -> String {
+        [
+            (self.latency_regression, "latency"),
+            (self.error_regression, "error-rate"),
+            (self.removed_regression, "removed"),
+        ]
+        .iter()
+        .filter(|(regr, _)| *regr)
+        .map(|(_, label)| *label)
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+}
+
+/// The comparison of two statistics-files
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsComparison {
+    pub before_traces: usize,
+    pub after_traces: usize,
+    /// ordered by level and key
+    pub items: Vec<CompareItem>,
+}
+
+/// collect the samples of all Process/Operations and call-chains of 'stats_rec' keyed by level and key
+fn get_samples(stats_rec: &StatsRec) -> BTreeMap<(CompareLevel, String), Sample<'_>> {
+    let sample = |count: usize,
+                  errors: usize,
+                  rate: Option<f64>,
+                  median_millis: Option<f64>,
+                  p95_millis: Option<f64>,
+                  duration_micros| Sample {
+        side: CompareSide {
+            count,
+            rate,
+            error_rate: if count > 0 {
+                errors as f64 / count as f64
+            } else {
+                0.0
+            },
+            median_millis,
+            p95_millis,
+            errors,
+        },
+        duration_micros,
+    };
+    let file_windows: &FileWindows = &stats_rec.file_windows;
+    let num_files = stats_rec.num_files;
+
+    let mut samples = BTreeMap::new();
+    stats_rec.stats.iter().for_each(|(process, os)| {
+        os.operation.0.iter().for_each(|(oper, pov)| {
+            let errors = pov.num_errors();
+            samples.insert(
+                (CompareLevel::ProcOper, format!("{process}/{oper}")),
+                sample(
+                    pov.count,
+                    errors,
+                    pov.get_avg_rate(file_windows, num_files),
+                    pov.get_median_millis(),
+                    pov.get_p_millis(0.95),
+                    &pov.duration_micros[..],
+                ),
+            );
+        });
+        os.call_chain.0.iter().for_each(|(cck, ccv)| {
+            let errors = ccv.cc_not_http_ok.max(ccv.cc_with_error_logs) as usize;
+            samples.insert(
+                (CompareLevel::CallChain, cck.call_chain_key()),
+                sample(
+                    ccv.count,
+                    errors,
+                    ccv.get_avg_rate(file_windows, num_files),
+                    ccv.get_median_millis(),
+                    ccv.get_p_millis(0.95),
+                    &ccv.duration_micros[..],
+                ),
+            );
+        });
+    });
+    samples
+}
+
+impl StatsComparison {
+    pub fn new(before: &StatsRec, after: &StatsRec, pars: &CompareParameters) -> Self {
+        let mut before_samples = get_samples(before);
+        let mut after_samples = get_samples(after);
+        let mut keys: Vec<_> = before_samples
+            .keys()
+            .chain(after_samples.keys())
+            .cloned()
+            .collect();
+        keys.sort_unstable();
+        keys.dedup();
+        let items = keys
+            .into_iter()
+            .map(|key| {
+                let b = before_samples.remove(&key);
+                let a = after_samples.remove(&key);
+                CompareItem::new(key.0, key.1, b, a, pars)
+            })
+            .collect();
+        Self {
+            before_traces: before.trace_id.len(),
+            after_traces: after.trace_id.len(),
+            items,
+        }
+    }
+
+    pub fn regressions(&self) -> impl Iterator<Item = &CompareItem> {
+        self.items.iter().filter(|item| item.is_regression())
+    }
+
+    fn num_items(&self, level: CompareLevel, status: CompareStatus) -> usize {
+        self.items
+            .iter()
+            .filter(|item| item.level == level && item.status == status)
+            .count()
+    }
+
+    pub fn write_csv(&self, path: &Path) {
+        let mut csv = CsvFileBuffer::new();
+        csv.add_toc(1);
+        csv.add_section(&format!(
+            "Comparison of {} traces before and {} traces after",
+            self.before_traces, self.after_traces
+        ));
+        csv.add_line(CompareItem::report_stats_line_header_str().to_owned());
+        self.items
+            .iter()
+            .for_each(|item| csv.add_line(item.report_stats_line()));
+        csv.write_file(path);
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let json = serde_json::to_string_pretty(self)?;
+        utils::write_string_to_file(path.to_str().expect("invalid file-path"), json)
+    }
+
+    /// A Markdown summary listing the regressions, the added and removed Process/Operations, and the deltas of the common Process/Operations.
+    /// The details of the call-chains are only included in the csv and json output.
+    pub fn to_markdown(&self) -> String {
+        let opt = |v: Option<f64>| v.map_or("-".to_owned(), |v| format!("{v:.3}"));
+        let mut md = vec![
+            "# Comparison of statistics".to_owned(),
+            String::new(),
+            "| | Process/Operations | Call-chains |".to_owned(),
+            "|---|---|---|".to_owned(),
+        ];
+        [
+            ("Added", CompareStatus::Added),
+            ("Removed", CompareStatus::Removed),
+            ("Common", CompareStatus::Common),
+        ]
+        .iter()
+        .for_each(|(label, status)| {
+            md.push(format!(
+                "| {label} | {} | {} |",
+                self.num_items(CompareLevel::ProcOper, *status),
+                self.num_items(CompareLevel::CallChain, *status)
+            ))
+        });
+        md.push(format!(
+            "\nTraces: {} before and {} after.\n",
+            self.before_traces, self.after_traces
+        ));
+
+        md.push("## Regressions\n".to_owned());
+        if self.regressions().next().is_none() {
+            md.push("No regressions found.\n".to_owned());
+        } else {
+            md.push("| Level | Key | Regression | Median before | Median after | P95 before | P95 after | Error-rate before | Error-rate after |".to_owned());
+            md.push("|---|---|---|---|---|---|---|---|---|".to_owned());
+            self.regressions().for_each(|item| {
+                let side = |s: &Option<CompareSide>, f: fn(&CompareSide) -> Option<f64>| {
+                    opt(s.as_ref().and_then(f))
+                };
+                md.push(format!(
+                    "| {:?} | {} | {} | {} | {} | {} | {} | {} | {} |",
+                    item.level,
+                    item.key.replace('|', "\\|"),
+                    item.regression_str(),
+                    side(&item.before, |s| s.median_millis),
+                    side(&item.after, |s| s.median_millis),
+                    side(&item.before, |s| s.p95_millis),
+                    side(&item.after, |s| s.p95_millis),
+                    side(&item.before, |s| Some(s.error_rate)),
+                    side(&item.after, |s| Some(s.error_rate)),
+                ))
+            });
+            md.push(String::new());
+        }
+
+        let proc_opers = |status: CompareStatus| {
+            self.items
+                .iter()
+                .filter(move |item| item.level == CompareLevel::ProcOper && item.status == status)
+        };
+        [
+            ("Added Process/Operations", CompareStatus::Added),
+            ("Removed Process/Operations", CompareStatus::Removed),
+        ]
+        .iter()
+        .for_each(|(title, status)| {
+            md.push(format!("## {title}\n"));
+            let start = md.len();
+            proc_opers(*status).for_each(|item| md.push(format!("* {}", item.key)));
+            if md.len() == start {
+                md.push("None.".to_owned());
+            }
+            md.push(String::new());
+        });
+
+        md.push("## Common Process/Operations\n".to_owned());
+        md.push("| Process/Operation | Count | Rate delta | Error-rate delta | Median delta (ms) | P95 delta (ms) | Prob. slower | Latency p-value |".to_owned());
+        md.push("|---|---|---|---|---|---|---|---|".to_owned());
+        proc_opers(CompareStatus::Common).for_each(|item| {
+            let (Some(b), Some(a)) = (&item.before, &item.after) else {
+                return;
+            };
+            md.push(format!(
+                "| {} | {} -> {} | {} | {} | {} | {} | {} | {} |",
+                item.key,
+                b.count,
+                a.count,
+                opt(delta(b.rate, a.rate)),
+                opt(Some(a.error_rate - b.error_rate)),
+                opt(delta(b.median_millis, a.median_millis)),
+                opt(delta(b.p95_millis, a.p95_millis)),
+                opt(item.prob_superiority),
+                opt(item.latency_p_value),
+            ))
+        });
+        md.push(String::new());
+        md.join("\n")
+    }
+
+    pub fn write_markdown(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        utils::write_string_to_file(
+            path.to_str().expect("invalid file-path"),
+            self.to_markdown(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CompareItem, CompareLevel, CompareParameters, CompareSide, CompareStatus, Sample,
+        StatsComparison,
+    };
+    use crate::{
+        processed::test_traces::{build_trace, TestSpan},
+        stats::{BasicStatsRec, StatsRec},
+    };
+    use serde_json::json;
+
+    fn pars() -> CompareParameters {
+        CompareParameters {
+            alpha: 0.01,
+            max_latency_ratio: 1.1,
+            max_p95_ratio: 1.25,
+            max_error_rate_increase: 0.01,
+            min_count: 20,
+            fail_on_removed: false,
+        }
+    }
+
+    fn side(count: usize, errors: usize, median_millis: f64) -> CompareSide {
+        CompareSide {
+            count,
+            rate: None,
+            error_rate: errors as f64 / count as f64,
+            median_millis: Some(median_millis),
+            p95_millis: Some(median_millis * 1.2),
+            errors,
+        }
+    }
+
+    fn sample(side: CompareSide, duration_micros: &[i64]) -> Option<Sample<'_>> {
+        Some(Sample {
+            side,
+            duration_micros,
+        })
+    }
+
+    fn item(
+        before: Option<Sample>,
+        after: Option<Sample>,
+        pars: &CompareParameters,
+    ) -> CompareItem {
+        CompareItem::new(
+            CompareLevel::ProcOper,
+            "svc/oper".to_owned(),
+            before,
+            after,
+            pars,
+        )
+    }
+
+    #[test]
+    fn latency_gate() {
+        let fast: Vec<_> = (0..30).map(|i| 10_000 + i * 10).collect();
+        let slow: Vec<_> = (0..30).map(|i| 20_000 + i * 10).collect();
+
+        // slower and significant
+        let regr = item(
+            sample(side(30, 0, 10.0), &fast),
+            sample(side(30, 0, 20.0), &slow),
+            &pars(),
+        );
+        assert_eq!(regr.status, CompareStatus::Common);
+        assert!(regr.latency_regression);
+        assert!(regr.prob_superiority.unwrap() > 0.99);
+        assert_eq!(regr.regression_str(), "latency");
+
+        // the ratios exceed the gate, but the raw durations do not differ significantly
+        let same = item(
+            sample(side(30, 0, 10.0), &fast),
+            sample(side(30, 0, 20.0), &fast),
+            &pars(),
+        );
+        assert!(!same.latency_regression);
+
+        // without raw durations (compact files) the ratios decide
+        let compact = item(
+            sample(side(30, 0, 10.0), &[]),
+            sample(side(30, 0, 20.0), &[]),
+            &pars(),
+        );
+        assert!(compact.latency_p_value.is_none());
+        assert!(compact.latency_regression);
+        let compact = item(
+            sample(side(30, 0, 10.0), &[]),
+            sample(side(30, 0, 10.5), &[]),
+            &pars(),
+        );
+        assert!(!compact.latency_regression);
+
+        // too few calls to be checked
+        let few = item(
+            sample(side(10, 0, 10.0), &fast[..10]),
+            sample(side(10, 0, 20.0), &slow[..10]),
+            &pars(),
+        );
+        assert!(!few.is_regression());
+    }
+
+    #[test]
+    fn error_rate_gate() {
+        let durations = vec![10_000; 100];
+        let regr = item(
+            sample(side(100, 1, 10.0), &durations),
+            sample(side(100, 20, 10.0), &durations),
+            &pars(),
+        );
+        assert!(regr.error_p_value.unwrap() < 0.01);
+        assert!(regr.error_regression);
+        assert!(!regr.latency_regression);
+
+        // a small increase is not significant
+        let small = item(
+            sample(side(100, 1, 10.0), &durations),
+            sample(side(100, 3, 10.0), &durations),
+            &pars(),
+        );
+        assert!(!small.error_regression);
+
+        let high_min_count = CompareParameters {
+            min_count: 200,
+            ..pars()
+        };
+        let few = item(
+            sample(side(100, 1, 10.0), &durations),
+            sample(side(100, 20, 10.0), &durations),
+            &high_min_count,
+        );
+        assert!(!few.error_regression);
+    }
+
+    #[test]
+    fn added_and_removed() {
+        let durations = vec![10_000; 30];
+        let added = item(None, sample(side(30, 0, 10.0), &durations), &pars());
+        assert_eq!(added.status, CompareStatus::Added);
+        assert!(!added.is_regression());
+
+        let removed = item(sample(side(30, 0, 10.0), &durations), None, &pars());
+        assert_eq!(removed.status, CompareStatus::Removed);
+        assert!(!removed.is_regression());
+
+        let fail_on_removed = CompareParameters {
+            fail_on_removed: true,
+            ..pars()
+        };
+        let removed = item(
+            sample(side(30, 0, 10.0), &durations),
+            None,
+            &fail_on_removed,
+        );
+        assert!(removed.removed_regression);
+        // only a removed Process/Operation counts as a regression
+        let removed_cc = CompareItem::new(
+            CompareLevel::CallChain,
+            "svc/oper".to_owned(),
+            sample(side(30, 0, 10.0), &durations),
+            None,
+            &fail_on_removed,
+        );
+        assert!(!removed_cc.is_regression());
+    }
+
+    /// statistics of 'num' traces 'gw/GET' -> 'child' with the duration of the child in micro-seconds
+    fn stats_rec(num: usize, child: &'static str, child_micros: i64, error: bool) -> StatsRec {
+        let mut sr = StatsRec::new(BasicStatsRec::default());
+        (0..num).for_each(|i| {
+            let status = if error { 500 } else { 200 };
+            let trace = build_trace(
+                &format!("{child}-{child_micros}-{i}"),
+                &[
+                    TestSpan::new("r", None, "gw/GET", i as i64 * 1_000_000, 50_000),
+                    TestSpan::new(
+                        "c",
+                        Some("r"),
+                        child,
+                        i as i64 * 1_000_000 + 10,
+                        child_micros + i as i64,
+                    )
+                    .tag("http.status_code", json!(status)),
+                ],
+            );
+            sr.extend_statistics(&trace, false);
+        });
+        sr
+    }
+
+    #[test]
+    fn align_before_and_after() {
+        let mut before = stats_rec(30, "svc/old", 10_000, false);
+        before.merge(stats_rec(30, "svc/slow", 10_000, false));
+        let mut after = stats_rec(30, "svc/new", 10_000, false);
+        after.merge(stats_rec(30, "svc/slow", 30_000, true));
+
+        let cmp = StatsComparison::new(&before, &after, &pars());
+        assert_eq!(cmp.before_traces, 60);
+        assert_eq!(cmp.after_traces, 60);
+        let status = |level: CompareLevel, key: &str| {
+            cmp.items
+                .iter()
+                .find(|item| item.level == level && item.key == key)
+                .map(|item| item.status)
+        };
+        assert_eq!(
+            status(CompareLevel::ProcOper, "gw/GET"),
+            Some(CompareStatus::Common)
+        );
+        assert_eq!(
+            status(CompareLevel::ProcOper, "svc/old"),
+            Some(CompareStatus::Removed)
+        );
+        assert_eq!(
+            status(CompareLevel::ProcOper, "svc/new"),
+            Some(CompareStatus::Added)
+        );
+        assert_eq!(
+            status(CompareLevel::ProcOper, "svc/slow"),
+            Some(CompareStatus::Common)
+        );
+        // the items are ordered by level and key
+        assert!(cmp
+            .items
+            .windows(2)
+            .all(|w| (w[0].level, &w[0].key) < (w[1].level, &w[1].key)));
+        assert!(cmp.items.iter().any(
+            |item| item.level == CompareLevel::CallChain && item.status == CompareStatus::Added
+        ));
+
+        let regressions: Vec<_> = cmp
+            .regressions()
+            .filter(|item| item.level == CompareLevel::ProcOper)
+            .map(|item| (item.key.as_str(), item.regression_str()))
+            .collect();
+        assert_eq!(
+            regressions,
+            vec![("svc/slow", "latency, error-rate".to_owned())]
+        );
+
+        // a compact candidate is gated on the ratios of the sketches
+        after.compact();
+        let cmp = StatsComparison::new(&before, &after, &pars());
+        let slow = cmp
+            .items
+            .iter()
+            .find(|item| item.key == "svc/slow")
+            .unwrap();
+        assert!(slow.latency_p_value.is_none());
+        assert!(slow.latency_regression);
+    }
+}
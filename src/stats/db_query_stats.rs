@@ -0,0 +1,192 @@
+//! Statistics per database query. Database spans are recognized by their 'db.statement' tag and grouped by the
+//! database instance and the fingerprint of the normalized statement, such that queries that only differ in their literal values are counted together.
+use super::FileWindows;
+use crate::{
+    processed::{Span, Trace},
+    utils::{self, CountStats, TimeStats},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path};
+
+/// The tag that contains the SQL-statement of a database span
+const DB_STATEMENT_TAG: &str = "db.statement";
+/// The tags that identify the database instance, in order of preference.
+const DB_INSTANCE_TAGS: [&str; 2] = ["db.instance", "db.name"];
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct DbQueryKey {
+    /// the database instance (or '-' when it is unknown)
+    pub db_instance: String,
+    /// the fingerprint (hash) of the normalized statement
+    pub fingerprint: String,
+}
+
+impl DbQueryKey {
+    /// the key as used in the stitched data
+    pub fn to_key_str(&self) -> String {
+        format!("{}/{}", self.db_instance, self.fingerprint)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct DbQueryStatsValue {
+    /// The normalized statement (literals replaced by '?')
+    pub statement: String,
+    pub count: usize,
+    pub duration_micros: Vec<i64>,
+    pub start_dt_micros: Vec<i64>,
+    /// Number of executions of this query for each trace that executes the query at least once
+    pub calls_per_trace: Vec<usize>,
+    /// the number of executions per end-point (root-call of the trace)
+    pub endpoints: HashMap<String, usize>,
+}
+
+impl DbQueryStatsValue {
+    /// number of traces that execute this query
+    pub fn num_traces(&self) -> usize {
+        self.calls_per_trace.len()
+    }
+
+    pub fn get_avg_rate(&self, file_windows: &FileWindows, num_files: i32) -> Option<f64> {
+        file_windows.get_rate(&self.start_dt_micros, num_files)
+    }
+
+    pub fn get_min_millis(&self) -> f64 {
+        TimeStats(&self.duration_micros).get_min_millis()
+    }
+
+    pub fn get_avg_millis(&self) -> f64 {
+        TimeStats(&self.duration_micros).get_avg_millis()
+    }
+
+    pub fn get_median_millis(&self) -> Option<f64> {
+        TimeStats(&self.duration_micros).get_median_millis()
+    }
+
+    pub fn get_p_millis(&self, p: f64) -> Option<f64> {
+        TimeStats(&self.duration_micros).get_p_millis(p)
+    }
+
+    pub fn get_max_millis(&self) -> f64 {
+        TimeStats(&self.duration_micros).get_max_millis()
+    }
+
+    /// header for report_stats_line output in ';'-separated csv-format
+    pub fn report_stats_line_header_str() -> &'static str {
+        "Db_instance; Fingerprint; Count; Num_traces; Mean_calls_per_trace; Max_calls_per_trace; Min_millis; Median_millis; Avg_millis; P95_millis; P99_millis; Max_millis; Total_millis; Endpoints; Statement"
+    }
+
+    /// reports the statistics for a single line in ';'-separated csv-format
+    pub fn report_stats_line(&self, key: &DbQueryKey) -> String {
+        let ts = TimeStats(&self.duration_micros);
+        let cs = CountStats(&self.calls_per_trace);
+        let mut endpoints: Vec<_> = self.endpoints.iter().collect();
+        endpoints.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let endpoints = endpoints
+            .into_iter()
+            .map(|(ep, cnt)| format!("{ep}({cnt})"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let total_millis = self.duration_micros.iter().sum::<i64>() as f64 / 1000.0;
+        format!(
+            "{}; {}; {}; {}; {}; {}; {}; {}; {}; {}; {}; {}; {}; {endpoints}; {}",
+            key.db_instance,
+            key.fingerprint,
+            self.count,
+            self.num_traces(),
+            cs.get_mean_str(),
+            cs.get_max(),
+            ts.get_min_millis_str(),
+            ts.get_median_millis_str(),
+            ts.get_avg_millis_str(),
+            ts.get_p_millis_str(0.95),
+            ts.get_p_millis_str(0.99),
+            ts.get_max_millis_str(),
+            utils::format_float(total_millis),
+            self.statement
+        )
+    }
+}
+
+/// get the database instance of a span
+fn get_db_instance(span: &Span) -> String {
+    DB_INSTANCE_TAGS
+        .iter()
+        .find_map(|tag| span.attributes.get(*tag).filter(|v| !v.is_empty()))
+        .cloned()
+        .unwrap_or_else(|| "-".to_owned())
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct DbQueryStats(pub HashMap<DbQueryKey, DbQueryStatsValue>);
+
+impl DbQueryStats {
+    /// merge the query statistics of 'other' into these statistics
+    pub fn merge(&mut self, other: DbQueryStats) {
+        other.0.into_iter().for_each(|(k, mut dqv)| {
+            let curr = self.0.entry(k).or_default();
+            if curr.statement.is_empty() {
+                curr.statement = dqv.statement;
+            }
+            curr.count += dqv.count;
+            curr.duration_micros.append(&mut dqv.duration_micros);
+            curr.start_dt_micros.append(&mut dqv.start_dt_micros);
+            curr.calls_per_trace.append(&mut dqv.calls_per_trace);
+            dqv.endpoints
+                .into_iter()
+                .for_each(|(ep, cnt)| *curr.endpoints.entry(ep).or_default() += cnt);
+        })
+    }
+
+    /// extend the query statistics with the database spans of a single trace
+    pub fn extend(&mut self, trace: &Trace) {
+        let mut trace_calls: HashMap<DbQueryKey, usize> = HashMap::new();
+        trace
+            .spans
+            .items
+            .iter()
+            .filter_map(|span| {
+                span.attributes
+                    .get(DB_STATEMENT_TAG)
+                    .filter(|stmt| !stmt.is_empty())
+                    .map(|stmt| (span, stmt))
+            })
+            .for_each(|(span, stmt)| {
+                let statement = utils::normalize_sql(stmt);
+                let key = DbQueryKey {
+                    db_instance: get_db_instance(span),
+                    fingerprint: utils::string_hash(&statement),
+                };
+                let dqv = self.0.entry(key.clone()).or_default();
+                if dqv.statement.is_empty() {
+                    dqv.statement = statement;
+                }
+                dqv.count += 1;
+                dqv.duration_micros.push(span.duration_micros);
+                dqv.start_dt_micros
+                    .push(span.start_dt.and_utc().timestamp_micros());
+                *dqv.endpoints.entry(trace.root_call.to_owned()).or_default() += 1;
+                *trace_calls.entry(key).or_default() += 1;
+            });
+        trace_calls.into_iter().for_each(|(key, cnt)| {
+            if let Some(dqv) = self.0.get_mut(&key) {
+                dqv.calls_per_trace.push(cnt)
+            }
+        });
+    }
+
+    /// write the query statistics, ranked on the total time spent per query, to a csv-file.
+    pub fn write_csv(&self, path: &Path) {
+        let mut csv = utils::CsvFileBuffer::new();
+        csv.add_section("Database queries grouped by instance and normalized statement (ranked on total time spent)");
+        csv.add_line(DbQueryStatsValue::report_stats_line_header_str().to_owned());
+        let mut data: Vec<_> = self.0.iter().collect();
+        data.sort_by(|a, b| {
+            let total = |dqv: &DbQueryStatsValue| dqv.duration_micros.iter().sum::<i64>();
+            total(b.1).cmp(&total(a.1)).then(a.0.cmp(b.0))
+        });
+        data.into_iter()
+            .for_each(|(k, dqv)| csv.add_line(dqv.report_stats_line(k)));
+        csv.write_file(path);
+    }
+}
@@ -0,0 +1,75 @@
+//! Statistics split by span-attribute dimensions, such as 'http.method', 'tenant' or 'service.version'.
+//! For each configured dimension the ProcOperStats and CChainStats of a Process are split in sub-series per value of the attribute.
+//! This allows comparing, for example, tenants or a canary-release and the stable release within a single dataset.
+//! The dimensions are set via set_split_dimensions (default: no splitting).
+use super::{call_chain::CChainStats, proc_oper_stats::ProcOperStats};
+use crate::processed::Span;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Mutex};
+
+static SPLIT_DIMENSIONS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Set the attribute keys that are used to split the statistics (an empty list disables the splitting).
+pub fn set_split_dimensions(dimensions: Vec<String>) {
+    let mut guard = SPLIT_DIMENSIONS.lock().unwrap();
+    *guard = dimensions
+}
+
+pub fn get_split_dimensions() -> Vec<String> {
+    SPLIT_DIMENSIONS.lock().unwrap().clone()
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct DimensionKey {
+    /// the attribute key, for example 'tenant'
+    pub dimension: String,
+    /// the value of the attribute
+    pub value: String,
+}
+
+impl DimensionKey {
+    /// the label of the dimension as shown in the csv-files and breakdown-lists
+    pub fn to_key_str(&self) -> String {
+        format!("{}={}", self.dimension, self.value)
+    }
+}
+
+/// Get the dimension-keys of a span. The value of a dimension is looked up in the span-tags first and subsequently in the tags of the process.
+/// Dimensions that are not present on the span are skipped.
+pub fn get_dimension_keys(span: &Span, dimensions: &[String]) -> Vec<DimensionKey> {
+    dimensions
+        .iter()
+        .filter_map(|dim| {
+            span.attributes
+                .get(dim)
+                .or_else(|| span.process.as_ref().and_then(|p| p.other_tags.get(dim)))
+                .filter(|v| !v.is_empty())
+                .map(|value| DimensionKey {
+                    dimension: dim.to_owned(),
+                    value: value.to_owned(),
+                })
+        })
+        .collect()
+}
+
+/// The statistics of a Process for a single value of a dimension
+#[derive(Debug, Default, Clone)]
+pub struct DimensionStatsValue {
+    pub operation: ProcOperStats,
+    pub call_chain: CChainStats,
+}
+
+/// The statistics of a Process split by dimension-value
+#[derive(Debug, Default, Clone)]
+pub struct DimensionStats(pub HashMap<DimensionKey, DimensionStatsValue>);
+
+impl DimensionStats {
+    /// merge the statistics per dimension-value of 'other' into these statistics
+    pub fn merge(&mut self, other: DimensionStats) {
+        other.0.into_iter().for_each(|(k, dsv)| {
+            let curr = self.0.entry(k).or_default();
+            curr.operation.merge(dsv.operation);
+            curr.call_chain.merge(dsv.call_chain);
+        })
+    }
+}
@@ -0,0 +1,162 @@
+//! Fan-out statistics per parent-operation -> child-operation edge.
+//! For each invocation of a parent (a span) we count how often each child Process/Operation is called directly.
+//! These per-invocation counts are used to detect chatty edges and N+1 patterns (fan-out that grows with the payload).
+use crate::{
+    processed::Spans,
+    utils::{self, CountStats},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Minimal mean number of calls per invocation before an edge is considered chatty
+const CHATTY_MIN_MEAN: f64 = 2.0;
+/// Minimal correlation between the fan-out and the duration of the parent to flag a chatty edge as an N+1 pattern
+const N_PLUS_1_MIN_CORRELATION: f64 = 0.5;
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct FanOutKey {
+    /// the calling Process/Operation
+    pub parent: String,
+    /// the called Process/Operation
+    pub child: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct FanOutStatsValue {
+    /// Number of calls to the child for each invocation of the parent that calls this child at least once
+    pub calls_per_invocation: Vec<usize>,
+    /// Duration of the parent (in microseconds) for the same invocations, used as a proxy for the payload size
+    pub parent_duration_micros: Vec<i64>,
+}
+
+impl FanOutStatsValue {
+    /// number of invocations of the parent that called the child
+    pub fn num_invocations(&self) -> usize {
+        self.calls_per_invocation.len()
+    }
+
+    /// total number of calls over the parent-child edge
+    pub fn num_calls(&self) -> usize {
+        self.calls_per_invocation.iter().sum()
+    }
+
+    /// correlation between the fan-out and the duration of the parent
+    pub fn get_correlation(&self) -> Option<f64> {
+        CountStats(&self.calls_per_invocation).get_correlation(&self.parent_duration_micros)
+    }
+
+    /// A chatty edge calls the child multiple times per invocation on average
+    pub fn is_chatty(&self) -> bool {
+        CountStats(&self.calls_per_invocation).get_mean() >= CHATTY_MIN_MEAN
+    }
+
+    /// An N+1 pattern is a chatty edge where the fan-out varies and grows with the work done by the parent
+    pub fn is_n_plus_1(&self) -> bool {
+        let cs = CountStats(&self.calls_per_invocation);
+        self.is_chatty()
+            && cs.get_max() > cs.get_min()
+            && self
+                .get_correlation()
+                .map(|corr| corr >= N_PLUS_1_MIN_CORRELATION)
+                .unwrap_or(false)
+    }
+
+    /// Score used to rank the chatty edges. It is the number of calls that could be saved when each invocation would do a single (batched) call.
+    pub fn excess_calls(&self) -> usize {
+        self.num_calls() - self.num_invocations()
+    }
+
+    /// header for report_stats_line output in ';'-separated csv-format
+    pub fn report_stats_line_header_str() -> &'static str {
+        "Parent; Child; Num_invocations; Num_calls; Mean_calls; P95_calls; Max_calls; Corr_parent_duration; Pattern"
+    }
+
+    /// reports the statistics for a single line in ';'-separated csv-format
+    pub fn report_stats_line(&self, key: &FanOutKey) -> String {
+        let cs = CountStats(&self.calls_per_invocation);
+        let pattern = if self.is_n_plus_1() {
+            "N+1"
+        } else if self.is_chatty() {
+            "chatty"
+        } else {
+            ""
+        };
+        format!(
+            "{}; {}; {}; {}; {}; {}; {}; {}; {pattern}",
+            key.parent,
+            key.child,
+            self.num_invocations(),
+            self.num_calls(),
+            cs.get_mean_str(),
+            cs.get_p(0.95).map(|p| p.to_string()).unwrap_or_default(),
+            cs.get_max(),
+            utils::format_float_opt(self.get_correlation()),
+        )
+    }
+}
+
+/// The fan-out statistics over all parent-child edges observed.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct FanOutStats(pub HashMap<FanOutKey, FanOutStatsValue>);
+
+impl FanOutStats {
+    /// merge the fan-out statistics of 'other' into these statistics
+    pub fn merge(&mut self, other: FanOutStats) {
+        other.0.into_iter().for_each(|(k, mut fov)| {
+            let curr = self.0.entry(k).or_default();
+            curr.calls_per_invocation
+                .append(&mut fov.calls_per_invocation);
+            curr.parent_duration_micros
+                .append(&mut fov.parent_duration_micros);
+        })
+    }
+
+    /// Extend the statistics with the parent-child edges of a single trace.
+    /// The 'children' are the direct children of each span as returned by Spans::get_children
+    pub fn extend(&mut self, spans: &Spans, children: &[Vec<usize>]) {
+        let proc_oper = |idx: usize| {
+            let span = &spans.items[idx];
+            format!("{}/{}", span.get_process_str(), span.operation_name)
+        };
+        children
+            .iter()
+            .enumerate()
+            .filter(|(_, ch)| !ch.is_empty())
+            .for_each(|(idx, ch)| {
+                let parent = proc_oper(idx);
+                let parent_duration = spans.items[idx].duration_micros;
+                let counts = ch.iter().fold(HashMap::new(), |mut counts, child_idx| {
+                    counts
+                        .entry(proc_oper(*child_idx))
+                        .and_modify(|cnt| *cnt += 1)
+                        .or_insert(1_usize);
+                    counts
+                });
+                counts.into_iter().for_each(|(child, cnt)| {
+                    let key = FanOutKey {
+                        parent: parent.clone(),
+                        child,
+                    };
+                    let fov = self.0.entry(key).or_default();
+                    fov.calls_per_invocation.push(cnt);
+                    fov.parent_duration_micros.push(parent_duration);
+                });
+            });
+    }
+
+    /// the chatty edges (including N+1 patterns) ranked on the number of excess calls (highest first)
+    pub fn chatty_edges(&self) -> Vec<(&FanOutKey, &FanOutStatsValue)> {
+        let mut edges: Vec<_> = self.0.iter().filter(|(_, v)| v.is_chatty()).collect();
+        edges.sort_by(|a, b| {
+            b.1.excess_calls()
+                .cmp(&a.1.excess_calls())
+                .then(a.0.cmp(b.0))
+        });
+        edges
+    }
+
+    /// number of edges flagged as an N+1 pattern
+    pub fn num_n_plus_1(&self) -> usize {
+        self.0.values().filter(|v| v.is_n_plus_1()).count()
+    }
+}
@@ -0,0 +1,238 @@
+//! Frozen copy of the StatsRecJson layout of version 0.2 (before the fan-out, child-concurrency, compact and repair fields were added).
+//! Bincode is a positional format that ignores '#[serde(default)]', so files in this layout can only be read via these structs.
+//! Do not modify these structs, as that breaks the reading of existing files.
+use super::operation_stats_json::{OperationStatsJson, StatsRecJson};
+use crate::{
+    stats::{
+        call_chain::{CChainStatsKey, CChainStatsValue, ExpectedRoots},
+        ProcOperStats, ProcOperStatsValue,
+    },
+    utils::Counted,
+    view_api::Version,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LegacyProcOperStatsValue {
+    pub count: usize,
+    pub num_traces: usize,
+    pub duration_micros: Vec<i64>,
+    pub start_dt_micros: Vec<i64>,
+    pub num_not_http_ok: i32,
+    pub num_with_error_logs: i32,
+    pub http_not_ok_codes: Counted<i16>,
+    pub error_logs: Counted<String>,
+}
+
+impl From<LegacyProcOperStatsValue> for ProcOperStatsValue {
+    fn from(lpov: LegacyProcOperStatsValue) -> Self {
+        Self {
+            count: lpov.count,
+            num_traces: lpov.num_traces,
+            duration_micros: lpov.duration_micros,
+            start_dt_micros: lpov.start_dt_micros,
+            num_not_http_ok: lpov.num_not_http_ok,
+            num_with_error_logs: lpov.num_with_error_logs,
+            http_not_ok_codes: lpov.http_not_ok_codes,
+            error_logs: lpov.error_logs,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LegacyCChainStatsValue {
+    pub count: usize,
+    pub depth: usize,
+    pub duration_micros: Vec<i64>,
+    pub start_dt_micros: Vec<i64>,
+    pub looped: Vec<String>,
+    pub rooted: bool,
+    pub expect_root: ExpectedRoots,
+    pub cc_not_http_ok: i32,
+    pub cc_with_error_logs: i32,
+    pub http_not_ok: Counted<i16>,
+    pub error_logs: Counted<String>,
+}
+
+impl From<LegacyCChainStatsValue> for CChainStatsValue {
+    fn from(lccv: LegacyCChainStatsValue) -> Self {
+        Self {
+            count: lccv.count,
+            depth: lccv.depth,
+            duration_micros: lccv.duration_micros,
+            start_dt_micros: lccv.start_dt_micros,
+            looped: lccv.looped,
+            rooted: lccv.rooted,
+            expect_root: lccv.expect_root,
+            cc_not_http_ok: lccv.cc_not_http_ok,
+            cc_with_error_logs: lccv.cc_with_error_logs,
+            http_not_ok: lccv.http_not_ok,
+            error_logs: lccv.error_logs,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LegacyOperationStatsJson {
+    pub method: HashMap<String, LegacyProcOperStatsValue>,
+    pub num_traces: usize,
+    pub num_received_calls: usize,
+    pub num_outbound_calls: usize,
+    pub num_unknown_calls: usize,
+    pub call_chain: Vec<(CChainStatsKey, LegacyCChainStatsValue)>,
+}
+
+impl From<LegacyOperationStatsJson> for OperationStatsJson {
+    fn from(losj: LegacyOperationStatsJson) -> Self {
+        Self {
+            method: ProcOperStats(
+                losj.method
+                    .into_iter()
+                    .map(|(k, v)| (k, v.into()))
+                    .collect(),
+            ),
+            num_traces: losj.num_traces,
+            num_received_calls: losj.num_received_calls,
+            num_outbound_calls: losj.num_outbound_calls,
+            num_unknown_calls: losj.num_unknown_calls,
+            call_chain: losj
+                .call_chain
+                .into_iter()
+                .map(|(k, v)| (k, v.into()))
+                .collect(),
+            dimensions: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LegacyStatsRecJson {
+    pub version: Version,
+    pub trace_id: Vec<String>,
+    pub root_call: Vec<String>,
+    pub num_spans: Vec<usize>,
+    pub num_files: i32,
+    pub num_endpoints: usize,
+    pub num_incomplete_traces: usize,
+    pub num_call_chains: usize,
+    pub init_num_unrooted_cc: usize,
+    pub num_fixes: usize,
+    pub num_unrooted_cc_after_fixes: usize,
+    pub start_dt: Vec<i64>,
+    pub end_dt: Vec<i64>,
+    pub duration_micros: Vec<i64>,
+    pub time_to_respond_micros: Vec<i64>,
+    pub caching_processes: Vec<String>,
+    pub stats: HashMap<String, LegacyOperationStatsJson>,
+}
+
+impl From<LegacyStatsRecJson> for StatsRecJson {
+    fn from(lsrj: LegacyStatsRecJson) -> Self {
+        Self {
+            version: lsrj.version,
+            trace_id: lsrj.trace_id,
+            root_call: lsrj.root_call,
+            num_spans: lsrj.num_spans,
+            num_files: lsrj.num_files,
+            num_endpoints: lsrj.num_endpoints,
+            num_incomplete_traces: lsrj.num_incomplete_traces,
+            num_call_chains: lsrj.num_call_chains,
+            init_num_unrooted_cc: lsrj.init_num_unrooted_cc,
+            num_fixes: lsrj.num_fixes,
+            num_unrooted_cc_after_fixes: lsrj.num_unrooted_cc_after_fixes,
+            start_dt: lsrj.start_dt,
+            end_dt: lsrj.end_dt,
+            duration_micros: lsrj.duration_micros,
+            time_to_respond_micros: lsrj.time_to_respond_micros,
+            caching_processes: lsrj.caching_processes,
+            stats: lsrj.stats.into_iter().map(|(k, v)| (k, v.into())).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::operation_stats_json::{StatsRecJson, STATS_REC_VERSION},
+        LegacyCChainStatsValue, LegacyOperationStatsJson, LegacyProcOperStatsValue,
+        LegacyStatsRecJson,
+    };
+    use crate::{stats::call_chain::CChainStatsKey, view_api::Version};
+    use std::{collections::HashMap, fs, io};
+
+    fn write_bincode<T: serde::Serialize>(file_name: &str, value: &T) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("{}_{file_name}", std::process::id()));
+        let writer = io::BufWriter::new(fs::File::create(&path).unwrap());
+        bincode::serialize_into(writer, value).unwrap();
+        path
+    }
+
+    #[test]
+    fn read_baseline_bincode() {
+        let key = CChainStatsKey {
+            call_chain: Vec::new(),
+            caching_process: String::new(),
+            is_leaf: true,
+        };
+        let lsrj = LegacyStatsRecJson {
+            version: Version::default(),
+            trace_id: vec!["t1".to_string()],
+            stats: HashMap::from([(
+                "svc".to_string(),
+                LegacyOperationStatsJson {
+                    method: HashMap::from([(
+                        "oper".to_string(),
+                        LegacyProcOperStatsValue {
+                            count: 2,
+                            duration_micros: vec![100, 300],
+                            ..Default::default()
+                        },
+                    )]),
+                    num_traces: 1,
+                    call_chain: vec![(
+                        key.clone(),
+                        LegacyCChainStatsValue {
+                            count: 2,
+                            duration_micros: vec![100, 300],
+                            cc_not_http_ok: 1,
+                            ..Default::default()
+                        },
+                    )],
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        };
+        let path = write_bincode("baseline_stats.bincode", &lsrj);
+        let srj = StatsRecJson::read_file(&path.clone().into_os_string()).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(srj.version, Version::default());
+        assert_eq!(srj.trace_id, vec!["t1".to_string()]);
+        let osj = &srj.stats["svc"];
+        assert_eq!(osj.num_traces, 1);
+        let pov = &osj.method.0["oper"];
+        assert_eq!(pov.count, 2);
+        assert_eq!(pov.duration_micros, vec![100, 300]);
+        assert!(pov.duration_sketch.is_none());
+        let (cck, ccv) = &osj.call_chain[0];
+        assert_eq!(cck, &key);
+        assert_eq!(ccv.cc_not_http_ok, 1);
+        assert_eq!(ccv.num_repaired, 0);
+
+        // the current layout is read without the fallback
+        let srj = StatsRecJson {
+            version: STATS_REC_VERSION,
+            ..srj
+        };
+        let path = write_bincode("current_stats.bincode", &srj);
+        let srj = StatsRecJson::read_file(&path.clone().into_os_string()).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(srj.version, STATS_REC_VERSION);
+        assert_eq!(srj.stats["svc"].method.0["oper"].count, 2);
+    }
+}
@@ -3,14 +3,19 @@
 mod bincode;
 mod bson;
 mod json;
+mod legacy;
 mod operation_stats_json;
 
-use super::StatsRec;
+use super::{stats_rec::compact_stats_enabled, StatsRec};
 
-pub use operation_stats_json::{OperationStatsJson, StatsRecJson};
+pub use operation_stats_json::{OperationStatsJson, StatsRecJson, STATS_REC_VERSION};
 
 /// write the (complete) statistics to either '.json'  or '.bincode'
-pub fn write_stats(file_name: &str, stats: StatsRec, ext: &str) {
+/// When compact statistics are enabled (see set_compact_stats) the durations and start-times are stored as sketches and time-buckets.
+pub fn write_stats(file_name: &str, mut stats: StatsRec, ext: &str) {
+    if compact_stats_enabled() {
+        stats.compact();
+    }
     let file_name = file_name.replace(".csv", &format!(".{ext}"));
     match ext {
         "bson" => bson::dump_file(&file_name, stats),
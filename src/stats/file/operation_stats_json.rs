@@ -2,10 +2,13 @@
 //! The StatsRec can not be stored in JSON as:
 //!    1. It contains a HashMap (call-chain) with non-string keys
 //!    2. It contains date-times which can not represented in JSON (we will store them as a i64, just like we had in the Jaeger-JSON file)
+use super::legacy::LegacyStatsRecJson;
 use crate::{
     stats::{
-        call_chain::{CChainStatsKey, CChainStatsValue},
-        OperationStats, ProcOperStats, StatsRec,
+        call_chain::{CChainStats, CChainStatsKey, CChainStatsValue},
+        DbQueryKey, DbQueryStatsValue, DimensionKey, DimensionStatsValue, FanOutKey,
+        FanOutStatsValue, FileWindow, InstanceKey, OperationStats, ProcOperStats,
+        ProcOperStatsValue, StatsRec,
     },
     utils,
     view_api::Version,
@@ -13,6 +16,10 @@ use crate::{
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, error::Error, ffi::OsString, fs::File, io, path::Path};
 
+/// Version of the StatsRecJson-layout written by this version of the tool.
+/// Bincode-files with a lower version use the layout of version 0.2 and are read via the LegacyStatsRecJson.
+pub const STATS_REC_VERSION: Version = Version { major: 0, minor: 4 };
+
 /// The OperationStatsJson is used as an intermediate value for storage as JSON does not allow compound hashmap-keys.
 /// Thus Hashmap is flattened to a vector of key-value pairs. For more details on the fields see OperationStats.
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -24,6 +31,34 @@ pub struct OperationStatsJson {
     pub num_unknown_calls: usize,
     //    method_cache_suffix: HashMap<String, usize>,  // methods in a cache-chain have a suffix.
     pub call_chain: Vec<(CChainStatsKey, CChainStatsValue)>,
+    /// statistics split per dimension-value (default to support files written by older versions)
+    #[serde(default)]
+    pub dimensions: Vec<(DimensionKey, DimensionStatsValueJson)>,
+}
+
+/// Variant of the DimensionStatsValue that can be stored in JSON (which does not allow compound keys in the call-chain hashmap)
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DimensionStatsValueJson {
+    pub operation: ProcOperStats,
+    pub call_chain: Vec<(CChainStatsKey, CChainStatsValue)>,
+}
+
+impl From<DimensionStatsValue> for DimensionStatsValueJson {
+    fn from(dsv: DimensionStatsValue) -> Self {
+        Self {
+            operation: dsv.operation,
+            call_chain: dsv.call_chain.0.into_iter().collect(),
+        }
+    }
+}
+
+impl From<DimensionStatsValueJson> for DimensionStatsValue {
+    fn from(dsj: DimensionStatsValueJson) -> Self {
+        Self {
+            operation: dsj.operation,
+            call_chain: CChainStats(dsj.call_chain.into_iter().collect()),
+        }
+    }
 }
 
 impl From<OperationStats> for OperationStatsJson {
@@ -35,6 +70,12 @@ impl From<OperationStats> for OperationStatsJson {
             num_outbound_calls: st.num_outbound_calls,
             num_unknown_calls: st.num_unknown_calls,
             call_chain: st.call_chain.0.into_iter().collect(),
+            dimensions: st
+                .dimensions
+                .0
+                .into_iter()
+                .map(|(k, v)| (k, v.into()))
+                .collect(),
         }
     }
 }
@@ -64,6 +105,18 @@ pub struct StatsRecJson {
     pub time_to_respond_micros: Vec<i64>,
     pub caching_processes: Vec<String>,
     pub stats: HashMap<String, OperationStatsJson>, // hashmap base on the leaf process (as that is the initial level of reporting)
+    /// fan-out per parent-child edge. Flattened as JSON does not allow compound keys (default to support files written by older versions)
+    #[serde(default)]
+    pub fan_out: Vec<(FanOutKey, FanOutStatsValue)>,
+    /// statistics per instance (host/pod), only present when instance statistics are enabled
+    #[serde(default)]
+    pub instance_stats: Vec<(InstanceKey, ProcOperStatsValue)>,
+    /// statistics per database query (default to support files written by older versions)
+    #[serde(default)]
+    pub db_queries: Vec<(DbQueryKey, DbQueryStatsValue)>,
+    /// observed time-window per input-file (default to support files written by older versions)
+    #[serde(default)]
+    pub file_windows: Vec<FileWindow>,
 }
 
 impl From<StatsRec> for StatsRecJson {
@@ -71,7 +124,7 @@ impl From<StatsRec> for StatsRecJson {
         let stats: HashMap<String, OperationStatsJson> =
             sr.stats.into_iter().map(|(k, v)| (k, v.into())).collect();
         Self {
-            version: sr.version,
+            version: STATS_REC_VERSION,
             trace_id: sr.trace_id,
             root_call: sr.root_call,
             num_spans: sr.num_spans,
@@ -96,6 +149,10 @@ impl From<StatsRec> for StatsRecJson {
             time_to_respond_micros: sr.time_to_respond_micros,
             caching_processes: sr.caching_processes,
             stats,
+            fan_out: sr.fan_out.0.into_iter().collect(),
+            instance_stats: sr.instance_stats.0.into_iter().collect(),
+            db_queries: sr.db_queries.0.into_iter().collect(),
+            file_windows: sr.file_windows.0,
         }
     }
 }
@@ -116,7 +173,7 @@ impl StatsRecJson {
         let sj = match ext {
             "bson" => bson::from_reader(reader)?,
             "json" => serde_json::from_reader(reader)?,
-            "bincode" => bincode::deserialize_from(reader)?,
+            "bincode" => Self::read_bincode(reader)?,
             ext => panic!(
                 "Unknown extension '{ext}'of inputfile {}",
                 path_str.display()
@@ -124,4 +181,22 @@ impl StatsRecJson {
         };
         Ok(sj)
     }
+
+    /// Read a bincode-file. Bincode ignores '#[serde(default)]', so files with an older layout are read via the LegacyStatsRecJson.
+    /// The version is the first field in all layouts, so it can be decoded before the rest of the file.
+    fn read_bincode(mut reader: impl io::Read) -> Result<Self, Box<dyn Error>> {
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer)?;
+        let version: Version = bincode::deserialize(&buffer)?;
+        if version < STATS_REC_VERSION {
+            println!(
+                "WARN: Fallback to Legacy-format (version {}.{}) to load data!!",
+                version.major, version.minor
+            );
+            let lsrj: LegacyStatsRecJson = bincode::deserialize(&buffer)?;
+            Ok(lsrj.into())
+        } else {
+            Ok(bincode::deserialize(&buffer)?)
+        }
+    }
 }
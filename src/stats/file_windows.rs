@@ -0,0 +1,263 @@
+//! The observed time-window of each input-file. Rates are computed per window (the number of events divided by the length of the window)
+//! and combined with the length of the windows as weights, such that gaps between files do not need to be guessed (see calc_rate).
+//! Files with overlapping windows are joined to a single segment, such that events are not counted twice.
+//! When the traces are sampled probabilistically (tag 'sampler.param') the rates are scaled up to the true traffic.
+use crate::{
+    processed::Trace,
+    raw::FILE_TRACKER,
+    utils::{self, TimeBuckets},
+};
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// The tags set by the Jaeger client on the root-span of a sampled trace
+const SAMPLER_TYPE_TAG: &str = "sampler.type";
+const SAMPLER_PARAM_TAG: &str = "sampler.param";
+/// only for this sampler-type the 'sampler.param' is the sampling-probability
+const PROBABILISTIC_SAMPLER: &str = "probabilistic";
+
+/// When having too few points the rates will become unreliable (same threshold as calc_rate)
+const POINTS_NEEDED_FOR_RATE: usize = 10;
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct FileWindow {
+    pub file_name: String,
+    /// start of the first trace in this file
+    pub start_micros: i64,
+    /// end of the last trace in this file
+    pub end_micros: i64,
+    pub num_traces: usize,
+    /// Sum of the inverse sampling-probability over the traces, which is the estimated number of traces before sampling.
+    /// Traces without a probabilistic sampler tag count as 1.
+    pub sampling_weight: f64,
+}
+
+impl FileWindow {
+    pub fn duration_secs(&self) -> f64 {
+        (self.end_micros - self.start_micros) as f64 / 1e6
+    }
+
+    /// the factor to scale an observed rate to the true traffic (1.0 for unsampled traces)
+    pub fn sampling_factor(&self) -> f64 {
+        if self.num_traces == 0 || self.sampling_weight <= 0.0 {
+            1.0
+        } else {
+            self.sampling_weight / self.num_traces as f64
+        }
+    }
+
+    /// the (sampling corrected) rate of traces in this window
+    pub fn trace_rate(&self) -> Option<f64> {
+        let secs = self.duration_secs();
+        (secs > 0.0).then(|| self.num_traces as f64 * self.sampling_factor() / secs)
+    }
+
+    fn extend(&mut self, other: &FileWindow) {
+        self.start_micros = self.start_micros.min(other.start_micros);
+        self.end_micros = self.end_micros.max(other.end_micros);
+        self.num_traces += other.num_traces;
+        self.sampling_weight += other.sampling_weight;
+    }
+}
+
+/// The sampling-probability of a trace, based on the tags of the root-span (or of the first span when the root is missing).
+fn sampling_probability(trace: &Trace) -> Option<f64> {
+    let spans = &trace.spans;
+    let span = spans
+        .root_idx
+        .map(|idx| &spans.items[idx])
+        .or_else(|| spans.items.first())?;
+    if span.attributes.get(SAMPLER_TYPE_TAG)? != PROBABILISTIC_SAMPLER {
+        return None;
+    }
+    span.attributes
+        .get(SAMPLER_PARAM_TAG)?
+        .parse::<f64>()
+        .ok()
+        .filter(|p| *p > 0.0 && *p <= 1.0)
+}
+
+/// the timestamps are in the same (naive) time-zone as the start_dt of the spans
+fn datetime_str(micros: i64) -> String {
+    DateTime::from_timestamp_micros(micros)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S%.3f").to_string())
+        .unwrap_or_default()
+}
+
+/// The windows of all input-files, ordered by file-name.
+#[derive(Debug, Default, Clone)]
+pub struct FileWindows(pub Vec<FileWindow>);
+
+impl FileWindows {
+    fn add_window(&mut self, window: FileWindow) {
+        match self
+            .0
+            .binary_search_by(|fw| fw.file_name.cmp(&window.file_name))
+        {
+            Ok(pos) => self.0[pos].extend(&window),
+            Err(pos) => self.0.insert(pos, window),
+        }
+    }
+
+    /// extend the window of the source-file of this trace
+    pub fn extend(&mut self, trace: &Trace) {
+        let file_name = FILE_TRACKER
+            .lock()
+            .unwrap()
+            .get_file_name(trace.source_file_id);
+        self.add_window(FileWindow {
+            file_name,
+            start_micros: trace.start_dt.and_utc().timestamp_micros(),
+            end_micros: trace.end_dt.and_utc().timestamp_micros(),
+            num_traces: 1,
+            sampling_weight: sampling_probability(trace).map_or(1.0, |p| 1.0 / p),
+        })
+    }
+
+    /// merge the windows of 'other'. Windows of the same file are joined.
+    pub fn merge(&mut self, other: FileWindows) {
+        other.0.into_iter().for_each(|fw| self.add_window(fw));
+    }
+
+    /// The windows ordered by start-time, where overlapping windows are joined to a single segment
+    fn segments(&self) -> Vec<FileWindow> {
+        let mut windows = self.0.clone();
+        windows.sort_by_key(|fw| fw.start_micros);
+        let mut segments: Vec<FileWindow> = Vec::new();
+        windows
+            .into_iter()
+            .for_each(|fw| match segments.last_mut() {
+                Some(last) if fw.start_micros <= last.end_micros => last.extend(&fw),
+                _ => segments.push(fw),
+            });
+        segments
+    }
+
+    /// the total time covered by the input-files in seconds (None when the windows are unknown)
+    pub fn total_secs(&self) -> Option<f64> {
+        let secs: f64 = self.segments().iter().map(|seg| seg.duration_secs()).sum();
+        (secs > 0.0).then_some(secs)
+    }
+
+    /// Rate (per second) of a series of (timestamp in micros, count). Each point is assigned to the last segment that starts before it.
+    /// The rate per segment is corrected for sampling, and the rates are combined with the length of the segments as weights.
+    fn rate_of_counts(&self, points: impl Iterator<Item = (i64, usize)>) -> Option<f64> {
+        let segments = self.segments();
+        let mut counts = vec![0; segments.len()];
+        points.for_each(|(micros, cnt)| {
+            let idx = segments
+                .partition_point(|seg| seg.start_micros <= micros)
+                .saturating_sub(1);
+            if let Some(c) = counts.get_mut(idx) {
+                *c += cnt
+            }
+        });
+        if counts.iter().sum::<usize>() < POINTS_NEEDED_FOR_RATE {
+            return None;
+        }
+        let total_secs: f64 = segments.iter().map(|seg| seg.duration_secs()).sum();
+        if total_secs <= 0.0 {
+            return None;
+        }
+        let weighted_rate: f64 = segments
+            .iter()
+            .zip(counts)
+            .filter(|(seg, _)| seg.duration_secs() > 0.0)
+            .map(|(seg, cnt)| {
+                let rate = cnt as f64 * seg.sampling_factor() / seg.duration_secs();
+                rate * seg.duration_secs() / total_secs
+            })
+            .sum();
+        Some(weighted_rate)
+    }
+
+    /// Average rate of a series of timestamps. Statistics written by older versions do not contain file-windows,
+    /// in which case the rate is computed by skipping the 'num_files' largest gaps (see calc_rate).
+    pub fn get_rate(&self, start_micros: &[i64], num_files: i32) -> Option<f64> {
+        if self.0.is_empty() {
+            utils::calc_rate(start_micros, num_files).map(|(avg_rate, _)| avg_rate)
+        } else {
+            self.rate_of_counts(start_micros.iter().map(|m| (*m, 1)))
+        }
+    }
+
+    /// Average rate of a series of timestamps stored as time-buckets (the middle of each bucket is used as timestamp).
+    pub fn get_bucket_rate(&self, tb: &TimeBuckets, num_files: i32) -> Option<f64> {
+        if self.0.is_empty() {
+            tb.get_avg_rate(num_files)
+        } else {
+            let half = tb.bucket_micros / 2;
+            self.rate_of_counts(
+                tb.buckets
+                    .iter()
+                    .map(|(idx, cnt)| (idx * tb.bucket_micros + half, *cnt)),
+            )
+        }
+    }
+
+    /// header for report_stats_line output in ';'-separated csv-format
+    pub fn report_stats_line_header_str() -> &'static str {
+        "File; Start; End; Duration_secs; Num_traces; Sampling_factor; Trace_rate"
+    }
+
+    /// reports the window of each file in ';'-separated csv-format
+    pub fn report_stats_lines(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .map(|fw| {
+                format!(
+                    "{}; {}; {}; {}; {}; {}; {}",
+                    fw.file_name,
+                    datetime_str(fw.start_micros),
+                    datetime_str(fw.end_micros),
+                    utils::format_float(fw.duration_secs()),
+                    fw.num_traces,
+                    utils::format_float(fw.sampling_factor()),
+                    utils::format_float_opt(fw.trace_rate()),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileWindow, FileWindows};
+    use crate::utils::TimeBuckets;
+
+    fn window(name: &str, start_secs: i64, end_secs: i64, sampling_weight: f64) -> FileWindow {
+        FileWindow {
+            file_name: name.to_owned(),
+            start_micros: start_secs * 1_000_000,
+            end_micros: end_secs * 1_000_000,
+            num_traces: 1,
+            sampling_weight,
+        }
+    }
+
+    #[test]
+    fn rate_per_file_window() {
+        // file 'a' has 2 events per second during 100 seconds, file 'b' has 1 event per second during 300 seconds
+        let fws = FileWindows(vec![window("a", 0, 100, 1.0), window("b", 3600, 3900, 1.0)]);
+        let values: Vec<i64> = (0..200)
+            .map(|i| i * 500_000)
+            .chain((0..300).map(|i| 3_600_000_000 + i * 1_000_000))
+            .collect();
+        let rate = fws.get_rate(&values, 2).unwrap();
+        assert!((rate - 500.0 / 400.0).abs() < 1e-9, "rate = {rate}");
+        let tb_rate = fws
+            .get_bucket_rate(&TimeBuckets::from_values(&values), 2)
+            .unwrap();
+        assert!((tb_rate - rate).abs() < 1e-9, "rate = {tb_rate}");
+    }
+
+    #[test]
+    fn overlapping_and_sampled_windows() {
+        // two overlapping files are joined, and a sampling-probability of 0.5 doubles the rate
+        let fws = FileWindows(vec![window("a", 0, 60, 2.0), window("b", 30, 100, 2.0)]);
+        let values: Vec<i64> = (0..100).map(|i| i * 1_000_000).collect();
+        let rate = fws.get_rate(&values, 2).unwrap();
+        assert!((rate - 2.0).abs() < 1e-9, "rate = {rate}");
+        assert!(fws.get_rate(&values[..5], 2).is_none());
+    }
+}
@@ -0,0 +1,303 @@
+//! Statistics per Service/Operation broken down by instance (host or pod), such that a single slow or failing instance does not disappear in the average.
+//! The instance dimension is optional as it increases the size of the statistics files. It is enabled via set_instance_stats.
+use super::{error_stats::get_span_error_information, proc_oper_stats::ProcOperStatsValue};
+use crate::{processed::Span, utils};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Mutex};
+
+static INSTANCE_STATS: Mutex<bool> = Mutex::new(false);
+
+/// Enable (or disable) the collection of statistics per instance (host/pod).
+pub fn set_instance_stats(val: bool) {
+    let mut guard = INSTANCE_STATS.lock().unwrap();
+    *guard = val
+}
+
+pub fn instance_stats_enabled() -> bool {
+    *INSTANCE_STATS.lock().unwrap()
+}
+
+/// minimal number of calls on an instance before it is compared to its siblings
+const MIN_INSTANCE_COUNT: usize = 10;
+/// z-score above which a deviation is considered significant
+const DEVIATION_Z_SCORE: f64 = 3.0;
+/// minimal relative increase of the average latency before an instance is flagged as slow
+const MIN_LATENCY_RATIO: f64 = 1.2;
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct InstanceKey {
+    /// the Service/Operation
+    pub service_oper: String,
+    /// the instance, which is the server_name (hostname) of the process, or the ip-address if the hostname is not available.
+    pub instance: String,
+}
+
+impl InstanceKey {
+    /// the key as used in the breakdown-list of the Viewer
+    pub fn to_key_str(&self) -> String {
+        format!("{}@{}", self.service_oper, self.instance)
+    }
+}
+
+/// get the instance label of a span
+fn get_instance(span: &Span) -> String {
+    match &span.process {
+        Some(p) if !p.server_name.is_empty() => p.server_name.to_owned(),
+        Some(p) if !p.ip.is_empty() => p.ip.to_owned(),
+        _ => "-".to_owned(),
+    }
+}
+
+/// A deviating instance with the reasons (latency and/or error-rate) why it was flagged.
+pub struct InstanceDeviation<'a> {
+    pub key: &'a InstanceKey,
+    pub avg_millis: f64,
+    pub siblings_avg_millis: f64,
+    pub frac_errors: f64,
+    pub siblings_frac_errors: f64,
+    pub slow: bool,
+    pub errors: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct InstanceStats(pub HashMap<InstanceKey, ProcOperStatsValue>);
+
+impl InstanceStats {
+    /// merge the instance statistics of 'other' into these statistics
+    pub fn merge(&mut self, other: InstanceStats) {
+        other.0.into_iter().for_each(|(k, pov)| {
+            self.0.entry(k).or_default().merge(pov);
+        })
+    }
+
+    /// update the instance statistics with a single span
+    pub fn update(&mut self, span: &Span) {
+        let key = InstanceKey {
+            service_oper: format!("{}/{}", span.get_process_str(), span.operation_name),
+            instance: get_instance(span),
+        };
+        let (http_not_ok_vec, error_logs_vec) = get_span_error_information(span);
+        let pov = self.0.entry(key).or_default();
+        pov.count += 1;
+        pov.start_dt_micros
+            .push(span.start_dt.and_utc().timestamp_micros());
+        pov.duration_micros.push(span.duration_micros);
+        pov.num_not_http_ok += if http_not_ok_vec.is_empty() { 0 } else { 1 };
+        pov.num_with_error_logs += if error_logs_vec.is_empty() { 0 } else { 1 };
+        pov.http_not_ok_codes.add_items(http_not_ok_vec);
+        pov.error_logs.add_items(error_logs_vec);
+    }
+
+    /// Compare each instance to the other instances (siblings) of the same Service/Operation.
+    /// An instance is flagged as slow when its average latency is significantly higher than the average over the siblings (z-score on the mean),
+    /// and flagged for errors when its error-rate is significantly higher (two-proportion z-test).
+    pub fn get_deviations(&self) -> Vec<InstanceDeviation<'_>> {
+        let mut per_service_oper: HashMap<&str, Vec<(&InstanceKey, &ProcOperStatsValue)>> =
+            HashMap::new();
+        self.0.iter().for_each(|(k, v)| {
+            per_service_oper
+                .entry(&k.service_oper[..])
+                .or_default()
+                .push((k, v))
+        });
+
+        let mut deviations: Vec<_> = per_service_oper
+            .into_values()
+            .filter(|instances| instances.len() > 1)
+            .flat_map(|instances| {
+                instances
+                    .iter()
+                    .filter(|(_, v)| v.count >= MIN_INSTANCE_COUNT)
+                    .filter_map(|(key, pov)| {
+                        let siblings: Vec<_> = instances
+                            .iter()
+                            .filter(|(k, _)| k != key)
+                            .map(|(_, v)| *v)
+                            .collect();
+                        let sib_count: usize = siblings.iter().map(|v| v.count).sum();
+                        let sib_errors: usize = siblings.iter().map(|v| v.num_errors()).sum();
+                        if sib_count < MIN_INSTANCE_COUNT {
+                            return None;
+                        }
+
+                        // the mean and standard deviation over the siblings are pooled from the per-sibling values, such that
+                        // compact statistics (without raw durations) are handled as well
+                        let avg_millis = pov.get_avg_millis();
+                        let sib_avg_millis = siblings
+                            .iter()
+                            .map(|v| v.count as f64 * v.get_avg_millis())
+                            .sum::<f64>()
+                            / sib_count as f64;
+                        let sib_sd_millis = (siblings
+                            .iter()
+                            .map(|v| {
+                                v.count as f64
+                                    * (v.get_variance_millis()
+                                        + (v.get_avg_millis() - sib_avg_millis).powi(2))
+                            })
+                            .sum::<f64>()
+                            / sib_count as f64)
+                            .sqrt();
+                        let slow = avg_millis > MIN_LATENCY_RATIO * sib_avg_millis
+                            && (sib_sd_millis == 0.0
+                                || (avg_millis - sib_avg_millis)
+                                    / (sib_sd_millis / (pov.count as f64).sqrt())
+                                    > DEVIATION_Z_SCORE);
+
+                        let frac_errors = pov.num_errors() as f64 / pov.count as f64;
+                        let sib_frac_errors = sib_errors as f64 / sib_count as f64;
+                        let errors = frac_errors > sib_frac_errors
+                            && utils::two_proportion_p_value(
+                                sib_count,
+                                sib_errors,
+                                pov.count,
+                                pov.num_errors(),
+                            )
+                            .is_some_and(|p| p < utils::two_sided_p_value(DEVIATION_Z_SCORE));
+
+                        if slow || errors {
+                            Some(InstanceDeviation {
+                                key,
+                                avg_millis,
+                                siblings_avg_millis: sib_avg_millis,
+                                frac_errors,
+                                siblings_frac_errors: sib_frac_errors,
+                                slow,
+                                errors,
+                            })
+                        } else {
+                            None
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        deviations.sort_by(|a, b| a.key.cmp(b.key));
+        deviations
+    }
+
+    /// header for report_stats_line output in ';'-separated csv-format
+    pub fn report_stats_line_header_str() -> &'static str {
+        "Process/Oper; Instance; Count; Min_millis; Median_millis; Avg_millis; Max_millis; frac_not_http_ok; frac_error_logs"
+    }
+
+    /// reports the statistics for a single line in ';'-separated csv-format
+    pub fn report_stats_line(key: &InstanceKey, pov: &ProcOperStatsValue) -> String {
+        format!(
+            "{}; {}; {}; {}; {}; {}; {}; {}; {}",
+            key.service_oper,
+            key.instance,
+            pov.count,
+            pov.get_min_millis_str(),
+            pov.get_median_millis_str(),
+            pov.get_avg_millis_str(),
+            pov.get_max_millis_str(),
+            pov.get_frac_not_http_ok_str(),
+            pov.get_frac_error_log_str()
+        )
+    }
+}
+
+impl<'a> InstanceDeviation<'a> {
+    /// header for report_stats_line output in ';'-separated csv-format
+    pub fn report_stats_line_header_str() -> &'static str {
+        "Process/Oper; Instance; Avg_millis; Siblings_avg_millis; frac_errors; Siblings_frac_errors; Slow; Errors"
+    }
+
+    /// reports the deviation for a single line in ';'-separated csv-format
+    pub fn report_stats_line(&self) -> String {
+        format!(
+            "{}; {}; {}; {}; {}; {}; {}; {}",
+            self.key.service_oper,
+            self.key.instance,
+            utils::format_float(self.avg_millis),
+            utils::format_float(self.siblings_avg_millis),
+            utils::format_float(self.frac_errors),
+            utils::format_float(self.siblings_frac_errors),
+            self.slow,
+            self.errors
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InstanceKey, InstanceStats, ProcOperStatsValue};
+    use std::collections::HashMap;
+
+    fn instance(
+        instance: &str,
+        durations_millis: &[i64],
+        errors: i32,
+    ) -> (InstanceKey, ProcOperStatsValue) {
+        let key = InstanceKey {
+            service_oper: "svc/oper".to_string(),
+            instance: instance.to_string(),
+        };
+        let pov = ProcOperStatsValue {
+            count: durations_millis.len(),
+            duration_micros: durations_millis.iter().map(|d| d * 1_000).collect(),
+            start_dt_micros: vec![1_700_000_000_000_000; durations_millis.len()],
+            num_not_http_ok: errors,
+            ..Default::default()
+        };
+        (key, pov)
+    }
+
+    fn instance_stats() -> InstanceStats {
+        let normal: Vec<i64> = (0..40).map(|i| 100 + i % 5).collect();
+        let slow: Vec<i64> = (0..40).map(|i| 200 + i % 5).collect();
+        InstanceStats(HashMap::from([
+            instance("pod-a", &normal, 0),
+            instance("pod-b", &normal, 1),
+            instance("pod-c", &slow, 0),
+            instance("pod-d", &normal, 20),
+        ]))
+    }
+
+    /// the flagged instances with their (slow, errors) flags
+    fn flagged(is: &InstanceStats) -> Vec<(String, bool, bool)> {
+        is.get_deviations()
+            .iter()
+            .map(|d| (d.key.instance.clone(), d.slow, d.errors))
+            .collect()
+    }
+
+    #[test]
+    fn deviations_raw_and_compact() {
+        let expect = vec![
+            ("pod-c".to_string(), true, false),
+            ("pod-d".to_string(), false, true),
+        ];
+        let mut is = instance_stats();
+        assert_eq!(flagged(&is), expect);
+
+        // compact statistics have no raw durations, but give the same result
+        is.0.values_mut().for_each(|pov| pov.compact());
+        assert_eq!(flagged(&is), expect);
+    }
+
+    #[test]
+    fn small_and_single_instances_are_not_flagged() {
+        let normal: Vec<i64> = (0..40).map(|i| 100 + i % 5).collect();
+        // too few calls to be compared, even though all calls are slow and failed
+        let mut is = InstanceStats(HashMap::from([
+            instance("pod-a", &normal, 0),
+            instance("pod-b", &normal, 0),
+            instance("pod-e", &[900; 5], 5),
+        ]));
+        assert!(flagged(&is).is_empty());
+
+        // an instance without siblings can not deviate
+        is.0.retain(|k, _| k.instance == "pod-a");
+        let (key, pov) = instance("pod-x", &[900; 40], 40);
+        is.0.insert(
+            InstanceKey {
+                service_oper: "other/oper".to_string(),
+                ..key
+            },
+            pov,
+        );
+        assert!(flagged(&is).is_empty());
+    }
+}
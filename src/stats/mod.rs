@@ -1,19 +1,46 @@
 //!  Computing statistics and call-chains over the traces.
 
+mod amplification;
+mod cache_stats;
 pub mod call_chain; // already defines its public interface
+mod compare;
+mod db_query_stats;
+mod dimension_stats;
 mod error_stats;
+mod fan_out_stats;
 pub mod file;
+mod file_windows;
+mod instance_stats;
 mod operation_stats;
+mod parallelism;
 mod proc_oper_stats;
+mod slo;
 mod stats_rec;
 mod traceext; // already defines its public interface
 mod traceextvec;
 
 pub use {
-    call_chain::{CChainEndPointCache, CChainStatsKey},
+    amplification::AmplificationMatrix,
+    cache_stats::{CacheStats, CacheStatsValue},
+    call_chain::{
+        prune_cchain_file_locked, read_cchain_folder, set_gap_repair, set_repair_strategy,
+        write_cchain_folder, CChainEndPointCache, CChainStatsKey, ChainInfo, EndPointCChains,
+        PrunePolicy, RepairStrategy,
+    },
+    compare::{CompareParameters, StatsComparison},
+    db_query_stats::{DbQueryKey, DbQueryStats, DbQueryStatsValue},
+    dimension_stats::{
+        get_split_dimensions, set_split_dimensions, DimensionKey, DimensionStats,
+        DimensionStatsValue,
+    },
+    error_stats::get_span_error_information,
+    fan_out_stats::{FanOutKey, FanOutStats, FanOutStatsValue},
+    file_windows::{FileWindow, FileWindows},
+    instance_stats::{set_instance_stats, InstanceKey, InstanceStats},
     operation_stats::OperationStats,
     proc_oper_stats::{ProcOperStats, ProcOperStatsValue},
-    stats_rec::{chained_stats, BasicStatsRec, LeafService, StatsRec},
-    traceext::{build_trace_ext, write_stats_to_csv_file, TraceExt},
+    slo::{find_proc_oper, get_slo_config, set_slo_config, SloConfig, SloObjective, SloStatus},
+    stats_rec::{chained_stats, set_compact_stats, BasicStatsRec, LeafService, StatsRec},
+    traceext::{build_trace_ext, set_waterfall_output, write_stats_to_csv_file, TraceExt},
     traceextvec::TraceExtVec,
 };
@@ -3,8 +3,10 @@ use super::{
         caching_process_label, get_call_chain, CChainStats, CChainStatsKey, CChainStatsValue,
         CallChain,
     },
+    dimension_stats::{DimensionKey, DimensionStats},
     error_stats::{get_cchain_error_information, get_span_error_information},
     file::OperationStatsJson,
+    parallelism::get_child_parallelism,
     proc_oper_stats::{ProcOperStats, ProcOperStatsValue},
 };
 use crate::{
@@ -27,12 +29,35 @@ pub struct OperationStats {
     pub num_unknown_calls: usize,
     /// The statistics over all call-chains that lead to this Process/Operation
     pub call_chain: CChainStats,
+    /// The statistics of this Process split by the values of the configured dimensions (see set_split_dimensions)
+    pub dimensions: DimensionStats,
 }
 
 impl OperationStats {
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// merge the statistics of 'other' (for the same Process) into these statistics
+    pub fn merge(&mut self, other: OperationStats) {
+        self.operation.merge(other.operation);
+        self.num_traces += other.num_traces;
+        self.num_received_calls += other.num_received_calls;
+        self.num_outbound_calls += other.num_outbound_calls;
+        self.num_unknown_calls += other.num_unknown_calls;
+        self.call_chain.merge(other.call_chain);
+        self.dimensions.merge(other.dimensions);
+    }
+
+    /// store the durations and start-times of all statistics of this Process in compact form
+    pub fn compact(&mut self) {
+        self.operation.compact();
+        self.call_chain.compact();
+        self.dimensions.0.values_mut().for_each(|dsv| {
+            dsv.operation.compact();
+            dsv.call_chain.compact();
+        });
+    }
 }
 
 impl From<OperationStatsJson> for OperationStats {
@@ -45,6 +70,12 @@ impl From<OperationStatsJson> for OperationStats {
             num_unknown_calls: stj.num_unknown_calls,
             operation: stj.method,
             call_chain,
+            dimensions: DimensionStats(
+                stj.dimensions
+                    .into_iter()
+                    .map(|(k, v)| (k, v.into()))
+                    .collect(),
+            ),
         }
     }
 }
@@ -53,13 +84,16 @@ impl OperationStats {
     // The update_stat closure is the actual update operation
     // This closure is later applied to the newly inserted record for this process, or is used to update an existing record,
     // such that both processes share exactly the same code.
+    #[allow(clippy::too_many_arguments)]
     pub fn update(
         &mut self,
         idx: usize,
         span: &Span,
         spans: &Spans,
+        children: &[usize],
         caching_process: &[String],
         root_call: &str,
+        dimensions: &[DimensionKey],
     ) {
         match &span.span_kind {
             Some(kind) => match &kind[..] {
@@ -73,6 +107,7 @@ impl OperationStats {
         let duration_micros = span.duration_micros;
         let start_dt_micros = span.start_dt.timestamp_micros();
         let (http_not_ok_vec, error_logs_vec) = get_span_error_information(span);
+        let parallelism = get_child_parallelism(idx, spans, children);
 
         let update_proc_oper_value = |oper_stat_val: &mut ProcOperStatsValue| {
             oper_stat_val.count += 1;
@@ -84,18 +119,25 @@ impl OperationStats {
                 .http_not_ok_codes
                 .add_items(http_not_ok_vec.clone());
             oper_stat_val.error_logs.add_items(error_logs_vec.clone());
+            if let Some((concurrency, wait_frac)) = parallelism {
+                oper_stat_val.child_concurrency.push(concurrency);
+                oper_stat_val.child_wait_frac.push(wait_frac);
+            }
         };
         // add a count per method
         let method = &span.operation_name;
-        self.operation
-            .0
-            .entry(method.to_owned())
-            .and_modify(update_proc_oper_value)
-            .or_insert_with(|| {
-                let mut oper_stat = ProcOperStatsValue::default();
-                update_proc_oper_value(&mut oper_stat);
-                oper_stat
-            });
+        let update_operation = |operation: &mut ProcOperStats| {
+            operation
+                .0
+                .entry(method.to_owned())
+                .and_modify(update_proc_oper_value)
+                .or_insert_with(|| {
+                    let mut oper_stat = ProcOperStatsValue::default();
+                    update_proc_oper_value(&mut oper_stat);
+                    oper_stat
+                });
+        };
+        update_operation(&mut self.operation);
 
         // // add a count per method_including-cached
         let call_chain = get_call_chain(idx, spans);
@@ -129,16 +171,25 @@ impl OperationStats {
                 ps.expect_root.add_root(root_call)
             }
         };
-        self.call_chain
-            .0
-            .entry(ps_key)
-            // next part could be made more dry via an update-closure
-            .and_modify(update_ps_val)
-            .or_insert_with(|| {
-                let mut ps = CChainStatsValue::new(depth, looped, rooted);
-                update_ps_val(&mut ps);
-                ps
-            });
+        let update_call_chain = |call_chain: &mut CChainStats, ps_key: CChainStatsKey| {
+            call_chain
+                .0
+                .entry(ps_key)
+                .and_modify(update_ps_val)
+                .or_insert_with(|| {
+                    let mut ps = CChainStatsValue::new(depth, looped.clone(), rooted);
+                    update_ps_val(&mut ps);
+                    ps
+                });
+        };
+
+        // the same update is applied to the sub-series of each dimension-value of this span
+        dimensions.iter().for_each(|dim_key| {
+            let dsv = self.dimensions.0.entry(dim_key.clone()).or_default();
+            update_operation(&mut dsv.operation);
+            update_call_chain(&mut dsv.call_chain, ps_key.clone());
+        });
+        update_call_chain(&mut self.call_chain, ps_key);
     }
 
     /// header for report_stats_line output in ';'-separated csv-format
@@ -0,0 +1,31 @@
+use crate::{processed::Spans, utils};
+
+/// returns the parallelism of the children of a span as a tuple (concurrency, wait_fraction), or None if the span has less than two children.
+///  * concurrency: sum of the durations of the children divided by the length of the union of the child-intervals. A value of 1.0 means the children are called sequentially, while a value N means N children run in parallel on average.
+///  * wait_fraction: the fraction of the duration of the span that is spent waiting for at least one of its children.
+pub fn get_child_parallelism(idx: usize, spans: &Spans, children: &[usize]) -> Option<(f64, f64)> {
+    if children.len() < 2 {
+        return None;
+    }
+    let intervals: Vec<_> = children
+        .iter()
+        .map(|child_idx| {
+            let child = &spans.items[*child_idx];
+            let start = child.start_dt.and_utc().timestamp_micros();
+            (start, start + child.duration_micros)
+        })
+        .collect();
+    let union = utils::interval_union_length(&intervals);
+    if union <= 0 {
+        return None;
+    }
+    let total: i64 = intervals.iter().map(|(start, end)| end - start).sum();
+    let concurrency = total as f64 / union as f64;
+    let duration = spans.items[idx].duration_micros;
+    let wait_fraction = if duration > 0 {
+        (union as f64 / duration as f64).min(1.0)
+    } else {
+        1.0
+    };
+    Some((concurrency, wait_fraction))
+}
@@ -1,4 +1,5 @@
-use crate::utils::{self, Counted, TimeStats};
+use super::FileWindows;
+use crate::utils::{self, Counted, DurationSketch, TimeBuckets, TimeStats};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -21,52 +22,158 @@ pub struct ProcOperStatsValue {
     pub http_not_ok_codes: Counted<i16>,
     /// Contains the counted list of error-messages that have been observed (Other log-levels are ignored).
     pub error_logs: Counted<String>,
+    /// Concurrency of the child-calls for each span with at least two children (sum of child durations divided by the union of the child-intervals)
+    #[serde(default)]
+    pub child_concurrency: Vec<f64>,
+    /// Fraction of the duration of the span spent waiting for its children (same spans as child_concurrency)
+    #[serde(default)]
+    pub child_wait_frac: Vec<f64>,
+    /// Compact representation of duration_micros. Only present in compacted statistics, in which case duration_micros is empty.
+    #[serde(default)]
+    pub duration_sketch: Option<DurationSketch>,
+    /// Compact representation of start_dt_micros. Only present in compacted statistics, in which case start_dt_micros is empty.
+    #[serde(default)]
+    pub start_dt_buckets: Option<TimeBuckets>,
 }
 
 impl ProcOperStatsValue {
+    /// merge the statistics of 'other' (for the same Process/Operation) into this value
+    pub fn merge(&mut self, mut other: ProcOperStatsValue) {
+        self.count += other.count;
+        self.num_traces += other.num_traces;
+        if self.is_compact() || other.is_compact() {
+            self.compact();
+            other.compact();
+            merge_compact(
+                (&mut self.duration_sketch, &mut self.start_dt_buckets),
+                (other.duration_sketch, other.start_dt_buckets),
+            );
+        } else {
+            self.duration_micros.append(&mut other.duration_micros);
+            self.start_dt_micros.append(&mut other.start_dt_micros);
+        }
+        self.num_not_http_ok += other.num_not_http_ok;
+        self.num_with_error_logs += other.num_with_error_logs;
+        self.http_not_ok_codes.merge(other.http_not_ok_codes);
+        self.error_logs.merge(other.error_logs);
+        self.child_concurrency.append(&mut other.child_concurrency);
+        self.child_wait_frac.append(&mut other.child_wait_frac);
+    }
+
+    /// true if the durations and start-times are stored in the compact format
+    pub fn is_compact(&self) -> bool {
+        self.duration_sketch.is_some()
+    }
+
+    /// replace the durations and start-times by a sketch and time-buckets (a no-op if the value is already compact)
+    pub fn compact(&mut self) {
+        if !self.is_compact() {
+            let (sketch, buckets) =
+                compact_values(&mut self.duration_micros, &mut self.start_dt_micros);
+            self.duration_sketch = Some(sketch);
+            self.start_dt_buckets = Some(buckets);
+        }
+    }
+
     pub fn get_min_millis(&self) -> f64 {
-        TimeStats(&self.duration_micros).get_min_millis()
+        match &self.duration_sketch {
+            Some(ds) => ds.get_min_millis(),
+            None => TimeStats(&self.duration_micros).get_min_millis(),
+        }
     }
 
     pub fn get_min_millis_str(&self) -> String {
-        TimeStats(&self.duration_micros).get_min_millis_str()
+        utils::format_float(self.get_min_millis())
     }
 
     pub fn get_avg_millis(&self) -> f64 {
-        TimeStats(&self.duration_micros).get_avg_millis()
+        match &self.duration_sketch {
+            Some(ds) => ds.get_avg_millis(),
+            None => TimeStats(&self.duration_micros).get_avg_millis(),
+        }
+    }
+
+    /// population variance of the durations in milliseconds² (approximate for compact values)
+    pub fn get_variance_millis(&self) -> f64 {
+        match &self.duration_sketch {
+            Some(ds) => ds.get_variance_millis(),
+            None if self.duration_micros.is_empty() => 0.0,
+            None => {
+                let avg = self.get_avg_millis();
+                self.duration_micros
+                    .iter()
+                    .map(|d| (*d as f64 / 1000.0 - avg).powi(2))
+                    .sum::<f64>()
+                    / self.duration_micros.len() as f64
+            }
+        }
+    }
+
+    /// the first start-time in microseconds (rounded down to the time-bucket for compact values)
+    pub fn get_first_start_micros(&self) -> Option<i64> {
+        match &self.start_dt_buckets {
+            Some(tb) => tb.first_micros(),
+            None => self.start_dt_micros.iter().min().cloned(),
+        }
     }
 
     pub fn get_avg_millis_str(&self) -> String {
-        TimeStats(&self.duration_micros).get_avg_millis_str()
+        utils::format_float(self.get_avg_millis())
     }
 
     pub fn get_median_millis(&self) -> Option<f64> {
-        TimeStats(&self.duration_micros).get_median_millis()
+        match &self.duration_sketch {
+            Some(ds) => ds.get_median_millis(),
+            None => TimeStats(&self.duration_micros).get_median_millis(),
+        }
     }
 
     /// get the P-percentile over the values
     pub fn get_p_millis(&self, p: f64) -> Option<f64> {
-        TimeStats(&self.duration_micros).get_p_millis(p)
+        match &self.duration_sketch {
+            Some(ds) => ds.get_p_millis(p),
+            None => TimeStats(&self.duration_micros).get_p_millis(p),
+        }
+    }
+
+    /// number of calls with a duration of at most 'millis' (approximate for compact values)
+    pub fn count_at_most_millis(&self, millis: f64) -> usize {
+        let micros = (millis * 1000.0) as i64;
+        match &self.duration_sketch {
+            Some(ds) => ds.count_at_most(micros),
+            None => self
+                .duration_micros
+                .iter()
+                .filter(|d| **d <= micros)
+                .count(),
+        }
     }
 
     pub fn get_median_millis_str(&self) -> String {
-        TimeStats(&self.duration_micros).get_median_millis_str()
+        utils::format_float_opt(self.get_median_millis())
     }
 
     pub fn get_max_millis(&self) -> f64 {
-        TimeStats(&self.duration_micros).get_max_millis()
+        match &self.duration_sketch {
+            Some(ds) => ds.get_max_millis(),
+            None => TimeStats(&self.duration_micros).get_max_millis(),
+        }
     }
 
     pub fn get_max_millis_str(&self) -> String {
-        TimeStats(&self.duration_micros).get_max_millis_str()
+        utils::format_float(self.get_max_millis())
     }
 
-    pub fn get_avg_rate(&self, num_files: i32) -> Option<f64> {
-        TimeStats(&self.start_dt_micros).get_avg_rate(num_files)
+    /// average rate based on the time-windows of the input-files (num_files is only used when these windows are missing)
+    pub fn get_avg_rate(&self, file_windows: &FileWindows, num_files: i32) -> Option<f64> {
+        match &self.start_dt_buckets {
+            Some(tb) => file_windows.get_bucket_rate(tb, num_files),
+            None => file_windows.get_rate(&self.start_dt_micros, num_files),
+        }
     }
 
-    pub fn get_avg_rate_str(&self, num_files: i32) -> String {
-        TimeStats(&self.start_dt_micros).get_avg_rate_str(num_files)
+    pub fn get_avg_rate_str(&self, file_windows: &FileWindows, num_files: i32) -> String {
+        utils::format_float_opt(self.get_avg_rate(file_windows, num_files))
     }
 
     pub fn get_frac_not_http_ok(&self) -> f64 {
@@ -76,6 +183,11 @@ impl ProcOperStatsValue {
         utils::format_float(self.get_frac_not_http_ok())
     }
 
+    /// number of calls with an error (a not http-ok code or an error-log), estimated as the largest of both counts
+    pub fn num_errors(&self) -> usize {
+        self.num_not_http_ok.max(self.num_with_error_logs) as usize
+    }
+
     pub fn get_frac_error_log(&self) -> f64 {
         self.num_with_error_logs as f64 / self.count as f64
     }
@@ -84,9 +196,19 @@ impl ProcOperStatsValue {
         utils::format_float(self.get_frac_error_log())
     }
 
+    /// average concurrency of the child-calls (only spans with at least two children are included)
+    pub fn get_avg_child_concurrency(&self) -> Option<f64> {
+        avg_opt(&self.child_concurrency)
+    }
+
+    /// average fraction of time spent waiting for child-calls (only spans with at least two children are included)
+    pub fn get_avg_child_wait_frac(&self) -> Option<f64> {
+        avg_opt(&self.child_wait_frac)
+    }
+
     /// header for report_stats_line output in ';'-separated csv-format
     pub fn report_stats_line_header_str() -> &'static str {
-        "Process/Oper; Count; Num_traces; Min_millis; Avg_millis; Max_millis; Percentage; Rate; Expect_duration; frac_not_http_ok; frac_error_logs"
+        "Process/Oper; Count; Num_traces; Min_millis; Avg_millis; Max_millis; Percentage; Rate; Expect_duration; frac_not_http_ok; frac_error_logs; Num_multi_child; Avg_child_concurrency; Avg_child_wait_frac"
     }
 
     /// reports the statistics for a single line in ';'-separated csv-format
@@ -95,13 +217,14 @@ impl ProcOperStatsValue {
         process_key: &str,
         operation: &str,
         n: f64,
+        file_windows: &FileWindows,
         num_files: i32,
     ) -> String {
         let percentage = self.count as f64 / n;
         let expect_duration = percentage * self.get_avg_millis();
         // let expect_contribution = if ps_key.is_leaf { expect_duration } else { 0.0 };
         let line = format!(
-            "{process_key}/{operation}; {}; {}; {}; {}; {}; {}; {}; {}; {}; {}; {}",
+            "{process_key}/{operation}; {}; {}; {}; {}; {}; {}; {}; {}; {}; {}; {}; {}; {}; {}",
             self.count,
             self.num_traces,
             self.get_min_millis_str(),
@@ -109,16 +232,119 @@ impl ProcOperStatsValue {
             self.get_avg_millis_str(),
             self.get_max_millis_str(),
             utils::format_float(percentage),
-            self.get_avg_rate_str(num_files),
+            self.get_avg_rate_str(file_windows, num_files),
             utils::format_float(expect_duration),
             self.get_frac_not_http_ok_str(),
-            self.get_frac_error_log_str()
+            self.get_frac_error_log_str(),
+            self.child_concurrency.len(),
+            utils::format_float_opt(self.get_avg_child_concurrency()),
+            utils::format_float_opt(self.get_avg_child_wait_frac()),
         );
         line
     }
 }
 
+/// Convert the raw durations and start-times into a sketch and time-buckets. The raw vectors are emptied.
+pub(crate) fn compact_values(
+    duration_micros: &mut Vec<i64>,
+    start_dt_micros: &mut Vec<i64>,
+) -> (DurationSketch, TimeBuckets) {
+    let sketch = DurationSketch::from_values(duration_micros);
+    let buckets = TimeBuckets::from_values(start_dt_micros);
+    *duration_micros = Vec::new();
+    *start_dt_micros = Vec::new();
+    (sketch, buckets)
+}
+
+/// merge the compact durations and start-times of 'other' into 'curr'
+pub(crate) fn merge_compact(
+    curr: (&mut Option<DurationSketch>, &mut Option<TimeBuckets>),
+    other: (Option<DurationSketch>, Option<TimeBuckets>),
+) {
+    match (curr.0.as_mut(), other.0) {
+        (Some(ds), Some(other)) => ds.merge(&other),
+        (None, other) => *curr.0 = other,
+        (_, None) => (),
+    }
+    match (curr.1.as_mut(), other.1) {
+        (Some(tb), Some(other)) => tb.merge(&other),
+        (None, other) => *curr.1 = other,
+        (_, None) => (),
+    }
+}
+
+fn avg_opt(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
 type OperationString = String;
 /// the information is distributed over the key and the value (no duplication in value)
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct ProcOperStats(pub HashMap<OperationString, ProcOperStatsValue>);
+
+impl ProcOperStats {
+    /// merge the statistics per operation of 'other' into these statistics
+    pub fn merge(&mut self, other: ProcOperStats) {
+        other.0.into_iter().for_each(|(oper, pov)| {
+            self.0.entry(oper).or_default().merge(pov);
+        })
+    }
+
+    /// store the durations and start-times of all operations in compact form
+    pub fn compact(&mut self) {
+        self.0.values_mut().for_each(|pov| pov.compact());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ProcOperStats, ProcOperStatsValue};
+    use std::collections::HashMap;
+
+    fn value(durations: &[i64], http_codes: &[i16]) -> ProcOperStatsValue {
+        let mut pov = ProcOperStatsValue {
+            count: durations.len(),
+            num_traces: 1,
+            duration_micros: durations.to_vec(),
+            start_dt_micros: durations
+                .iter()
+                .map(|d| 1_700_000_000_000_000 + d)
+                .collect(),
+            num_not_http_ok: http_codes.len() as i32,
+            ..Default::default()
+        };
+        http_codes.iter().for_each(|code| {
+            pov.http_not_ok_codes.add_item(*code);
+        });
+        pov
+    }
+
+    #[test]
+    fn merge_raw_and_compact() {
+        let mut pos = ProcOperStats(HashMap::from([("a".to_string(), value(&[1_000], &[500]))]));
+        pos.merge(ProcOperStats(HashMap::from([
+            ("a".to_string(), value(&[2_000, 3_000], &[500, 404])),
+            ("b".to_string(), value(&[4_000], &[])),
+        ])));
+        let a = &pos.0["a"];
+        assert_eq!((a.count, a.num_traces, a.num_not_http_ok), (3, 2, 3));
+        assert_eq!(a.duration_micros, vec![1_000, 2_000, 3_000]);
+        assert_eq!(a.start_dt_micros.len(), 3);
+        assert_eq!(a.http_not_ok_codes.get_count(500), 2);
+        assert_eq!(a.http_not_ok_codes.get_count(404), 1);
+        assert_eq!(pos.0["b"].count, 1);
+
+        // a raw value merged into a compact value is compacted first
+        let mut compact = value(&[1_000], &[]);
+        compact.compact();
+        compact.merge(value(&[2_000, 3_000], &[]));
+        assert!(compact.duration_micros.is_empty());
+        assert_eq!(compact.duration_sketch.as_ref().unwrap().count, 3);
+        assert_eq!(compact.get_min_millis(), 1.0);
+        assert_eq!(compact.get_max_millis(), 3.0);
+    }
+}
@@ -0,0 +1,273 @@
+//! Service Level Objectives (SLO's) per endpoint or Service/Operation. The objectives are read from a json config-file and set via set_slo_config.
+//! An endpoint is the Service/Operation of the root-span of a trace, so endpoints and Service/Operations share the same key 'service/operation'.
+//! For each objective the compliance (fraction of calls within the latency threshold), the Apdex score, the burn-rate and the consumed error-budget are computed.
+use super::{file_windows::FileWindows, proc_oper_stats::ProcOperStatsValue, StatsRec};
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fs::File, io, path::Path, sync::Mutex};
+
+static SLO_CONFIG: Mutex<Option<SloConfig>> = Mutex::new(None);
+
+/// Set the SLO-configuration (None disables the SLO-reporting)
+pub fn set_slo_config(config: Option<SloConfig>) {
+    let mut guard = SLO_CONFIG.lock().unwrap();
+    *guard = config
+}
+
+pub fn get_slo_config() -> Option<SloConfig> {
+    SLO_CONFIG.lock().unwrap().clone()
+}
+
+fn default_period_days() -> f64 {
+    30.0
+}
+
+fn default_latency_target() -> f64 {
+    0.99
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SloObjective {
+    /// The endpoint or Service/Operation in the format 'service/operation'
+    pub key: String,
+    /// Latency threshold: a call is good if its duration is at most this value
+    pub latency_millis: Option<f64>,
+    /// Fraction of the calls that should be within the latency threshold (default 0.99)
+    #[serde(default = "default_latency_target")]
+    pub latency_target: f64,
+    /// Maximal fraction of calls that fail (HTTP-status not ok)
+    pub error_rate_target: Option<f64>,
+    /// The Apdex threshold T. Calls up to T are satisfied and up to 4T are tolerated. Defaults to the latency threshold.
+    pub apdex_millis: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SloConfig {
+    /// The period over which the error-budget is defined (default 30 days)
+    #[serde(default = "default_period_days")]
+    pub period_days: f64,
+    pub objectives: Vec<SloObjective>,
+}
+
+impl SloConfig {
+    /// Read the SLO-configuration from a json-file
+    pub fn read_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let f = File::open(path)?;
+        let reader = io::BufReader::new(f);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// The status of an objective for a Service/Operation, where the windows of the input-files determine which part of the period is covered.
+    pub fn evaluate(
+        &self,
+        objective: &SloObjective,
+        pov: &ProcOperStatsValue,
+        file_windows: &FileWindows,
+    ) -> SloStatus {
+        let count = pov.count as f64;
+        let frac_at_most = |millis: f64| pov.count_at_most_millis(millis) as f64 / count;
+
+        let compliance = objective.latency_millis.map(frac_at_most);
+        let error_rate = pov.num_errors() as f64 / count;
+        let apdex = objective
+            .apdex_millis
+            .or(objective.latency_millis)
+            .map(|t| {
+                let satisfied = frac_at_most(t);
+                let tolerating = frac_at_most(4.0 * t) - satisfied;
+                satisfied + tolerating / 2.0
+            });
+
+        // the burn-rate is the fraction of bad calls relative to the fraction that is allowed by the objective
+        let latency_burn = compliance
+            .filter(|_| objective.latency_target < 1.0)
+            .map(|c| (1.0 - c) / (1.0 - objective.latency_target));
+        let error_burn = objective
+            .error_rate_target
+            .filter(|t| *t > 0.0)
+            .map(|t| error_rate / t);
+        let burn_rate = match (latency_burn, error_burn) {
+            (Some(l), Some(e)) => Some(l.max(e)),
+            (l, e) => l.or(e),
+        };
+        let budget_consumed = burn_rate.and_then(|br| {
+            file_windows
+                .total_secs()
+                .map(|secs| br * secs / (self.period_days * 86_400.0))
+        });
+
+        SloStatus {
+            compliance,
+            apdex,
+            error_rate,
+            burn_rate,
+            budget_consumed,
+        }
+    }
+
+    /// Evaluate all objectives that have a matching Service/Operation in 'stats_rec'
+    pub fn evaluate_all<'a>(
+        &'a self,
+        stats_rec: &StatsRec,
+    ) -> Vec<(&'a SloObjective, usize, SloStatus)> {
+        self.objectives
+            .iter()
+            .filter_map(|obj| {
+                find_proc_oper(stats_rec, &obj.key).map(|pov| {
+                    (
+                        obj,
+                        pov.count,
+                        self.evaluate(obj, pov, &stats_rec.file_windows),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// Find the statistics of a Service/Operation via a key 'service/operation'. The service-name is the part before the first '/'.
+pub fn find_proc_oper<'a>(stats_rec: &'a StatsRec, key: &str) -> Option<&'a ProcOperStatsValue> {
+    let (process, operation) = key.split_once('/')?;
+    stats_rec
+        .stats
+        .get(process)
+        .and_then(|os| os.operation.0.get(operation))
+}
+
+/// The SLO-status of a single objective
+#[derive(Debug, Clone)]
+pub struct SloStatus {
+    /// Fraction of the calls within the latency threshold
+    pub compliance: Option<f64>,
+    pub apdex: Option<f64>,
+    pub error_rate: f64,
+    /// Rate at which the error-budget is consumed (1.0 means the budget is exactly used up at the end of the period)
+    pub burn_rate: Option<f64>,
+    /// Fraction of the error-budget of the period consumed during the time-window covered by the input-files
+    pub budget_consumed: Option<f64>,
+}
+
+impl SloStatus {
+    /// header for report_stats_line output in ';'-separated csv-format
+    pub fn report_stats_line_header_str() -> &'static str {
+        "Key; Count; Latency_millis; Latency_target; Compliance; Apdex; Error_rate; Error_rate_target; Burn_rate; Budget_consumed"
+    }
+
+    /// reports the status of an objective in ';'-separated csv-format
+    pub fn report_stats_line(&self, objective: &SloObjective, count: usize) -> String {
+        format!(
+            "{}; {count}; {}; {}; {}; {}; {}; {}; {}; {}",
+            objective.key,
+            utils::format_float_opt(objective.latency_millis),
+            utils::format_float(objective.latency_target),
+            utils::format_float_opt(self.compliance),
+            utils::format_float_opt(self.apdex),
+            utils::format_float(self.error_rate),
+            utils::format_float_opt(objective.error_rate_target),
+            utils::format_float_opt(self.burn_rate),
+            utils::format_float_opt(self.budget_consumed),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SloConfig, SloObjective};
+    use crate::stats::{
+        file_windows::{FileWindow, FileWindows},
+        ProcOperStatsValue,
+    };
+
+    fn objective(latency_millis: f64, latency_target: f64) -> SloObjective {
+        SloObjective {
+            key: "svc/oper".to_owned(),
+            latency_millis: Some(latency_millis),
+            latency_target,
+            error_rate_target: None,
+            apdex_millis: None,
+        }
+    }
+
+    /// 100 calls: 90 of 50 ms, 6 of 150 ms and 4 of 500 ms, of which 3 failed
+    fn pov() -> ProcOperStatsValue {
+        let duration_micros: Vec<_> = [(90, 50_000), (6, 150_000), (4, 500_000)]
+            .iter()
+            .flat_map(|(n, d)| vec![*d; *n])
+            .collect();
+        ProcOperStatsValue {
+            count: duration_micros.len(),
+            duration_micros,
+            num_not_http_ok: 3,
+            ..Default::default()
+        }
+    }
+
+    /// a single input-file covering one hour
+    fn one_hour() -> FileWindows {
+        FileWindows(vec![FileWindow {
+            file_name: "hour.json".to_owned(),
+            start_micros: 0,
+            end_micros: 3_600_000_000,
+            num_traces: 100,
+            sampling_weight: 100.0,
+        }])
+    }
+
+    fn assert_close(value: Option<f64>, expected: f64) {
+        let value = value.unwrap();
+        assert!((value - expected).abs() < 1e-9, "{value} != {expected}");
+    }
+
+    #[test]
+    fn apdex_and_burn_rate() {
+        let config = SloConfig {
+            period_days: 30.0,
+            objectives: Vec::new(),
+        };
+        let mut obj = objective(100.0, 0.95);
+        let status = config.evaluate(&obj, &pov(), &one_hour());
+        assert_close(status.compliance, 0.9);
+        // 90 satisfied (<= T) and 6 tolerating (<= 4T)
+        assert_close(status.apdex, 0.9 + 0.06 / 2.0);
+        assert_eq!(status.error_rate, 0.03);
+        // 10% slow calls where 5% is allowed
+        assert_close(status.burn_rate, 2.0);
+        assert_close(status.budget_consumed, 2.0 * 3_600.0 / (30.0 * 86_400.0));
+
+        // the error burn-rate (3% errors where 1% is allowed) dominates the latency burn-rate
+        obj.error_rate_target = Some(0.01);
+        let status = config.evaluate(&obj, &pov(), &one_hour());
+        assert_close(status.burn_rate, 3.0);
+
+        // calls with an error-log count as errors too, in the same way as in the canary and compare gates
+        let mut with_error_logs = pov();
+        with_error_logs.num_with_error_logs = 5;
+        let status = config.evaluate(&obj, &with_error_logs, &one_hour());
+        assert_eq!(status.error_rate, 0.05);
+        assert_close(status.burn_rate, 5.0);
+
+        // with a separate Apdex threshold all calls up to 200 ms are satisfied and those of 500 ms are tolerated
+        obj.apdex_millis = Some(200.0);
+        let status = config.evaluate(&obj, &pov(), &one_hour());
+        assert_close(status.apdex, 0.96 + 0.04 / 2.0);
+        assert_close(status.compliance, 0.9);
+    }
+
+    #[test]
+    fn no_budget_without_target_or_windows() {
+        let config = SloConfig {
+            period_days: 30.0,
+            objectives: Vec::new(),
+        };
+        // a latency-target of 1.0 leaves no budget, so no burn-rate is computed
+        let status = config.evaluate(&objective(100.0, 1.0), &pov(), &one_hour());
+        assert_close(status.compliance, 0.9);
+        assert!(status.burn_rate.is_none());
+        assert!(status.budget_consumed.is_none());
+
+        // the consumed budget requires the time-window of the input-files
+        let status = config.evaluate(&objective(100.0, 0.95), &pov(), &FileWindows::default());
+        assert_close(status.burn_rate, 2.0);
+        assert!(status.budget_consumed.is_none());
+    }
+}
@@ -1,11 +1,18 @@
 use super::{
+    cache_stats::{CacheStats, CacheStatsValue},
     call_chain::{
-        call_chain_key, get_call_chain, CChainEndPointCache, CChainStats, CChainStatsKey,
-        CChainStatsValue,
+        call_chain_key, get_call_chain, get_repair_strategy, repair_probabilistic,
+        CChainEndPointCache, CChainStats, CChainStatsKey, CChainStatsValue, CallChain, ChainInfo,
     },
-    file::StatsRecJson,
+    db_query_stats::DbQueryStats,
+    dimension_stats::{get_dimension_keys, get_split_dimensions},
+    fan_out_stats::{FanOutStats, FanOutStatsValue},
+    file::{StatsRecJson, STATS_REC_VERSION},
+    file_windows::FileWindows,
+    instance_stats::{instance_stats_enabled, InstanceDeviation, InstanceStats},
     operation_stats::OperationStats,
     proc_oper_stats::ProcOperStatsValue,
+    slo::{get_slo_config, SloStatus},
 };
 use crate::view_api::Version;
 use crate::{
@@ -18,8 +25,24 @@ use std::{
     error::Error,
     ffi::OsString,
     mem,
+    sync::Mutex,
 };
 
+static COMPACT_STATS: Mutex<bool> = Mutex::new(false);
+
+/// Store the durations and start-times of the statistics in compact form (sketches and time-buckets) when writing the statistics-files.
+pub fn set_compact_stats(val: bool) {
+    let mut guard = COMPACT_STATS.lock().unwrap();
+    *guard = val
+}
+
+pub fn compact_stats_enabled() -> bool {
+    *COMPACT_STATS.lock().unwrap()
+}
+
+/// Process/Operations with an average child-concurrency below this value are reported as calling their children sequentially
+const SEQUENTIAL_MAX_CONCURRENCY: f64 = 1.2;
+
 #[derive(Default, Clone)]
 pub struct BasicStatsRec {
     pub num_files: i32, // i32 is more convenient for compuations than an usize
@@ -76,6 +99,14 @@ pub struct StatsRec {
     pub caching_processes: Vec<String>,
     /// Statistis per leaf-process (end-point of the chain of processes)
     pub stats: HashMap<LeafService, OperationStats>, // hashmap based on the leaf process (as that is the initial level of reporting)
+    /// Number of calls per invocation for each parent-operation -> child-operation edge
+    pub fan_out: FanOutStats,
+    /// Statistics per Service/Operation and instance (host/pod). Only collected when enabled via set_instance_stats
+    pub instance_stats: InstanceStats,
+    /// Statistics per database query (grouped by database instance and normalized statement)
+    pub db_queries: DbQueryStats,
+    /// The observed time-window of each input-file, used to compute the rates
+    pub file_windows: FileWindows,
 }
 
 impl From<StatsRecJson> for StatsRec {
@@ -100,6 +131,10 @@ impl From<StatsRecJson> for StatsRec {
             time_to_respond_micros: srj.time_to_respond_micros,
             caching_processes: srj.caching_processes,
             stats,
+            fan_out: FanOutStats(srj.fan_out.into_iter().collect()),
+            instance_stats: InstanceStats(srj.instance_stats.into_iter().collect()),
+            db_queries: DbQueryStats(srj.db_queries.into_iter().collect()),
+            file_windows: FileWindows(srj.file_windows),
         }
     }
 }
@@ -121,21 +156,100 @@ impl StatsRec {
             init_num_unrooted_cc,
             num_fixes,
             num_unrooted_cc_after_fixes,
+            version: STATS_REC_VERSION,
             ..Default::default()
         }
     }
 
+    /// Merge the statistics of 'other' into this StatsRec, for example to combine hourly analyses into a daily analysis.
+    /// The per-trace data and the samples (durations and start-times) are concatenated and the counters are summed.
+    /// The number of end-points and call-chains are recomputed over the merged data. The number of initially unrooted call-chains is summed, and thus is an upper bound.
+    pub fn merge(&mut self, mut other: StatsRec) {
+        let compact = self.is_compact() || other.is_compact();
+        let known_ids: HashSet<_> = self.trace_id.iter().collect();
+        let num_duplicates = other
+            .trace_id
+            .iter()
+            .filter(|id| known_ids.contains(id))
+            .count();
+        if num_duplicates > 0 {
+            utils::report(
+                Chapter::Issues,
+                format!("Merged statistics contain {num_duplicates} traces that were already included. These traces are counted twice."),
+            );
+        }
+
+        self.trace_id.append(&mut other.trace_id);
+        self.root_call.append(&mut other.root_call);
+        self.num_spans.append(&mut other.num_spans);
+        self.start_dt.append(&mut other.start_dt);
+        self.end_dt.append(&mut other.end_dt);
+        self.duration_micros.append(&mut other.duration_micros);
+        self.time_to_respond_micros
+            .append(&mut other.time_to_respond_micros);
+
+        self.num_files += other.num_files;
+        self.num_incomplete_traces += other.num_incomplete_traces;
+        self.init_num_unrooted_cc += other.init_num_unrooted_cc;
+        self.num_fixes += other.num_fixes;
+        other.caching_processes.into_iter().for_each(|cp| {
+            if !self.caching_processes.contains(&cp) {
+                self.caching_processes.push(cp)
+            }
+        });
+
+        other.stats.into_iter().for_each(|(proc, stat)| {
+            self.stats.entry(proc).or_default().merge(stat);
+        });
+        self.fan_out.merge(other.fan_out);
+        self.instance_stats.merge(other.instance_stats);
+        self.db_queries.merge(other.db_queries);
+        self.file_windows.merge(other.file_windows);
+        if compact {
+            // values that are only present in one of the inputs still need to be compacted
+            self.compact();
+        }
+
+        self.num_endpoints = self.root_call.iter().collect::<HashSet<_>>().len();
+        let (total_cc, num_unrooted) = self.count_call_chains();
+        self.num_call_chains = total_cc;
+        self.num_unrooted_cc_after_fixes = num_unrooted;
+    }
+
+    /// true if the durations and start-times of this StatsRec are stored as sketches and time-buckets
+    pub fn is_compact(&self) -> bool {
+        self.stats
+            .values()
+            .flat_map(|os| os.operation.0.values())
+            .any(|pov| pov.is_compact())
+    }
+
+    /// Replace the durations and start-times of all Process/Operation and call-chain statistics by sketches and time-buckets.
+    /// Percentiles are approximate afterwards (bounded relative error), while the counts, min, max and average remain exact.
+    pub fn compact(&mut self) {
+        self.stats.values_mut().for_each(|os| os.compact());
+        self.instance_stats
+            .0
+            .values_mut()
+            .for_each(|pov| pov.compact());
+    }
+
     /// Read a StatsRecJson file and turn it into a StatsRec
     pub fn read_file(path: &OsString) -> Result<Self, Box<dyn Error>> {
         let srj = StatsRecJson::read_file(path)?;
         Ok(srj.into())
     }
 
-    /// Calculate the contents of the call-chain-file
-    pub fn call_chain_keys(&self) -> Vec<CChainStatsKey> {
+    /// Calculate the contents of the call-chain-file, i.e. the call-chains with their observed count and first/last-seen dates
+    pub fn call_chain_infos(&self) -> Vec<(CChainStatsKey, ChainInfo)> {
         self.stats
             .values()
-            .flat_map(|stat| stat.call_chain.0.keys().cloned())
+            .flat_map(|stat| {
+                stat.call_chain
+                    .0
+                    .iter()
+                    .map(|(cck, ccv)| (cck.clone(), ChainInfo::observed(ccv)))
+            })
             .collect()
     }
 
@@ -165,9 +279,19 @@ impl StatsRec {
         self.time_to_respond_micros
             .push(trace.time_to_respond_micros);
 
+        let children = spans.get_children();
+        self.fan_out.extend(spans, &children);
+        self.db_queries.extend(trace);
+        self.file_windows.extend(trace);
+
+        let with_instances = instance_stats_enabled();
+        let split_dimensions = get_split_dimensions();
+
         let mut proc_used = HashSet::new();
         // keep track of the proces/operation combinations used at least once in this process
         let mut proc_oper_used = HashSet::new();
+        // and the same for the proces/operation/dimension-value combinations
+        let mut proc_oper_dim_used = HashSet::new();
         spans
             .items
             .iter()
@@ -180,16 +304,35 @@ impl StatsRec {
                     true
                 }
             })
+            // spans inserted by a gap-repair were not observed, so they have no real duration or status. They only appear
+            // as a step in the call-chains of their descendants.
+            .filter(|(_, span)| !span.is_gap_repair())
             .for_each(|(idx, span)| {
                 let proc = span.get_process_str();
 
                 // keep track of the proces/operation (via &str references)
                 let _ = proc_used.insert(proc);
                 let _ = proc_oper_used.insert((proc, &span.operation_name));
+                if with_instances {
+                    self.instance_stats.update(span);
+                }
+                let dim_keys = get_dimension_keys(span, &split_dimensions);
+                dim_keys.iter().for_each(|dim_key| {
+                    let _ =
+                        proc_oper_dim_used.insert((proc, &span.operation_name, dim_key.clone()));
+                });
                 let proc = proc.to_owned();
 
                 let update_stat = |stat: &mut OperationStats| {
-                    stat.update(idx, span, spans, &self.caching_processes, &trace.root_call);
+                    stat.update(
+                        idx,
+                        span,
+                        spans,
+                        &children[idx],
+                        &self.caching_processes,
+                        &trace.root_call,
+                        &dim_keys,
+                    );
                 };
 
                 // This is the actual insert or update based on the 'update_stats'.
@@ -217,6 +360,18 @@ impl StatsRec {
                     .and_modify(|oper| oper.num_traces += 1);
             });
         });
+        proc_oper_dim_used
+            .into_iter()
+            .for_each(|(proc, oper, dim_key)| {
+                self.stats.entry(proc.to_owned()).and_modify(|st| {
+                    st.dimensions.0.entry(dim_key).and_modify(|dsv| {
+                        dsv.operation
+                            .0
+                            .entry(oper.to_owned())
+                            .and_modify(|oper| oper.num_traces += 1);
+                    });
+                });
+            });
     }
 
     pub fn to_csv_string(&self) -> String {
@@ -322,12 +477,71 @@ impl StatsRec {
         s.push(ProcOperStatsValue::report_stats_line_header_str().to_owned());
         data.iter().for_each(|(k, stat)| {
             stat.operation.0.iter().for_each(|(method, meth_stat)| {
-                let line = meth_stat.report_stats_line(k, method, num_traces, self.num_files);
+                let line = meth_stat.report_stats_line(
+                    k,
+                    method,
+                    num_traces,
+                    &self.file_windows,
+                    self.num_files,
+                );
                 s.push(line);
             })
         });
         s.push("\n".to_owned());
 
+        let has_dimensions = data.iter().any(|(_, stat)| !stat.dimensions.0.is_empty());
+        if has_dimensions {
+            s.push(
+                "#Statistics per Process/Operation split by dimension (attribute=value)".to_owned(),
+            );
+            s.push(format!(
+                "Dimension; {}",
+                ProcOperStatsValue::report_stats_line_header_str()
+            ));
+            data.iter().for_each(|(k, stat)| {
+                let mut dims: Vec<_> = stat.dimensions.0.iter().collect();
+                dims.sort_by(|a, b| a.0.cmp(b.0));
+                dims.into_iter().for_each(|(dim_key, dsv)| {
+                    dsv.operation.0.iter().for_each(|(method, meth_stat)| {
+                        let line = meth_stat.report_stats_line(
+                            k,
+                            method,
+                            num_traces,
+                            &self.file_windows,
+                            self.num_files,
+                        );
+                        s.push(format!("{}; {line}", dim_key.to_key_str()));
+                    })
+                })
+            });
+            s.push("\n".to_owned());
+
+            s.push("#Statistics per call-chain split by dimension (attribute=value)".to_owned());
+            s.push(format!(
+                "Dimension; {}",
+                CChainStatsValue::report_stats_line_header_str()
+            ));
+            data.iter().for_each(|(k, stat)| {
+                let mut dims: Vec<_> = stat.dimensions.0.iter().collect();
+                dims.sort_by(|a, b| a.0.cmp(b.0));
+                dims.into_iter().for_each(|(dim_key, dsv)| {
+                    let mut cc_data: Vec<_> = dsv.call_chain.0.iter().collect();
+                    cc_data.sort_by(|a, b| a.0.cmp(b.0));
+                    cc_data.into_iter().for_each(|(ps_key, cchain_stats)| {
+                        let line = cchain_stats.report_stats_line(
+                            k,
+                            ps_key,
+                            num_traces,
+                            &self.file_windows,
+                            self.num_files,
+                        );
+                        s.push(format!("{}; {line}", dim_key.to_key_str()));
+                    })
+                })
+            });
+            s.push("\n".to_owned());
+        }
+
         s.push("#The unique key of the next table is 'Call_Chain' (which includes full path and the leaf-marker). So the Process column contains duplicates".to_owned());
 
         s.push(CChainStatsValue::report_stats_line_header_str().to_owned());
@@ -345,10 +559,117 @@ impl StatsRec {
             .collect::<Vec<_>>();
         ps_data.sort_by(|a, b| a.0.cmp(b.0));
         ps_data.into_iter().for_each(|(ps_key, key, cchain_stats)| {
-            s.push(cchain_stats.report_stats_line(&key, ps_key, num_traces, self.num_files))
+            s.push(cchain_stats.report_stats_line(
+                &key,
+                ps_key,
+                num_traces,
+                &self.file_windows,
+                self.num_files,
+            ))
         });
         s.push("\n".to_owned());
 
+        s.push("#Sequential child-calls: Process/Operations that call their children (mostly) sequentially, ranked on the total time waiting for children. These are candidates for parallelization of downstream calls".to_owned());
+        s.push("Process/Oper; Num_multi_child; Avg_child_concurrency; Avg_child_wait_frac; Total_wait_millis".to_owned());
+        let mut seq_data: Vec<_> = self
+            .stats
+            .iter()
+            .flat_map(|(k, stat)| {
+                stat.operation.0.iter().filter_map(move |(oper, pov)| {
+                    match (
+                        pov.get_avg_child_concurrency(),
+                        pov.get_avg_child_wait_frac(),
+                    ) {
+                        (Some(conc), Some(wait)) if conc < SEQUENTIAL_MAX_CONCURRENCY => {
+                            let total_wait =
+                                pov.get_avg_millis() * wait * pov.child_wait_frac.len() as f64;
+                            Some((
+                                format!("{k}/{oper}"),
+                                pov.child_concurrency.len(),
+                                conc,
+                                wait,
+                                total_wait,
+                            ))
+                        }
+                        _ => None,
+                    }
+                })
+            })
+            .collect();
+        seq_data.sort_by(|a, b| b.4.total_cmp(&a.4));
+        seq_data
+            .into_iter()
+            .for_each(|(po, num, conc, wait, total_wait)| {
+                s.push(format!(
+                    "{po}; {num}; {}; {}; {}",
+                    utils::format_float(conc),
+                    utils::format_float(wait),
+                    utils::format_float(total_wait)
+                ))
+            });
+        s.push("\n".to_owned());
+
+        s.push("#Fan-out: number of calls to the child per invocation of the parent (only invocations that call the child at least once)".to_owned());
+        s.push(FanOutStatsValue::report_stats_line_header_str().to_owned());
+        let mut fo_data: Vec<_> = self.fan_out.0.iter().collect();
+        fo_data.sort_by(|a, b| a.0.cmp(b.0));
+        fo_data
+            .into_iter()
+            .for_each(|(k, fov)| s.push(fov.report_stats_line(k)));
+        s.push("\n".to_owned());
+
+        s.push("#Chatty edges ranked on the number of excess calls (calls that could be saved by batching). Pattern 'N+1' means the fan-out grows with the duration of the parent".to_owned());
+        s.push(FanOutStatsValue::report_stats_line_header_str().to_owned());
+        self.fan_out
+            .chatty_edges()
+            .into_iter()
+            .for_each(|(k, fov)| s.push(fov.report_stats_line(k)));
+        s.push("\n".to_owned());
+
+        if !self.instance_stats.0.is_empty() {
+            s.push("#Statistics per instance (host/pod) of each Process/Operation".to_owned());
+            s.push(InstanceStats::report_stats_line_header_str().to_owned());
+            let mut inst_data: Vec<_> = self.instance_stats.0.iter().collect();
+            inst_data.sort_by(|a, b| a.0.cmp(b.0));
+            inst_data
+                .into_iter()
+                .for_each(|(k, pov)| s.push(InstanceStats::report_stats_line(k, pov)));
+            s.push("\n".to_owned());
+
+            s.push("#Instances that deviate significantly from the other instances (siblings) of the same Process/Operation".to_owned());
+            s.push(InstanceDeviation::report_stats_line_header_str().to_owned());
+            self.instance_stats
+                .get_deviations()
+                .into_iter()
+                .for_each(|dev| s.push(dev.report_stats_line()));
+            s.push("\n".to_owned());
+        }
+
+        if !self.caching_processes.is_empty() {
+            s.push("#Cache hit-ratio per call-chain towards a caching process. A call without downstream calls (leaf) is a hit. The flushed downstream calls estimate the load when each call is a miss".to_owned());
+            s.push(CacheStatsValue::report_stats_line_header_str().to_owned());
+            CacheStats::new(self)
+                .0
+                .iter()
+                .for_each(|(k, csv)| s.push(csv.report_stats_line(k)));
+            s.push("\n".to_owned());
+        }
+
+        if let Some(slo_config) = get_slo_config() {
+            s.push(format!("#Service Level Objectives per endpoint or Service/Operation. The budget consumed is relative to a period of {} days", slo_config.period_days));
+            s.push(SloStatus::report_stats_line_header_str().to_owned());
+            slo_config
+                .evaluate_all(self)
+                .into_iter()
+                .for_each(|(obj, count, status)| s.push(status.report_stats_line(obj, count)));
+            s.push("\n".to_owned());
+        }
+
+        s.push("#Observed time-window per input-file, used to compute the rates. The trace-rate is corrected for probabilistic sampling ('sampler.param')".to_owned());
+        s.push(FileWindows::report_stats_line_header_str().to_owned());
+        s.extend(self.file_windows.report_stats_lines());
+        s.push("\n".to_owned());
+
         s.join("\n")
     }
 
@@ -409,6 +730,10 @@ impl StatsRec {
             .collect()
     }
 
+    /// Fix the non-rooted call-chains by remapping them on a rooted call-chain of the cchain-cache, and return the number of fixes.
+    /// A repaired call-chain is merged into the rooted call-chain via CChainStatsValue::merge, such that its start-times, error-counts,
+    /// expected roots and repair-information are kept. (Summing only the count and the durations drops the errors of the repaired calls
+    /// and leaves fewer start-times than durations.)
     pub fn fix_call_chain(&mut self, cchain_cache: &mut CChainEndPointCache) -> usize {
         let mut num_fixes = 0;
 
@@ -416,7 +741,7 @@ impl StatsRec {
         let new_stats: HashMap<_, _> = mem::take(&mut self.stats)
             .into_iter()
             .map(|(key, mut stats)| {
-                    let (rooted, mut non_rooted): (Vec<_>, Vec<_>) = stats.call_chain.0
+                    let (rooted, non_rooted): (Vec<_>, Vec<_>) = stats.call_chain.0
                         .into_iter()
                         .partition(|(_k2, v2)| v2.rooted);
 
@@ -425,10 +750,16 @@ impl StatsRec {
                         utils::report(Chapter::Details, format!("For key '{key}'  found {} non-rooted out of {} traces with call-chain depths {depths:?}", non_rooted.len(), non_rooted.len() + rooted.len()));
                     }
 
+                    // observed frequencies of the rooted call-chains, which are used for a probabilistic repair
+                    let mut observed: HashMap<CallChain, usize> = HashMap::new();
+                    rooted.iter().for_each(|(k, v)| *observed.entry(k.call_chain.clone()).or_default() += v.count);
+
                     // fix the non-rooted paths by a rewrite of the key
+                    let num_non_rooted = non_rooted.len();
                     let mut fix_failed = 0;
-                    non_rooted.iter_mut()
-                        .for_each(|(cck, v)| {
+                    let mut new_call_chain = CChainStats(rooted.into_iter().collect());
+                    non_rooted.into_iter()
+                        .for_each(|(mut cck, mut v)| {
                             if let Some(expect_cc) = v
                                 .expect_root
                                 .get_frequent_endpoint()
@@ -438,30 +769,28 @@ impl StatsRec {
                                     assert!(!v.rooted);  // should be false
                                     num_fixes += 1;
                                     v.rooted = true;
+                                    v.set_repaired(1.0);
+                                    new_call_chain.merge(CChainStats(HashMap::from([(cck, v)])));
                                 } else {
-                                    fix_failed += 1;
+                                    let (fixed, parts) = repair_probabilistic(cck, v, expect_cc, &observed);
+                                    if fixed {
+                                        num_fixes += 1;
+                                    } else {
+                                        fix_failed += 1;
+                                    }
+                                    new_call_chain.merge(CChainStats(parts.into_iter().collect()));
                                 }
                             } else {
                                 println!("Failed to find call-chain");
                                 fix_failed += 1;
+                                new_call_chain.merge(CChainStats(HashMap::from([(cck, v)])));
                             }
                     });
                     if fix_failed > 0 {
-                        utils::report(Chapter::Details, format!("Failed to fix {fix_failed} chains out of {} non-rooted chains. ({num_fixes} fixes applied succesful)", non_rooted.len()));
+                        utils::report(Chapter::Details, format!("Failed to fix {fix_failed} chains out of {num_non_rooted} non-rooted chains. ({num_fixes} fixes applied succesful)"));
                     }
 
-                    let new_call_chain = rooted.into_iter()
-                        .chain(non_rooted)
-                        .fold(HashMap::new(), |mut cc, (k, mut v_new)| {
-                            cc.entry(k)
-                                .and_modify(|v_curr: &mut CChainStatsValue| {
-                                    v_curr.count += v_new.count;
-                                    v_curr.duration_micros.append(&mut v_new.duration_micros);
-                                })
-                                .or_insert(v_new);
-                            cc
-                        });
-                    stats.call_chain = CChainStats( new_call_chain );
+                    stats.call_chain = new_call_chain;
                     (key, stats)
             })
             .collect();
@@ -469,6 +798,38 @@ impl StatsRec {
 
         num_fixes
     }
+
+    /// Summarize the repairs of the non-rooted call-chains in the report: the number of repaired calls and their average confidence.
+    pub fn report_repairs(&self) {
+        let repaired: Vec<_> = self
+            .stats
+            .values()
+            .flat_map(|os| os.call_chain.0.values())
+            .filter(|ccv| ccv.num_repaired > 0)
+            .collect();
+        let num_repaired: usize = repaired.iter().map(|ccv| ccv.num_repaired).sum();
+        if num_repaired == 0 {
+            return;
+        }
+        let weighted_confidence: f64 = repaired
+            .iter()
+            .map(|ccv| ccv.repair_confidence.unwrap_or(1.0) * ccv.num_repaired as f64)
+            .sum();
+        let num_uncertain: usize = repaired
+            .iter()
+            .filter(|ccv| ccv.repair_confidence.unwrap_or(1.0) < 1.0)
+            .map(|ccv| ccv.num_repaired)
+            .sum();
+        let (strategy, min_confidence) = get_repair_strategy();
+        utils::report(
+            Chapter::Summary,
+            format!(
+                "Repaired {num_repaired} calls on {} call-chains (repair-strategy {strategy:?}, min_confidence {min_confidence}) with an average confidence of {:.3}. {num_uncertain} of these calls are on call-chains with a confidence below 1.0 (repaired based on observed frequencies).",
+                repaired.len(),
+                weighted_confidence / num_repaired as f64
+            ),
+        );
+    }
 }
 
 /// Compute basic call statistics, which only looks at functions/operations and does not include the call path
@@ -512,3 +873,152 @@ fn root_call_list(trace_ids: &[String], root_calls: &[String]) -> String {
         .collect();
     labelled.join(",   ")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{BasicStatsRec, CChainEndPointCache, StatsRec};
+    use crate::processed::{
+        test_traces::{build_trace, TestSpan},
+        Trace,
+    };
+    use serde_json::json;
+    use std::{collections::BTreeMap, fs};
+
+    fn trace_a() -> Trace {
+        build_trace(
+            "t1",
+            &[
+                TestSpan::new("a0", None, "gateway/GET /api", 0, 10_000),
+                TestSpan::new("a1", Some("a0"), "svca/call", 1_000, 5_000),
+                TestSpan::new("a2", Some("a1"), "app/handle", 2_000, 2_000),
+            ],
+        )
+    }
+
+    fn trace_b() -> Trace {
+        build_trace(
+            "t2",
+            &[
+                TestSpan::new("b0", None, "gateway/GET /api", 0, 20_000),
+                TestSpan::new("b1", Some("b0"), "svcb/call", 1_000, 15_000)
+                    .tag("http.status_code", json!(500)),
+                TestSpan::new("b2", Some("b1"), "app/handle", 2_000, 3_000),
+            ],
+        )
+    }
+
+    /// a trace of which the root is missing, so the call-chain of 'app/handle' is not rooted
+    fn trace_c() -> Trace {
+        build_trace(
+            "t3",
+            &[
+                TestSpan::new("c1", Some("missing"), "svca/call", 1_000, 6_000),
+                TestSpan::new("c2", Some("c1"), "app/handle", 2_000, 4_000),
+            ],
+        )
+    }
+
+    fn stats_rec(traces: &[Trace], caching_processes: &[&str]) -> StatsRec {
+        let mut sr = StatsRec::new(BasicStatsRec {
+            caching_processes: caching_processes.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        });
+        traces
+            .iter()
+            .for_each(|trace| sr.extend_statistics(trace, false));
+        sr
+    }
+
+    /// the counts, sorted durations and error-counts per Process/Operation and per call-chain
+    fn summary(sr: &StatsRec) -> BTreeMap<String, (usize, Vec<i64>, i32, Vec<(String, usize)>)> {
+        let mut summary = BTreeMap::new();
+        sr.stats.iter().for_each(|(proc, os)| {
+            os.operation.0.iter().for_each(|(oper, pov)| {
+                let mut durations = pov.duration_micros.clone();
+                durations.sort();
+                let key = format!("{proc}/{oper}");
+                summary.insert(key, (pov.count, durations, pov.num_not_http_ok, Vec::new()));
+            });
+            os.call_chain.0.iter().for_each(|(cck, ccv)| {
+                let mut durations = ccv.duration_micros.clone();
+                durations.sort();
+                let mut roots: Vec<_> = ccv
+                    .expect_root
+                    .0
+                    .iter()
+                    .map(|er| (er.proc_oper.clone(), er.count))
+                    .collect();
+                roots.sort();
+                summary.insert(
+                    cck.call_chain_key(),
+                    (ccv.count, durations, ccv.cc_not_http_ok, roots),
+                );
+            });
+        });
+        summary
+    }
+
+    #[test]
+    fn merge_equals_combined_analysis() {
+        let mut merged = stats_rec(&[trace_a()], &["svca"]);
+        merged.merge(stats_rec(&[trace_b(), trace_c()], &["svcb", "svca"]));
+        let combined = stats_rec(&[trace_a(), trace_b(), trace_c()], &["svca", "svcb"]);
+
+        assert_eq!(merged.trace_id, vec!["t1", "t2", "t3"]);
+        assert_eq!(merged.root_call, combined.root_call);
+        assert_eq!(merged.num_spans, vec![3, 3, 2]);
+        assert_eq!(merged.duration_micros, combined.duration_micros);
+        assert_eq!(merged.caching_processes, vec!["svca", "svcb"]);
+        assert_eq!(merged.num_endpoints, 2);
+        assert_eq!(merged.num_call_chains, combined.count_call_chains().0);
+
+        let merged = summary(&merged);
+        assert_eq!(merged, summary(&combined));
+        assert_eq!(merged["app/handle"].0, 3);
+        assert_eq!(merged["app/handle"].1, vec![2_000, 3_000, 4_000]);
+        assert_eq!(merged["svcb/call"].2, 1);
+    }
+
+    #[test]
+    fn fix_call_chain_merges_all_fields() {
+        // the root is present, but the parent of 'svca/call' is missing
+        let trace_d = build_trace(
+            "t4",
+            &[
+                TestSpan::new("d0", None, "gateway/GET /api", 0, 30_000),
+                TestSpan::new("d1", Some("missing"), "svca/call", 1_000, 8_000),
+                TestSpan::new("d2", Some("d1"), "app/handle", 2_000, 7_000)
+                    .tag("http.status_code", json!(500)),
+            ],
+        );
+        let rooted = stats_rec(&[trace_a()], &[]);
+        let mut sr = stats_rec(&[trace_a(), trace_d], &[]);
+
+        let folder = std::env::temp_dir().join(format!("fix_cchain_test_{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let mut cchain_cache = CChainEndPointCache::new(folder.clone());
+        cchain_cache.create_update_entry(
+            &CChainEndPointCache::str_to_cache_key("gateway/GET /api"),
+            rooted.call_chain_infos(),
+        );
+        assert_eq!(sr.fix_call_chain(&mut cchain_cache), 2);
+        drop(cchain_cache);
+        fs::remove_dir_all(folder).unwrap();
+
+        let (cck, ccv) = sr.stats["app"]
+            .call_chain
+            .0
+            .iter()
+            .find(|(cck, _)| cck.is_leaf)
+            .unwrap();
+        assert_eq!(cck.call_chain.len(), 3);
+        assert!(ccv.rooted);
+        assert_eq!(ccv.count, 2);
+        assert_eq!(ccv.duration_micros, vec![2_000, 7_000]);
+        assert_eq!(ccv.start_dt_micros.len(), 2);
+        assert_eq!(ccv.cc_not_http_ok, 1);
+        assert_eq!(ccv.http_not_ok.get_count(500), 1);
+        assert_eq!(ccv.num_repaired, 1);
+        assert_eq!(ccv.repair_confidence, Some(1.0));
+    }
+}
@@ -1,5 +1,20 @@
-use crate::{processed::Trace, stats::stats_rec::StatsRec, utils};
-use std::path::Path;
+use crate::{mermaid::Waterfall, processed::Trace, stats::stats_rec::StatsRec, utils};
+use std::{path::Path, sync::Mutex};
+
+/// number of characters of the time-axis of the waterfall written next to each trace
+const WATERFALL_WIDTH: usize = 60;
+
+static WATERFALL_OUTPUT: Mutex<bool> = Mutex::new(false);
+
+/// Enable (or disable) writing a waterfall (.waterfall.txt) next to each trace that is written via 'write_trace'.
+pub fn set_waterfall_output(val: bool) {
+    let mut guard = WATERFALL_OUTPUT.lock().unwrap();
+    *guard = val
+}
+
+fn waterfall_output_enabled() -> bool {
+    *WATERFALL_OUTPUT.lock().unwrap()
+}
 
 /// Collect statistics as a string and write it to a textfile in CSV format
 pub fn write_stats_to_csv_file(csv_file: &str, stats: &StatsRec) {
@@ -38,6 +53,12 @@ impl TraceExt {
         //println!("Now writing the read Jaeger_trace to {output_file}");
         utils::write_string_to_file(&output_file, trace_str)
             .expect("Failed to write trace (.txt) to file");
+        if waterfall_output_enabled() {
+            let waterfall_str = Waterfall::new(&self.trace).to_text(WATERFALL_WIDTH, false);
+            let output_file = format!("{}.waterfall.txt", self.base_name);
+            utils::write_string_to_file(&output_file, waterfall_str)
+                .expect("Failed to write waterfall (.waterfall.txt) to file");
+        }
     }
 }
 
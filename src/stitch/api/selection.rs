@@ -35,10 +35,12 @@ fn get_stitch_sources(original: &Stitched, selection: &Vec<bool>) -> StitchSourc
     StitchSources(sel_src)
 }
 
-/// get a copy of the process_operation data for a specific selection
-fn get_proc_oper_selection(original: &Stitched, selection: &[bool]) -> Vec<(String, StitchedSet)> {
+/// get a copy of a list of stitched sets (process_operation or db_query data) for a specific selection
+fn get_stitched_set_selection(
+    original: &[(String, StitchedSet)],
+    selection: &[bool],
+) -> Vec<(String, StitchedSet)> {
     original
-        .service_operation
         .iter()
         .filter_map(|(k, stitched_set)| {
             stitched_set
@@ -72,7 +74,9 @@ fn get_call_chain_selection(
 
 /// get a derived dataset that only contains the selected columns
 pub fn get_derived_stitched(original: &Stitched, selection: &Vec<bool>) -> Arc<Stitched> {
-    let process_operation = get_proc_oper_selection(original, selection);
+    let process_operation = get_stitched_set_selection(&original.service_operation, selection);
+    let db_query = get_stitched_set_selection(&original.db_query, selection);
+    let cache = get_stitched_set_selection(&original.cache, selection);
     let call_chain = get_call_chain_selection(original, selection);
     let sources = get_stitch_sources(original, selection);
     let version = original.version;
@@ -82,5 +86,7 @@ pub fn get_derived_stitched(original: &Stitched, selection: &Vec<bool>) -> Arc<S
         basic: StitchedSet(Vec::new()), // exluded from copy
         service_operation: process_operation,
         call_chain,
+        db_query,
+        cache,
     })
 }
@@ -4,6 +4,7 @@ use super::{
     utils,
 };
 use crate::{
+    mermaid::{BlastRadius, ServicePaths},
     view_api::types::{ChartDataParameters, ProcessList, Selection, Table}, 
     MermaidScope, 
     Metric, 
@@ -114,6 +115,18 @@ impl Viewer for StitchedDataSet {
             .get_mermaid_diagram(service_oper, call_chain_key, edge_value, scope, compact)
     }
 
+    fn get_sequence_diagram(&self, service_oper: &str, max_chains: usize) -> String {
+        self.current.get_sequence_diagram(service_oper, max_chains)
+    }
+
+    fn get_blast_radius(&self, service_oper: &str) -> BlastRadius {
+        self.current.get_blast_radius(service_oper)
+    }
+
+    fn get_paths_between(&self, from: &str, to: &str) -> ServicePaths {
+        self.current.get_paths_between(from, to)
+    }
+
     fn get_call_chain_chart_data(
         &self,
         call_chain_key: &str,
@@ -35,14 +35,23 @@ fn get_month_description(month: &str) -> &str {
 pub fn get_label_list(data: &Stitched) -> Vec<String> {
     // TODO: we could guard against multiple matches being present
     let re = Regex::new(r"(\d{4})(\d{2})(\d{2})").expect("Failed to create regexp for dates");
+    // the time-buckets of a single analysis (described as "bucket yyyy-mm-ddThh:mm") are labeled by their time of day
+    let re_time = Regex::new(r"^bucket \d{4}-\d{2}-\d{2}T(\d{2}):(\d{2})$")
+        .expect("Failed to create regexp for times");
 
     data.sources
         .0
         .iter()
         .filter(|src| src.column.is_some())
         .enumerate()
-        .map(
-            |(idx, src)| match re.captures(&src.description).map(|caps| caps.extract()) {
+        .map(|(idx, src)| {
+            if let Some((_full, [hour, minute])) = re_time
+                .captures(&src.description)
+                .map(|caps| caps.extract())
+            {
+                return format!("{hour}:{minute}");
+            }
+            match re.captures(&src.description).map(|caps| caps.extract()) {
                 Some((_full, [_year, month, day])) => {
                     let month = get_month_description(month);
                     // remove the 0-prefix if it exists
@@ -54,8 +63,8 @@ pub fn get_label_list(data: &Stitched) -> Vec<String> {
                     format!("{month}-{day}")
                 }
                 None => format!("{}", idx),
-            },
-        )
+            }
+        })
         .collect()
 }
 
@@ -422,3 +431,35 @@ pub fn get_file_stats(data: &Stitched) -> Table {
         data: values,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::get_label_list;
+    use crate::stitch::{StitchSourceItem, StitchSources, Stitched};
+
+    #[test]
+    fn labels_of_dates_and_time_buckets() {
+        let stitched = |descriptions: &[&str]| Stitched {
+            sources: StitchSources(
+                descriptions
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, descr)| StitchSourceItem::new(Some(idx as u32), descr))
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+        assert_eq!(
+            get_label_list(&stitched(&[
+                "bucket 2023-11-14T22:10",
+                "bucket 2023-11-14T22:15"
+            ])),
+            vec!["22:10", "22:15"]
+        );
+        // a stitched source that happens to contain a time is labeled by its date
+        assert_eq!(
+            get_label_list(&stitched(&["run 20231008 T10:30", "20231102", "other"])),
+            vec!["Oct-8", "Nov-2", "2"]
+        );
+    }
+}
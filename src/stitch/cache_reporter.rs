@@ -0,0 +1,59 @@
+use crate::{
+    stats::{CacheStats, CacheStatsValue},
+    AnomalyParameters, Metric,
+};
+use std::collections::HashSet;
+
+use super::stitched_line::StitchedLine;
+
+type Processor = fn(&CacheStatsValue) -> Option<f64>;
+type CacheData<'a> = Vec<Option<&'a CacheStatsValue>>;
+
+/// Cache report items
+pub struct CacheReportItem {
+    pub metric: Metric,
+    processor: Processor,
+}
+
+/// this container of ReportItems is used to bundle the methods that run over the cache statistics of a series of StatsRecs.
+pub struct CacheReportItems(pub Vec<CacheReportItem>);
+
+impl CacheReportItem {
+    pub fn new(metric: Metric, processor: Processor) -> Self {
+        Self { metric, processor }
+    }
+
+    pub fn extract_stitched_line(
+        &self,
+        data: &CacheData,
+        pars: &AnomalyParameters,
+    ) -> StitchedLine {
+        let values = data
+            .iter()
+            .map(|ms| ms.and_then(self.processor))
+            .collect::<Vec<_>>();
+
+        StitchedLine::compute_new(self.metric, values, pars)
+    }
+}
+
+impl CacheReportItems {
+    /// get all the call-chains towards a caching process that are present in at least one of the columns
+    pub fn get_keys(data: &[Option<CacheStats>]) -> Vec<String> {
+        let mut keys = HashSet::new();
+        data.iter().flatten().for_each(|cache_stats| {
+            cache_stats.0.keys().for_each(|k| {
+                _ = keys.insert(k.clone());
+            })
+        });
+        let mut keys: Vec<_> = keys.into_iter().collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    pub fn extract_dataset<'a>(data: &'a [Option<CacheStats>], key: &str) -> CacheData<'a> {
+        data.iter()
+            .map(|cache_stats| cache_stats.as_ref().and_then(|cs| cs.0.get(key)))
+            .collect()
+    }
+}
@@ -11,7 +11,7 @@ use std::{cmp::Ordering, collections::HashMap};
 
 /// The POData is the input for the processor (which is a series of report-closures.
 /// If the processor operated on a tuple we could extract a joined type from the next two types.
-type ProcessorInput<'a> = (&'a CChainStatsValue, i32, usize);
+type ProcessorInput<'a> = (&'a CChainStatsValue, &'a StatsRec, usize);
 type Processor = fn(&ProcessorInput) -> Option<f64>;
 type CCData<'a> = Vec<Option<ProcessorInput<'a>>>;
 
@@ -110,7 +110,7 @@ impl CCReportItems {
 
         // a ref to the extract the three values that are needed for the analysis being:
         //    1. the complete CallChainValue record
-        //    2. the number of files in the analysis
+        //    2. the StatsRec containing the value (the file-windows and number of files are needed for the rates)
         //    3. the number of traces included in this analysis
         data.iter()
             .map(|stats_rec| {
@@ -119,7 +119,7 @@ impl CCReportItems {
                         st.call_chain
                             .0
                             .get(cc_key)
-                            .map(|oper| (oper, stats_rec.num_files, stats_rec.trace_id.len()))
+                            .map(|oper| (oper, stats_rec, stats_rec.trace_id.len()))
                     })
                 })
             })
@@ -0,0 +1,69 @@
+use crate::{
+    stats::{DbQueryKey, DbQueryStatsValue, StatsRec},
+    AnomalyParameters, Metric,
+};
+use std::collections::HashSet;
+
+use super::stitched_line::StitchedLine;
+
+/// The DQData is the input for the processor (which is a series of report-closures).
+type ProcessorInput<'a> = (&'a DbQueryStatsValue, &'a StatsRec, usize);
+type Processor = fn(&ProcessorInput) -> Option<f64>;
+type DQData<'a> = Vec<Option<ProcessorInput<'a>>>;
+
+/// Database-query report items
+pub struct DQReportItem {
+    pub metric: Metric,
+    processor: Processor,
+}
+
+/// this container of ReportItems is used to bundle the methods that run over the database-query statistics of a series of StatsRecs.
+pub struct DQReportItems(pub Vec<DQReportItem>);
+
+impl DQReportItem {
+    pub fn new(metric: Metric, processor: Processor) -> Self {
+        Self { metric, processor }
+    }
+
+    pub fn extract_stitched_line(&self, data: &DQData, pars: &AnomalyParameters) -> StitchedLine {
+        let values = data
+            .iter()
+            .map(|ms| ms.as_ref().and_then(self.processor))
+            .collect::<Vec<_>>();
+
+        StitchedLine::compute_new(self.metric, values, pars)
+    }
+}
+
+impl DQReportItems {
+    /// get all the database-query keys that are present in at least one of the StatsRecs
+    pub fn get_keys(data: &[Option<StatsRec>]) -> Vec<DbQueryKey> {
+        let mut keys = HashSet::new();
+        data.iter().flatten().for_each(|stats_rec| {
+            stats_rec.db_queries.0.keys().for_each(|k| {
+                _ = keys.insert(k.clone());
+            })
+        });
+        let mut keys: Vec<_> = keys.into_iter().collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    pub fn extract_dataset<'a>(data: &'a [Option<StatsRec>], dq_key: &'a DbQueryKey) -> DQData<'a> {
+        // extract the three values that are needed for the analysis being:
+        //    1. the DbQueryStatsValue record
+        //    2. the StatsRec containing the value (the file-windows and number of files are needed for the rates)
+        //    3. the number of traces included in this analysis
+        data.iter()
+            .map(|stats_rec| {
+                stats_rec.as_ref().and_then(|stats_rec| {
+                    stats_rec
+                        .db_queries
+                        .0
+                        .get(dq_key)
+                        .map(|dqv| (dqv, stats_rec, stats_rec.trace_id.len()))
+                })
+            })
+            .collect()
+    }
+}
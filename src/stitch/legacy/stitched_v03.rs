@@ -0,0 +1,49 @@
+use super::super::{
+    call_chain_data::CallChainData, stitch_list::StitchSources, stitched_set::StitchedSet, Stitched,
+};
+use crate::{view_api::Version, ServiceOperString};
+use serde::{Deserialize, Serialize};
+
+/// Frozen copy of the Stitched layout of version 0.3 (before the database-query and cache statistics were added).
+/// Bincode ignores '#[serde(default)]', so bincode-files in this layout can only be read via this struct.
+#[derive(Default, Serialize, Deserialize)]
+pub struct LegacyStitchedV03 {
+    pub version: Version,
+    pub sources: StitchSources,
+    pub basic: StitchedSet,
+    pub service_operation: Vec<(ServiceOperString, StitchedSet)>,
+    pub call_chain: Vec<(ServiceOperString, Vec<CallChainData>)>,
+}
+
+impl From<LegacyStitchedV03> for Stitched {
+    fn from(ls: LegacyStitchedV03) -> Self {
+        Stitched::new(ls.sources, ls.basic, ls.service_operation, ls.call_chain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::super::Stitched, LegacyStitchedV03};
+    use crate::view_api::Version;
+    use std::{fs, io};
+
+    #[test]
+    fn read_v03_bincode() {
+        let ls = LegacyStitchedV03 {
+            version: Version::new(0, 3),
+            call_chain: vec![("svc/oper".to_string(), Vec::new())],
+            ..Default::default()
+        };
+        let path =
+            std::env::temp_dir().join(format!("{}_stitched_v03.bincode", std::process::id()));
+        let writer = io::BufWriter::new(fs::File::create(&path).unwrap());
+        bincode::serialize_into(writer, &ls).unwrap();
+
+        let stitched = Stitched::from_file(path.to_str().unwrap()).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(stitched.call_chain.len(), 1);
+        assert_eq!(stitched.call_chain[0].0, "svc/oper");
+        assert!(stitched.db_query.is_empty());
+        assert!(stitched.cache.is_empty());
+    }
+}
@@ -6,12 +6,15 @@
 
 mod anomalies;
 mod api;
+mod cache_reporter;
 mod call_chain_data;
 mod call_chain_reporter;
 mod dataseries;
+mod db_query_reporter;
 mod key;
 mod legacy;
 mod proc_oper_stats_reporter;
+mod slo_reporter;
 mod stats_rec_reporter;
 mod stitch_list;
 mod stitch_tables;
@@ -19,9 +22,9 @@ mod stitched;
 mod stitched_line;
 mod stitched_set;
 
-pub use anomalies::AnomalyParameters;
+pub use anomalies::{AnomalyParameters, DEFAULT_ANOMALY_PARS};
 pub use api::StitchedDataSet;
-pub use stitch_list::StitchList;
+pub use stitch_list::{StitchList, StitchSourceItem, StitchSources};
 pub use stitched::{StitchParameters, Stitched};
 pub use stitched_line::{BestFit, StitchedLine};
 pub use stitched_set::StitchedSet;
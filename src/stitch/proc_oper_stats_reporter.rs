@@ -8,7 +8,7 @@ use super::{key::Key, stitched_line::StitchedLine};
 
 /// The POData is the input for the processor (which is a series of report-closures.
 /// If the processor operated on a tuple we could extract a joined type from the next two types.
-type ProcessorInput<'a> = (&'a ProcOperStatsValue, i32, usize);
+type ProcessorInput<'a> = (&'a ProcOperStatsValue, &'a StatsRec, usize);
 type Processor = fn(&ProcessorInput) -> Option<f64>;
 type POData<'a> = Vec<Option<ProcessorInput<'a>>>;
 
@@ -60,7 +60,7 @@ impl POReportItems {
     pub fn extract_dataset<'a>(data: &'a [Option<StatsRec>], po_key: &'a Key) -> POData<'a> {
         // a ref to the extract the three values that are needed for the analysis being:
         //    1. the complete MethodStatsValue record
-        //    2. the number of files in the analysis
+        //    2. the StatsRec containing the value (the file-windows and number of files are needed for the rates)
         //    3. the number of traces included in this analysis
         data.iter()
             .map(|stats_rec| {
@@ -69,7 +69,7 @@ impl POReportItems {
                         st.operation
                             .0
                             .get(&po_key.operation) // can return None!
-                            .map(|oper| (oper, stats_rec.num_files, stats_rec.trace_id.len()))
+                            .map(|oper| (oper, stats_rec, stats_rec.trace_id.len()))
                     })
                 })
             })
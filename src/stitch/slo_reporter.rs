@@ -0,0 +1,52 @@
+use super::{key::Key, stitched_line::StitchedLine};
+use crate::{
+    stats::{find_proc_oper, SloConfig, SloObjective, SloStatus, StatsRec},
+    AnomalyParameters, Metric,
+};
+
+type Processor = fn(&SloStatus) -> Option<f64>;
+type SloData = Vec<Option<SloStatus>>;
+
+/// SLO report items, which are computed for the Process/Operations that have an objective in the SLO-configuration
+pub struct SloReportItem {
+    pub metric: Metric,
+    processor: Processor,
+}
+
+/// this container of ReportItems is used to bundle the methods that run over the SLO-status of a series of StatsRecs.
+pub struct SloReportItems(pub Vec<SloReportItem>);
+
+impl SloReportItem {
+    pub fn new(metric: Metric, processor: Processor) -> Self {
+        Self { metric, processor }
+    }
+
+    pub fn extract_stitched_line(&self, data: &SloData, pars: &AnomalyParameters) -> StitchedLine {
+        let values = data
+            .iter()
+            .map(|ms| ms.as_ref().and_then(self.processor))
+            .collect::<Vec<_>>();
+
+        StitchedLine::compute_new(self.metric, values, pars)
+    }
+}
+
+impl SloReportItems {
+    /// compute the SLO-status of the Process/Operation 'po_key' for each of the StatsRecs (each column of the stitched data)
+    pub fn extract_dataset(
+        data: &[Option<StatsRec>],
+        po_key: &Key,
+        config: &SloConfig,
+        objective: &SloObjective,
+    ) -> SloData {
+        let key = po_key.to_string();
+        data.iter()
+            .map(|stats_rec| {
+                stats_rec.as_ref().and_then(|stats_rec| {
+                    find_proc_oper(stats_rec, &key)
+                        .map(|pov| config.evaluate(objective, pov, &stats_rec.file_windows))
+                })
+            })
+            .collect()
+    }
+}
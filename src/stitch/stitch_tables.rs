@@ -1,12 +1,12 @@
 use super::{
+    cache_reporter::{CacheReportItem, CacheReportItems},
     call_chain_reporter::{CCReportItem, CCReportItems},
+    db_query_reporter::{DQReportItem, DQReportItems},
     proc_oper_stats_reporter::{POReportItem, POReportItems},
+    slo_reporter::{SloReportItem, SloReportItems},
     stats_rec_reporter::SRReportItem,
 };
-use crate::{
-    utils::{self, TimeStats},
-    Metric,
-};
+use crate::{utils::TimeStats, Metric};
 use lazy_static::lazy_static;
 
 lazy_static! {
@@ -14,9 +14,8 @@ lazy_static! {
     pub static ref BASIC_REPORT_ITEMS: Vec<SRReportItem> = vec![
         SRReportItem::new(Metric::NumFiles, |stats_rec| Some(stats_rec.num_files as f64)),
         SRReportItem::new(Metric::Rate, |stats_rec| {
-            let dt: Vec<_> = stats_rec.start_dt.iter().map(|dt| utils::datetime_to_micros(*dt)).collect();
-            TimeStats(&dt)
-                .get_avg_rate(stats_rec.num_files)
+            let dt: Vec<_> = stats_rec.start_dt.iter().map(|dt| dt.and_utc().timestamp_micros()).collect();
+            stats_rec.file_windows.get_rate(&dt, stats_rec.num_files)
         }),
         SRReportItem::new(Metric::NumTraces, |stats_rec| Some(
             stats_rec.trace_id.len() as f64
@@ -51,8 +50,8 @@ lazy_static! {
         POReportItem::new(Metric::OccurancePercentage, |&(pov, _, num_traces)| Some(
             pov.count as f64 / num_traces as f64
         )),
-        POReportItem::new(Metric::Rate, |&(pov, num_files, _)| pov
-            .get_avg_rate(num_files)),
+        POReportItem::new(Metric::Rate, |&(pov, stats_rec, _)| pov
+            .get_avg_rate(&stats_rec.file_windows, stats_rec.num_files)),
         POReportItem::new(Metric::MinDurationMillis, |&(pov, _, _)| Some(pov.get_min_millis())),
         POReportItem::new(Metric::AvgDurationMillis, |&(pov, _, _)| Some(pov.get_avg_millis())),
         POReportItem::new(Metric::MedianDurationMillis, |&(pov, _, _)| pov.get_median_millis()),
@@ -77,8 +76,8 @@ lazy_static! {
         CCReportItem::new(Metric::OccurancePercentage, |&(ccv, _, num_traces)| Some(
             ccv.count as f64 / num_traces as f64
         )),
-        CCReportItem::new(Metric::Rate, |&(ccv, num_files, _)| ccv
-            .get_avg_rate(num_files)),
+        CCReportItem::new(Metric::Rate, |&(ccv, stats_rec, _)| ccv
+            .get_avg_rate(&stats_rec.file_windows, stats_rec.num_files)),
         CCReportItem::new(Metric::MaxDurationMillis, |&(ccv, _, _)| Some(ccv.get_min_millis())),
         CCReportItem::new(Metric::AvgDurationMillis, |&(ccv, _, _)| Some(ccv.get_avg_millis())),
         CCReportItem::new(Metric::MedianDurationMillis, |&(ccv, _, _)| ccv.get_median_millis()),
@@ -95,3 +94,41 @@ lazy_static! {
         )),
     ]);
 }
+
+lazy_static! {
+    pub static ref DB_QUERY_REPORT_ITEMS: DQReportItems = DQReportItems(vec![
+        // The downstream analysis assumes that the first Report item is the Count measure!!
+        DQReportItem::new(Metric::Count, |&(dqv, _, _)| Some(dqv.count as f64)),
+        DQReportItem::new(Metric::OccurancePercentage, |&(dqv, _, num_traces)| Some(
+            dqv.num_traces() as f64 / num_traces as f64
+        )),
+        DQReportItem::new(Metric::Rate, |&(dqv, stats_rec, _)| dqv
+            .get_avg_rate(&stats_rec.file_windows, stats_rec.num_files)),
+        DQReportItem::new(Metric::MinDurationMillis, |&(dqv, _, _)| Some(dqv.get_min_millis())),
+        DQReportItem::new(Metric::AvgDurationMillis, |&(dqv, _, _)| Some(dqv.get_avg_millis())),
+        DQReportItem::new(Metric::MedianDurationMillis, |&(dqv, _, _)| dqv.get_median_millis()),
+        DQReportItem::new(Metric::MaxDurationMillis, |&(dqv, _, _)| Some(dqv.get_max_millis())),
+        DQReportItem::new(Metric::P95Millis, |&(dqv, _, _)| dqv.get_p_millis(0.95)),
+        DQReportItem::new(Metric::P99Millis, |&(dqv, _, _)| dqv.get_p_millis(0.99)),
+    ]);
+}
+
+lazy_static! {
+    /// Only computed for the Process/Operations that have an objective in the SLO-configuration (see set_slo_config)
+    pub static ref SLO_REPORT_ITEMS: SloReportItems = SloReportItems(vec![
+        SloReportItem::new(Metric::SloCompliance, |st| st.compliance),
+        SloReportItem::new(Metric::Apdex, |st| st.apdex),
+        SloReportItem::new(Metric::BurnRate, |st| st.burn_rate),
+        SloReportItem::new(Metric::ErrorBudgetConsumed, |st| st.budget_consumed),
+    ]);
+}
+
+lazy_static! {
+    pub static ref CACHE_REPORT_ITEMS: CacheReportItems = CacheReportItems(vec![
+        // The downstream analysis assumes that the first Report item is the Count measure!!
+        CacheReportItem::new(Metric::Count, |csv| Some(csv.count() as f64)),
+        CacheReportItem::new(Metric::CacheHitRatio, |csv| csv.hit_ratio()),
+        CacheReportItem::new(Metric::DownstreamCalls, |csv| Some(csv.downstream_calls as f64)),
+        CacheReportItem::new(Metric::FlushedDownstreamCalls, |csv| csv.flushed_downstream_calls()),
+    ]);
+}
@@ -4,7 +4,8 @@ use crate::{
     string_hash,
     Metric,
     MermaidScope,
-    mermaid,
+    mermaid::{self, BlastRadius, ServicePaths},
+    stats::{get_slo_config, CacheStats, StatsRec},
     utils::{self, CsvFileBuffer},
     view_api::Version,
     ServiceOperString, StitchList,
@@ -12,13 +13,19 @@ use crate::{
 
 use super::{
     anomalies::{Anomalies, AnomalyParameters},
+    cache_reporter::CacheReportItems,
     call_chain_data::CallChainData,
     call_chain_reporter::CCReportItems,
     dataseries::DataSeries,
-    legacy::LegacyStitched,
+    db_query_reporter::DQReportItems,
+    legacy::{LegacyStitched, LegacyStitchedV03},
     proc_oper_stats_reporter::POReportItems,
+    slo_reporter::SloReportItems,
     stitch_list::StitchSources,
-    stitch_tables::{BASIC_REPORT_ITEMS, CALL_CHAIN_REPORT_ITEMS, PROC_OPER_REPORT_ITEMS},
+    stitch_tables::{
+        BASIC_REPORT_ITEMS, CACHE_REPORT_ITEMS, CALL_CHAIN_REPORT_ITEMS, DB_QUERY_REPORT_ITEMS,
+        PROC_OPER_REPORT_ITEMS, SLO_REPORT_ITEMS,
+    },
     stitched_set::StitchedSet,
 };
 use serde::{Deserialize, Serialize};
@@ -32,6 +39,14 @@ pub struct StitchParameters {
 }
 
 type ServiceOperList = Vec<(ServiceOperString, StitchedSet)>;
+/// list of database queries keyed by 'db_instance/fingerprint'
+type DbQueryList = Vec<(String, StitchedSet)>;
+/// list of cache statistics keyed by the call-chain towards the caching process
+type CacheList = Vec<(String, StitchedSet)>;
+
+/// Version of the Stitched-layout written by this version of the tool.
+/// Bincode-files with a lower version are read via the LegacyStitchedV03 (or the older LegacyStitched).
+const STITCHED_VERSION: Version = Version { major: 0, minor: 4 };
 
 #[derive(Default, Serialize, Deserialize)]
 pub struct Stitched {
@@ -43,6 +58,12 @@ pub struct Stitched {
     ///  call-chain is keyed by the Service/Operation and the values is a series of call-chains that end in this process/Oper
     /// The values is a Vector call-chains represent all different paths (call-chains) that terminate in de Process/Oper of the key of this vector.
     pub call_chain: Vec<(ServiceOperString, Vec<CallChainData>)>,
+    /// Statistics per database query (default to support json-files written by older versions, bincode-files use a legacy layout)
+    #[serde(default)]
+    pub db_query: DbQueryList,
+    /// Cache hit-ratios per call-chain towards a caching process (default to support json-files written by older versions)
+    #[serde(default)]
+    pub cache: CacheList,
 }
 
 impl Stitched {
@@ -53,11 +74,13 @@ impl Stitched {
         call_chain: Vec<(ServiceOperString, Vec<CallChainData>)>,
     ) -> Self {
         Self {
-            version: Version::new(0, 3),
+            version: STITCHED_VERSION,
             sources,
             basic,
             service_operation,
             call_chain,
+            db_query: Vec::new(),
+            cache: Vec::new(),
         }
     }
 
@@ -66,12 +89,25 @@ impl Stitched {
     ///    1. `stitched_tables::BASIC_REPORT_ITEMS`: Some basic statistics for this dataset.
     ///    2. `stitched_tables::PROC_OPER_REPORT_ITEMS`:  A report on the level of Process/Operation.
     ///    3. `stitched_tables::CALL_CHAIN_REPORT_ITEMS`:  A detailed report where we compute separate statistics for each call-chain (call-path) that lead to a specific Process/Operation.
+    ///    4. `stitched_tables::DB_QUERY_REPORT_ITEMS`:  A report per database query (database instance and normalized statement).
+    ///    5. `stitched_tables::SLO_REPORT_ITEMS`:  The SLO-status (compliance, Apdex, burn-rate) added to all Process/Operations when SLOs are configured (empty values for a Process/Operation without an objective).
+    ///    6. `stitched_tables::CACHE_REPORT_ITEMS`:  The cache hit-ratio per call-chain towards a caching process.
     pub fn build(mut stitch_list: StitchList, pars: &StitchParameters) -> Self {
         let sources = mem::take(&mut stitch_list.lines);
 
         // this method reads the data in the original format, so data contains one column (StatsRec) per dataset
-        let mut data = stitch_list.read_data();
+        let data = stitch_list.read_data();
+
+        Self::build_from_data(sources, data, pars)
+    }
 
+    /// build a stitched dataset from data that is already in memory, for example the time-buckets of a single analysis.
+    /// The 'sources' should contain a numbered line for each column of 'data' (a None-column represents a gap in the time-line).
+    pub fn build_from_data(
+        sources: StitchSources,
+        mut data: Vec<Option<StatsRec>>,
+        pars: &StitchParameters,
+    ) -> Self {
         //TODO: check if drop works as expected.
         let num_dropped = DataSeries(&mut data).drop_low_volume_traces(pars.drop_count);
         println!(
@@ -87,16 +123,34 @@ impl Stitched {
                 .collect(),
         );
 
+        let slo_config = get_slo_config();
         let service_operation = POReportItems::get_keys(&data)
             .into_iter()
             .map(|po_key| {
                 let key_data = POReportItems::extract_dataset(&data, &po_key);
-                let stitched_set = PROC_OPER_REPORT_ITEMS
+                let mut stitched_set: Vec<_> = PROC_OPER_REPORT_ITEMS
                     .0
                     .iter()
                     .map(|por| por.extract_stitched_line(&key_data, &pars.anomaly_pars))
                     .collect();
-                (po_key.to_string(), StitchedSet(stitched_set))
+                let key = po_key.to_string();
+                // when SLOs are configured all Process/Operations get the SLO-lines (empty without an objective),
+                // such that all rows of the summary tables have the same columns.
+                if let Some(config) = slo_config.as_ref() {
+                    let slo_data = match config.objectives.iter().find(|obj| obj.key == key) {
+                        Some(objective) => {
+                            SloReportItems::extract_dataset(&data, &po_key, config, objective)
+                        }
+                        None => vec![None; data.len()],
+                    };
+                    stitched_set.extend(
+                        SLO_REPORT_ITEMS
+                            .0
+                            .iter()
+                            .map(|sr| sr.extract_stitched_line(&slo_data, &pars.anomaly_pars)),
+                    );
+                }
+                (key, StitchedSet(stitched_set))
             })
             .collect();
 
@@ -125,7 +179,41 @@ impl Stitched {
             })
             .collect();
 
-        Stitched::new(sources, basic, service_operation, call_chain)
+        let db_query = DQReportItems::get_keys(&data)
+            .into_iter()
+            .map(|dq_key| {
+                let key_data = DQReportItems::extract_dataset(&data, &dq_key);
+                let stitched_set = DB_QUERY_REPORT_ITEMS
+                    .0
+                    .iter()
+                    .map(|dqr| dqr.extract_stitched_line(&key_data, &pars.anomaly_pars))
+                    .collect();
+                (dq_key.to_key_str(), StitchedSet(stitched_set))
+            })
+            .collect();
+
+        let cache_data: Vec<_> = data
+            .iter()
+            .map(|stats_rec| stats_rec.as_ref().map(CacheStats::new))
+            .collect();
+        let cache = CacheReportItems::get_keys(&cache_data)
+            .into_iter()
+            .map(|key| {
+                let key_data = CacheReportItems::extract_dataset(&cache_data, &key);
+                let stitched_set = CACHE_REPORT_ITEMS
+                    .0
+                    .iter()
+                    .map(|cr| cr.extract_stitched_line(&key_data, &pars.anomaly_pars))
+                    .collect();
+                (key, StitchedSet(stitched_set))
+            })
+            .collect();
+
+        Stitched {
+            db_query,
+            cache,
+            ..Stitched::new(sources, basic, service_operation, call_chain)
+        }
     }
 
     // read from file (json or bincode)
@@ -151,7 +239,7 @@ impl Stitched {
                 }
             }
             "bincode" => {
-                match bincode::deserialize_from(reader) {
+                match Self::from_bincode(reader) {
                     Ok(stitched) => stitched,
                     Err(err) => {
                         println!("Original reader failed with error: {err:?}\n");
@@ -169,6 +257,20 @@ impl Stitched {
         Ok(stitched)
     }
 
+    /// Read a bincode-file. The version is the first field of the current and the 0.3 layout, so it is decoded first to select the layout.
+    fn from_bincode(mut reader: impl io::Read) -> Result<Self, Box<dyn Error>> {
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer)?;
+        let version: Version = bincode::deserialize(&buffer)?;
+        if version >= STITCHED_VERSION {
+            Ok(bincode::deserialize(&buffer)?)
+        } else {
+            println!("WARN: Fallback to the layout of version 0.3 to load data!!");
+            let ls: LegacyStitchedV03 = bincode::deserialize(&buffer)?;
+            Ok(ls.into())
+        }
+    }
+
     /// write the 'stitched' dataset to json
     pub fn to_json(&self, file_name: &str) {
 
@@ -249,7 +351,7 @@ impl Stitched {
         let mut csv = CsvFileBuffer::new();
 
         csv.add_empty_lines(2);
-        csv.add_toc(10);
+        csv.add_toc(11);
 
         csv.add_section("List of stitched data-files (numbered) and comments (unnumbered):");
         csv.append(&mut self.sources.csv_output());
@@ -359,6 +461,18 @@ impl Stitched {
             });
         });
 
+        csv.add_section("Statistics per database query (Db_instance/Fingerprint of the normalized statement):");
+        csv.add_line(self.full_data_header(&["Db_instance/Fingerprint"]));
+        self.db_query
+            .iter()
+            .for_each(|(label, stitched_set)| csv.append(&mut stitched_set.csv_output(&[label])));
+
+        csv.add_section("Cache hit-ratio per call-chain towards a caching process:");
+        csv.add_line(self.full_data_header(&["Call_chain"]));
+        self.cache
+            .iter()
+            .for_each(|(label, stitched_set)| csv.append(&mut stitched_set.csv_output(&[label])));
+
         csv.write_file(path);
     }
 
@@ -374,6 +488,7 @@ impl Stitched {
             .0
             .iter()
             .map(|por| por.metric)
+            .chain(SLO_REPORT_ITEMS.0.iter().map(|sr| sr.metric))
             .collect();
         metrics.iter().for_each(|metric| {
             csv.add_section(&format!("{} (Proces/Operation-level)", metric.to_str()));
@@ -448,7 +563,12 @@ impl Stitched {
         let mut num_anomalies = 0;
 
         csv.add_empty_lines(2);
-        csv.add_toc(PROC_OPER_REPORT_ITEMS.0.len() + CALL_CHAIN_REPORT_ITEMS.0.len() + 2);
+        csv.add_toc(
+            PROC_OPER_REPORT_ITEMS.0.len()
+                + SLO_REPORT_ITEMS.0.len()
+                + CALL_CHAIN_REPORT_ITEMS.0.len()
+                + 2,
+        );
 
         num_anomalies += self.add_process_operation_anomalies(&mut csv, pars);
         num_anomalies += self.add_call_chain_anomalies(&mut csv, pars);
@@ -473,20 +593,46 @@ impl Stitched {
         scope: MermaidScope,
         compact: bool,
     ) -> String {
+        self.trace_paths().get_diagram(
+            service_oper,
+            call_chain_key,
+            edge_value,
+            scope,
+            compact,
+        )
+    }
+
+    /// get a mermaid sequence diagram of the 'max_chains' most common call-chains of the endpoint 'service_oper'.
+    pub fn get_sequence_diagram(&self, service_oper: &str, max_chains: usize) -> String {
+        self.trace_paths()
+            .get_sequence_diagram(service_oper, max_chains)
+    }
+
+    /// get all endpoints whose call-chains pass through 'service_oper', weighted by their share of the traffic.
+    pub fn get_blast_radius(&self, service_oper: &str) -> BlastRadius {
+        self.trace_paths().blast_radius(service_oper)
+    }
+
+    /// get all distinct paths from 'from' to 'to' with their counts and latencies.
+    pub fn get_paths_between(&self, from: &str, to: &str) -> ServicePaths {
+        self.trace_paths().paths_between(from, to)
+    }
+
+    /// The call-chains of the stitched dataset with the averages of their statistics, which is the basis for the Mermaid diagrams
+    fn trace_paths(&self) -> mermaid::TracePaths {
         // bundle all data that corresponds to the same Service (currently grouped by Service-operation)
         let mut grouped_cc: HashMap<&str, Vec<&Vec<_>>> = HashMap::new();
         self
             .call_chain
             .iter()
             .for_each(|(service_oper, v)| {
-                let (service, oper_opt) = mermaid::split_service_operation(service_oper);
+                let (service, _oper_opt) = mermaid::split_service_operation(service_oper);
                 grouped_cc
                     .entry(service)
-                    .and_modify(|values| values.push(&v))
+                    .and_modify(|values| values.push(v))
                     .or_insert([v].to_vec());
             });
 
-
         let trace_tree = grouped_cc
             .into_iter()
             .map(|(service, ccd_vv)| {
@@ -497,48 +643,43 @@ impl Stitched {
                         ccd_v
                             .iter()
                             .map(|ccd| {
+                                let metric_avg = |metric: Metric| {
+                                    ccd.data
+                                        .0
+                                        .iter()
+                                        .find(|x| x.metric == metric)
+                                        .and_then(|data| data.data_avg)
+                                };
                                 let count: u64 = ccd
-                                .data
-                                .0
-                                .first()
-                                .and_then(|data| data.data_avg)
-                                .unwrap()
-                                .round() as u64;
-                            let avg_duration_millis = ccd
-                                .data
-                                .0
-                                .iter()
-                                .find(|x| x.metric == Metric::AvgDurationMillis)
-                                .and_then(|data| data.data_avg)
-                                .expect("avg-duration missing");
-                            mermaid::TraceData::new(
-                                &ccd.full_key,
-                                ccd.rooted,
-                                ccd.is_leaf,
-                                count,
-                                //TODO: some more parameters need to be passed.
-                                None,
-                                avg_duration_millis,
-                                None,
-                                None,
-                                None,
-                                None,
-                            )    
-                        })
+                                    .data
+                                    .0
+                                    .first()
+                                    .and_then(|data| data.data_avg)
+                                    .unwrap()
+                                    .round() as u64;
+                                let avg_duration_millis = metric_avg(Metric::AvgDurationMillis)
+                                    .expect("avg-duration missing");
+                                mermaid::TraceData::new(
+                                    &ccd.full_key,
+                                    ccd.rooted,
+                                    ccd.is_leaf,
+                                    count,
+                                    metric_avg(Metric::Rate),
+                                    avg_duration_millis,
+                                    metric_avg(Metric::P75Millis),
+                                    metric_avg(Metric::P90Millis),
+                                    metric_avg(Metric::P95Millis),
+                                    metric_avg(Metric::P99Millis),
+                                )
+                            })
                     })
                     .collect();
                 (service.to_string(), trace_data)
             })
             .collect();
-        mermaid::TracePaths(trace_tree).get_diagram(
-            service_oper,
-            call_chain_key,
-            edge_value,
-            scope,
-            compact,
-        )
+        mermaid::TracePaths(trace_tree)
     }
-    
+
     // /// Take the call_chain data out of the record and return as a hashmap
     // pub fn call_chain_as_hashmap(&mut self) -> HashMap<String, StitchedSet> {
     //     mem::take(&mut self.call_chain).into_iter().collect()
@@ -1,15 +1,20 @@
-use super::utils;
+use super::{super::time_series::time_series_path, utils};
 use crate::{
-    mermaid,
+    graph::GraphExport,
+    mermaid::{self, BlastRadius, ServicePaths},
     stats::StatsRec,
-    utils::{write_string_to_file, TimeStats},
-    view_api::types::ProcessList,
-    MermaidScope, Metric, TraceScope, ViewError, Viewer,
+    utils::write_string_to_file,
+    view_api::types::{ChartDataParameters, ProcessList, Selection, Table},
+    MermaidScope, Metric, StitchedDataSet, TraceScope, ViewError, Viewer,
 };
 use log::{error, info};
 use std::path::Path;
 
-pub struct TraceDataSet(pub StatsRec);
+pub struct TraceDataSet {
+    pub stats_rec: StatsRec,
+    /// The time-series over the time-buckets of this dataset (only present when trace_analysis was run with a bucket-interval)
+    time_series: Option<StitchedDataSet>,
+}
 
 //TODO How are we going to pass this parameter?
 
@@ -17,7 +22,25 @@ static NUM_FILES: i32 = 1;
 
 impl TraceDataSet {
     pub fn new(data: StatsRec) -> Self {
-        Self(data)
+        Self {
+            stats_rec: data,
+            time_series: None,
+        }
+    }
+
+    /// Try to load the time-series that has been written alongside the statistics-file
+    fn load_time_series(file_name: &str) -> Option<StitchedDataSet> {
+        ["json", "bincode"]
+            .iter()
+            .map(|ext| time_series_path(Path::new(file_name), ext))
+            .find(|path| path.exists())
+            .and_then(|path| {
+                info!("Trying to load the time-series {}", path.display());
+                StitchedDataSet::from_file(path.to_str()?)
+                    .map_err(|err| error!("Failed to load time-series: {err:?}"))
+                    .ok()
+            })
+            .map(|sds| *sds)
     }
 
     /// get a diagram and write it to a folder
@@ -34,6 +57,62 @@ impl TraceDataSet {
             self.get_mermaid_diagram(service_oper, call_chain_key, edge_value, scope, compact);
         write_diagram(folder, service_oper, diagram);
     }
+
+    /// Build the graph of the 'service_oper' (the same graph as the Mermaid diagram) for export to DOT, GraphML or JSON.
+    pub fn get_graph_export(
+        &self,
+        service_oper: &str,
+        call_chain_key: Option<&str>,
+    ) -> GraphExport {
+        self.trace_paths()
+            .get_graph_export(service_oper, call_chain_key)
+    }
+
+    /// The call-chains of the dataset with their statistics, which is the basis for the Mermaid diagrams
+    fn trace_paths(&self) -> mermaid::TracePaths {
+        let trace_tree = self
+            .stats_rec
+            .stats
+            .iter()
+            // TODO: Next line should be Service instead of Service_oper as the operations is an array of operations, so each operation is split out.
+            //   this is consistent with how the data is used in TraceData.build_trace_forrest()
+            //   rename 'service_oper' to 'service'
+            .map(|(service, oper_stats)| {
+                let trace_data = oper_stats
+                    .call_chain
+                    .0
+                    .iter()
+                    .map(|(cck, ccv)| {
+                        let key = cck.call_chain_key();
+                        let count = ccv.count as u64;
+                        let avg_duration_millis = ccv.get_avg_millis();
+                        let rate = ccv.get_avg_rate(&self.stats_rec.file_windows, NUM_FILES);
+                        let p75_millis = ccv.get_p_millis(0.75);
+                        let p90_millis = ccv.get_p_millis(0.90);
+                        let p95_millis = ccv.get_p_millis(0.95);
+                        let p99_millis = ccv.get_p_millis(0.99);
+
+                        // TODO: Made switch to aggregator at the wrong site. this is still a tree. Move it to get_diagram
+                        mermaid::TraceData::new(
+                            &key,
+                            ccv.rooted,
+                            cck.is_leaf,
+                            count,
+                            rate,
+                            avg_duration_millis,
+                            p75_millis,
+                            p90_millis,
+                            p95_millis,
+                            p99_millis,
+                        )
+                    })
+                    .collect();
+                (service.clone(), trace_data)
+            })
+            .collect();
+
+        mermaid::TracePaths(trace_tree)
+    }
 }
 
 impl Viewer for TraceDataSet {
@@ -43,7 +122,10 @@ impl Viewer for TraceDataSet {
 
             let file_path = Path::new(file_name).to_path_buf().into_os_string(); //get_full_path(base_path, input);
             match StatsRec::read_file(&file_path) {
-                Ok(stats_rec) => Ok(Box::new(TraceDataSet::new(stats_rec))),
+                Ok(stats_rec) => Ok(Box::new(TraceDataSet {
+                    time_series: Self::load_time_series(file_name),
+                    ..TraceDataSet::new(stats_rec)
+                })),
                 Err(err) => Err(ViewError::load_failure(
                     file_name.to_owned(),
                     format!("{err:?}"),
@@ -56,9 +138,19 @@ impl Viewer for TraceDataSet {
         }
     }
 
+    /// A TraceDataSet contains a time-series when it has been computed with a bucket-interval.
+    fn is_time_series(&self) -> bool {
+        self.time_series.is_some()
+    }
+
     /// Get the list of processes that exist in the current dataset.
     fn get_process_list(&self, metric: Metric) -> ProcessList {
-        utils::get_process_list(&self.0, metric)
+        utils::get_process_list(&self.stats_rec, metric)
+    }
+
+    /// Get the breakdown of a Service-Operation per instance and per dimension-value.
+    fn get_breakdown_list(&self, service_oper: &str, metric: Metric) -> ProcessList {
+        utils::get_breakdown_list(&self.stats_rec, service_oper, metric)
     }
 
     /// Get the list of call-chains for a given Service-Operation
@@ -69,7 +161,51 @@ impl Viewer for TraceDataSet {
         scope: TraceScope,
         inbound_idx: Option<i64>,
     ) -> ProcessList {
-        utils::get_call_chain_list(&self.0, proc_oper, metric, scope, inbound_idx)
+        utils::get_call_chain_list(&self.stats_rec, proc_oper, metric, scope, inbound_idx)
+    }
+
+    /// Get the chart-data over the time-buckets for a specific service-operation
+    fn get_service_oper_chart_data(
+        &self,
+        full_service_oper_key: &str,
+        metric: Metric,
+    ) -> Option<ChartDataParameters> {
+        self.time_series
+            .as_ref()
+            .and_then(|ts| ts.get_service_oper_chart_data(full_service_oper_key, metric))
+    }
+
+    /// Get the chart-data over the time-buckets for a specific call-chain
+    fn get_call_chain_chart_data(
+        &self,
+        call_chain_key: &str,
+        metric: Metric,
+    ) -> Option<ChartDataParameters> {
+        self.time_series
+            .as_ref()
+            .and_then(|ts| ts.get_call_chain_chart_data(call_chain_key, metric))
+    }
+
+    fn get_file_stats(&self) -> Table {
+        match &self.time_series {
+            Some(ts) => ts.get_file_stats(),
+            None => panic!("Get_file_stats only exists for time-series data"),
+        }
+    }
+
+    fn get_selection(&self) -> &Selection {
+        match &self.time_series {
+            Some(ts) => ts.get_selection(),
+            None => panic!("Get_selection only exists for time-series data"),
+        }
+    }
+
+    /// The selection only applies to the time-series (charts), as the statistics of the dataset are not split by bucket.
+    fn set_selection(&mut self, selected: Vec<bool>) -> Result<(), ViewError> {
+        match &mut self.time_series {
+            Some(ts) => ts.set_selection(selected),
+            None => panic!("Set_selection only exists for time-series data"),
+        }
     }
 
     fn get_mermaid_diagram(
@@ -80,55 +216,21 @@ impl Viewer for TraceDataSet {
         scope: MermaidScope,
         compact: bool,
     ) -> String {
-        let trace_tree = self
-            .0
-            .stats
-            .iter()
-            // TODO: Next line should be Service instead of Service_oper as the operations is an array of operations, so each operation is split out.
-            //   this is consistent with how the data is used in TraceData.build_trace_forrest()
-            //   rename 'service_oper' to 'service'
-            .map(|(service, oper_stats)| {
-                let trace_data = oper_stats
-                    .call_chain
-                    .0
-                    .iter()
-                    .map(|(cck, ccv)| {
-                        let key = cck.call_chain_key();
-                        let count = ccv.count as u64;
-                        let avg_duration_millis = TimeStats(&ccv.duration_micros).get_avg_millis();
-                        let rate = TimeStats(&ccv.duration_micros).get_avg_rate(NUM_FILES);
-                        let p75_millis = TimeStats(&ccv.duration_micros).get_p_millis(0.75);
-                        let p90_millis = TimeStats(&ccv.duration_micros).get_p_millis(0.90);
-                        let p95_millis = TimeStats(&ccv.duration_micros).get_p_millis(0.95);
-                        let p99_millis = TimeStats(&ccv.duration_micros).get_p_millis(0.99);
+        self.trace_paths()
+            .get_diagram(service_oper, call_chain_key, edge_value, scope, compact)
+    }
 
-                        // TODO: Made switch to aggregator at the wrong site. this is still a tree. Move it to get_diagram
-                        mermaid::TraceData::new(
-                            &key,
-                            ccv.rooted,
-                            cck.is_leaf,
-                            count,
-                            rate,
-                            avg_duration_millis,
-                            p75_millis,
-                            p90_millis,
-                            p95_millis,
-                            p99_millis,
-                        )
-                    })
-                    .collect();
-                (service.clone(), trace_data)
-            })
-            .collect();
+    fn get_sequence_diagram(&self, service_oper: &str, max_chains: usize) -> String {
+        self.trace_paths()
+            .get_sequence_diagram(service_oper, max_chains)
+    }
+
+    fn get_blast_radius(&self, service_oper: &str) -> BlastRadius {
+        self.trace_paths().blast_radius(service_oper)
+    }
 
-        let diagram = mermaid::TracePaths(trace_tree).get_diagram(
-            service_oper,
-            call_chain_key,
-            edge_value,
-            scope,
-            compact,
-        );
-        diagram
+    fn get_paths_between(&self, from: &str, to: &str) -> ServicePaths {
+        self.trace_paths().paths_between(from, to)
     }
 }
 
@@ -62,6 +62,41 @@ pub fn get_process_list(data: &StatsRec, metric: Metric) -> ProcessList {
     reorder_and_renumber(proc_list, !metric.is_none())
 }
 
+/// Return the breakdown of the Service/Operation 'service_oper' per instance (key 'service/oper@instance') and per
+/// dimension-value (key 'service/oper@dimension=value'), ranked like the process-list.
+/// These keys are kept out of the process-list, as the other lookups of the Viewer only resolve plain Service/Operations.
+pub fn get_breakdown_list(data: &StatsRec, service_oper: &str, metric: Metric) -> ProcessList {
+    let item = |key: String, count: usize, chain_type: &str| ProcessListItem {
+        idx: 0, // will be renumbered
+        key: key.clone(),
+        display: key,
+        rank: 0.0,
+        avg_count: count as i64,
+        chain_type: chain_type.to_owned(),
+        inbound_idx: 0,
+    };
+    let instances = data
+        .instance_stats
+        .0
+        .iter()
+        .filter(|(key, _)| key.service_oper == service_oper)
+        .map(|(key, pov)| item(key.to_key_str(), pov.count, "instance"));
+    let dimensions = service_oper
+        .split_once('/')
+        .and_then(|(service, oper)| data.stats.get(service).map(|os| (os, oper)))
+        .into_iter()
+        .flat_map(|(os, oper)| {
+            os.dimensions.0.iter().filter_map(move |(dim_key, dsv)| {
+                dsv.operation.0.get(oper).map(|pov| {
+                    let key = format!("{service_oper}@{}", dim_key.to_key_str());
+                    item(key, pov.count, "dimension")
+                })
+            })
+        });
+    let breakdown_list = instances.chain(dimensions).collect();
+    reorder_and_renumber(breakdown_list, !metric.is_none())
+}
+
 // /// get an ordered list of call-chains ranked based on 'metric' that are inbound on a point.
 // fn get_call_chain_list_inbound(data: &StatsRec, service_oper: &str, metric: &str) -> ProcessList {
 //     let proc_list = match data
@@ -190,3 +225,81 @@ pub fn get_call_chain_list(
     // }
     unimplemented!()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{get_breakdown_list, get_process_list};
+    use crate::{
+        processed::test_traces::{build_trace, TestSpan},
+        stats::{
+            BasicStatsRec, DimensionKey, DimensionStatsValue, InstanceKey, ProcOperStats,
+            ProcOperStatsValue, StatsRec,
+        },
+        Metric,
+    };
+    use std::collections::HashMap;
+
+    fn pov(count: usize) -> ProcOperStatsValue {
+        ProcOperStatsValue {
+            count,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn breakdown_is_not_in_process_list() {
+        let mut sr = StatsRec::new(BasicStatsRec::default());
+        let trace = build_trace(
+            "breakdown",
+            &[
+                TestSpan::new("r", None, "gw/GET", 0, 10_000),
+                TestSpan::new("c", Some("r"), "svc/oper", 1_000, 5_000),
+            ],
+        );
+        sr.extend_statistics(&trace, false);
+        ["pod-a", "pod-b"].iter().for_each(|instance| {
+            let key = InstanceKey {
+                service_oper: "svc/oper".to_owned(),
+                instance: instance.to_string(),
+            };
+            sr.instance_stats.0.insert(key, pov(3));
+        });
+        let dim_key = DimensionKey {
+            dimension: "tenant".to_owned(),
+            value: "acme".to_owned(),
+        };
+        let dsv = DimensionStatsValue {
+            operation: ProcOperStats(HashMap::from([("oper".to_owned(), pov(2))])),
+            ..Default::default()
+        };
+        sr.stats
+            .get_mut("svc")
+            .unwrap()
+            .dimensions
+            .0
+            .insert(dim_key, dsv);
+
+        // the process-list only contains keys that the other lookups of the Viewer can resolve
+        let mut keys: Vec<_> = get_process_list(&sr, Metric::None)
+            .into_iter()
+            .map(|item| item.key)
+            .collect();
+        keys.sort();
+        assert_eq!(keys, vec!["gw/GET", "svc/oper"]);
+
+        let mut breakdown: Vec<_> = get_breakdown_list(&sr, "svc/oper", Metric::None)
+            .into_iter()
+            .map(|item| (item.key, item.chain_type, item.avg_count))
+            .collect();
+        breakdown.sort();
+        assert_eq!(
+            breakdown,
+            vec![
+                ("svc/oper@pod-a".to_owned(), "instance".to_owned(), 3),
+                ("svc/oper@pod-b".to_owned(), "instance".to_owned(), 3),
+                ("svc/oper@tenant=acme".to_owned(), "dimension".to_owned(), 2),
+            ]
+        );
+        assert!(get_breakdown_list(&sr, "gw/GET", Metric::None).is_empty());
+    }
+}
@@ -0,0 +1,288 @@
+//! Canary (version) comparison within a single dataset.
+//! During a rollout the traces of the old (baseline) and the new (candidate) version of a service are mixed in one export.
+//! The spans of each service are split by a version-tag (via the dimension-split of the statistics) and per Process/Operation
+//! the latency-distribution (Mann-Whitney U) and the error-rate (two-proportion z-test) of the candidate are compared to the baseline.
+use super::dedup;
+use crate::{
+    processed, raw,
+    stats::{
+        get_split_dimensions, set_split_dimensions, DimensionKey, ProcOperStatsValue, StatsRec,
+    },
+    utils::{self, Chapter, CsvFileBuffer, MannWhitneyU},
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub struct CanaryParameters {
+    /// The tag (span-tag or process-tag) that contains the version, for example 'service.version'
+    pub version_tag: String,
+    /// The baseline version. When not provided the version that is observed first is used as baseline (per service).
+    pub baseline: Option<String>,
+    /// The candidate version. When not provided the version that is observed last is used as candidate (per service).
+    pub candidate: Option<String>,
+    /// significance level of the tests
+    pub alpha: f64,
+    /// maximal accepted ratio between the median latency of the candidate and the baseline
+    pub max_latency_ratio: f64,
+    /// maximal accepted (absolute) increase of the error-rate
+    pub max_error_rate_increase: f64,
+    /// minimal number of calls for both versions before a Process/Operation is compared
+    pub min_count: usize,
+}
+
+/// The comparison of the baseline and the candidate version of a single Process/Operation
+#[derive(Debug)]
+pub struct CanaryComparison {
+    pub service_oper: String,
+    pub baseline: String,
+    pub candidate: String,
+    pub baseline_count: usize,
+    pub candidate_count: usize,
+    pub baseline_median_millis: Option<f64>,
+    pub candidate_median_millis: Option<f64>,
+    pub latency_test: Option<MannWhitneyU>,
+    pub baseline_error_rate: f64,
+    pub candidate_error_rate: f64,
+    pub error_p_value: Option<f64>,
+    pub latency_regression: bool,
+    pub error_regression: bool,
+}
+
+/// number of spans that have an error (either a not http-ok code or an error-log)
+impl CanaryComparison {
+    fn new(
+        service_oper: String,
+        (baseline, base): (&str, &ProcOperStatsValue),
+        (candidate, cand): (&str, &ProcOperStatsValue),
+        pars: &CanaryParameters,
+    ) -> Self {
+        let to_millis = |pov: &ProcOperStatsValue| -> Vec<f64> {
+            pov.duration_micros
+                .iter()
+                .map(|d| *d as f64 / 1000.0)
+                .collect()
+        };
+        let latency_test = utils::mann_whitney_u(&to_millis(base), &to_millis(cand));
+        let baseline_median_millis = base.get_median_millis();
+        let candidate_median_millis = cand.get_median_millis();
+        // without raw durations (compact statistics) the gate is based on the ratio of the medians only
+        let significant = latency_test
+            .map(|mw| mw.z > 0.0 && mw.p_value < pars.alpha)
+            .unwrap_or(base.is_compact() || cand.is_compact());
+        let latency_regression = match (baseline_median_millis, candidate_median_millis) {
+            (Some(base_med), Some(cand_med)) => {
+                significant && cand_med > pars.max_latency_ratio * base_med
+            }
+            _ => false,
+        };
+
+        let baseline_error_rate = base.num_errors() as f64 / base.count as f64;
+        let candidate_error_rate = cand.num_errors() as f64 / cand.count as f64;
+        let error_p_value = utils::two_proportion_p_value(
+            base.count,
+            base.num_errors(),
+            cand.count,
+            cand.num_errors(),
+        );
+        let error_regression = candidate_error_rate - baseline_error_rate
+            > pars.max_error_rate_increase
+            && error_p_value.map(|p| p < pars.alpha).unwrap_or(false);
+
+        Self {
+            service_oper,
+            baseline: baseline.to_owned(),
+            candidate: candidate.to_owned(),
+            baseline_count: base.count,
+            candidate_count: cand.count,
+            baseline_median_millis,
+            candidate_median_millis,
+            latency_test,
+            baseline_error_rate,
+            candidate_error_rate,
+            error_p_value,
+            latency_regression,
+            error_regression,
+        }
+    }
+
+    pub fn is_regression(&self) -> bool {
+        self.latency_regression || self.error_regression
+    }
+
+    /// header for report_stats_line output in ';'-separated csv-format
+    pub fn report_stats_line_header_str() -> &'static str {
+        "Process/Oper; Baseline; Candidate; Baseline_count; Candidate_count; Baseline_median_millis; Candidate_median_millis; Prob_superiority; Latency_p_value; Baseline_error_rate; Candidate_error_rate; Error_p_value; Latency_regression; Error_regression"
+    }
+
+    /// reports the comparison for a single line in ';'-separated csv-format
+    pub fn report_stats_line(&self) -> String {
+        format!(
+            "{}; {}; {}; {}; {}; {}; {}; {}; {}; {}; {}; {}; {}; {}",
+            self.service_oper,
+            self.baseline,
+            self.candidate,
+            self.baseline_count,
+            self.candidate_count,
+            utils::format_float_opt(self.baseline_median_millis),
+            utils::format_float_opt(self.candidate_median_millis),
+            utils::format_float_opt(self.latency_test.map(|mw| mw.prob_superiority)),
+            utils::format_float_opt(self.latency_test.map(|mw| mw.p_value)),
+            utils::format_float(self.baseline_error_rate),
+            utils::format_float(self.candidate_error_rate),
+            utils::format_float_opt(self.error_p_value),
+            self.latency_regression,
+            self.error_regression
+        )
+    }
+}
+
+/// Determine the (baseline, candidate) versions for a service. When these are not provided via the parameters the
+/// version that is observed first is the baseline and the version observed last is the candidate.
+fn get_versions<'a>(
+    service: &str,
+    versions: &'a HashMap<DimensionKey, i64>,
+    pars: &'a CanaryParameters,
+) -> Option<(&'a str, &'a str)> {
+    match (&pars.baseline, &pars.candidate) {
+        (Some(base), Some(cand)) => Some((base, cand)),
+        _ => {
+            let mut observed: Vec<_> = versions
+                .iter()
+                .map(|(k, first_micros)| (*first_micros, &k.value[..]))
+                .collect();
+            observed.sort_unstable();
+            if observed.len() > 2 && (pars.baseline.is_none() || pars.candidate.is_none()) {
+                utils::report(
+                    Chapter::Issues,
+                    format!("Service '{service}' has {} versions. Provide the baseline and candidate version to compare.", observed.len()),
+                );
+                return None;
+            }
+            let base = pars.baseline.as_deref().or(observed.first().map(|o| o.1))?;
+            let cand = pars.candidate.as_deref().or(observed.last().map(|o| o.1))?;
+            if base == cand {
+                None
+            } else {
+                Some((base, cand))
+            }
+        }
+    }
+}
+
+/// Compare the versions of each service over the statistics (which should have been computed with the version-tag as split-dimension).
+pub fn compare_versions(stats_rec: &StatsRec, pars: &CanaryParameters) -> Vec<CanaryComparison> {
+    let mut comparisons: Vec<_> = stats_rec
+        .stats
+        .iter()
+        .flat_map(|(service, st)| {
+            // the time at which each version is observed first
+            let versions: HashMap<_, _> = st
+                .dimensions
+                .0
+                .iter()
+                .filter(|(k, _)| k.dimension == pars.version_tag)
+                .map(|(k, dsv)| {
+                    let first = dsv
+                        .operation
+                        .0
+                        .values()
+                        .filter_map(|pov| pov.get_first_start_micros())
+                        .min()
+                        .unwrap_or(i64::MAX);
+                    (k.clone(), first)
+                })
+                .collect();
+            let Some((baseline, candidate)) = get_versions(service, &versions, pars) else {
+                return Vec::new();
+            };
+            let get_ops = |version: &str| {
+                st.dimensions.0.get(&DimensionKey {
+                    dimension: pars.version_tag.to_owned(),
+                    value: version.to_owned(),
+                })
+            };
+            let (Some(base), Some(cand)) = (get_ops(baseline), get_ops(candidate)) else {
+                return Vec::new();
+            };
+            base.operation
+                .0
+                .iter()
+                .filter_map(|(oper, base_pov)| {
+                    cand.operation.0.get(oper).and_then(|cand_pov| {
+                        if base_pov.count < pars.min_count || cand_pov.count < pars.min_count {
+                            None
+                        } else {
+                            Some(CanaryComparison::new(
+                                format!("{service}/{oper}"),
+                                (baseline, base_pov),
+                                (candidate, cand_pov),
+                                pars,
+                            ))
+                        }
+                    })
+                })
+                .collect()
+        })
+        .collect();
+    comparisons.sort_by(|a, b| a.service_oper.cmp(&b.service_oper));
+    comparisons
+}
+
+/// Read the traces in 'path', compare the baseline and candidate versions and write the result to 'Stats/canary_comparison.csv'.
+/// Returns the folder of the traces and the comparisons.
+pub fn analyze_canary(path: &Path, pars: &CanaryParameters) -> (PathBuf, Vec<CanaryComparison>) {
+    let (traces, num_files, folder) =
+        raw::read_process_file_or_folder(path, processed::extract_traces);
+    let folder = utils::canonicalize_path(folder);
+    let traces = dedup::deduplicate(traces);
+
+    // split on the version-tag only, and restore the split-dimensions of the regular analysis afterwards
+    let split_dimensions = get_split_dimensions();
+    set_split_dimensions(vec![pars.version_tag.to_owned()]);
+    let mut stats_rec = StatsRec {
+        num_files,
+        ..Default::default()
+    };
+    traces
+        .iter()
+        .for_each(|trace| stats_rec.extend_statistics(trace, false));
+    set_split_dimensions(split_dimensions);
+
+    let comparisons = compare_versions(&stats_rec, pars);
+
+    let mut csv = CsvFileBuffer::new();
+    csv.add_section(&format!(
+        "Comparison of versions based on tag '{}' (alpha={}, max_latency_ratio={}, max_error_rate_increase={})",
+        pars.version_tag, pars.alpha, pars.max_latency_ratio, pars.max_error_rate_increase
+    ));
+    csv.add_line(CanaryComparison::report_stats_line_header_str().to_owned());
+    comparisons
+        .iter()
+        .for_each(|cmp| csv.add_line(cmp.report_stats_line()));
+    let mut csv_file = utils::extend_create_folder(&folder, "Stats");
+    csv_file.push("canary_comparison.csv");
+    csv.write_file(&csv_file);
+
+    comparisons
+        .iter()
+        .filter(|cmp| cmp.is_regression())
+        .for_each(|cmp| {
+            utils::report(
+                Chapter::Summary,
+                format!(
+                    "Regression of '{}' in version '{}' (baseline '{}'): median {} ms versus {} ms, error-rate {} versus {}",
+                    cmp.service_oper,
+                    cmp.candidate,
+                    cmp.baseline,
+                    utils::format_float_opt(cmp.candidate_median_millis),
+                    utils::format_float_opt(cmp.baseline_median_millis),
+                    utils::format_float(cmp.candidate_error_rate),
+                    utils::format_float(cmp.baseline_error_rate),
+                ),
+            )
+        });
+    (folder, comparisons)
+}
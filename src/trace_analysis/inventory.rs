@@ -0,0 +1,204 @@
+//! Instrumentation inventory, showing per service how it is instrumented (SDK, Jaeger-client, span-kinds and tag-keys) and which called services do not emit spans at all.
+//! This inventory is used to track the tracing-coverage, for example during a migration to OpenTelemetry.
+use crate::{
+    processed::{get_peer_name, is_inferred_service, Span},
+    stats::TraceExt,
+    utils::{self, Chapter, CsvFileBuffer},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    path::Path,
+};
+
+/// The number of distinct values of a tag-key that is tracked. Above this value the cardinality is reported as '>MAX_CARDINALITY'.
+const MAX_CARDINALITY: usize = 100;
+
+#[derive(Default)]
+struct ServiceInventory {
+    num_spans: usize,
+    sdk: BTreeSet<String>,
+    jaeger_versions: BTreeSet<String>,
+    span_kinds: BTreeMap<String, usize>,
+    /// per tag-key the distinct values (capped at MAX_CARDINALITY + 1)
+    tag_values: BTreeMap<String, HashSet<String>>,
+}
+
+impl ServiceInventory {
+    fn add_span(&mut self, span: &Span) {
+        self.num_spans += 1;
+        if let Some(proc) = &span.process {
+            if !proc.telemetry_sdk_name.is_empty() || !proc.telemetry_sdk_language.is_empty() {
+                self.sdk.insert(format!(
+                    "{} {} {}",
+                    proc.telemetry_sdk_language,
+                    proc.telemetry_sdk_name,
+                    proc.telemetry_sdk_version
+                ));
+            }
+            if !proc.jaeger_version.is_empty() {
+                self.jaeger_versions.insert(proc.jaeger_version.to_owned());
+            }
+            proc.other_tags
+                .iter()
+                .for_each(|(k, v)| self.add_tag_value(format!("process:{k}"), v));
+        }
+        let kind = span.span_kind.as_deref().unwrap_or("-").to_owned();
+        *self.span_kinds.entry(kind).or_default() += 1;
+        span.attributes
+            .iter()
+            .for_each(|(k, v)| self.add_tag_value(k.to_owned(), v));
+    }
+
+    fn add_tag_value(&mut self, key: String, value: &str) {
+        let values = self.tag_values.entry(key).or_default();
+        if values.len() <= MAX_CARDINALITY {
+            values.insert(value.to_owned());
+        }
+    }
+
+    fn emits_server_spans(&self) -> bool {
+        self.span_kinds.contains_key("server") || self.span_kinds.contains_key("consumer")
+    }
+}
+
+/// A service that is called by instrumented services, but never emits spans itself.
+#[derive(Default)]
+struct UninstrumentedService {
+    num_calls: usize,
+    callers: BTreeSet<String>,
+}
+
+#[derive(Default)]
+pub struct InstrumentationInventory {
+    services: BTreeMap<String, ServiceInventory>,
+    uninstrumented: BTreeMap<String, UninstrumentedService>,
+}
+
+impl InstrumentationInventory {
+    /// build the inventory over all spans of all traces
+    pub fn new(traces: &[TraceExt]) -> Self {
+        let mut inventory = Self::default();
+        traces.iter().for_each(|tr| {
+            tr.trace
+                .spans
+                .items
+                .iter()
+                .filter(|span| !is_inferred_service(span.get_process_str()))
+                .for_each(|span| {
+                    inventory
+                        .services
+                        .entry(span.get_process_str().to_owned())
+                        .or_default()
+                        .add_span(span)
+                })
+        });
+
+        // Leaf client-spans that refer to a peer that never emits spans point to an uninstrumented service.
+        // A client-span that only has inferred (virtual) children is considered a leaf too.
+        let known: HashSet<_> = inventory.services.keys().cloned().collect();
+        traces.iter().for_each(|tr| {
+            let spans = &tr.trace.spans;
+            let children = spans.get_children();
+            spans
+                .items
+                .iter()
+                .enumerate()
+                .filter(|(idx, span)| {
+                    children[*idx]
+                        .iter()
+                        .all(|ch| is_inferred_service(spans.items[*ch].get_process_str()))
+                        && matches!(span.span_kind.as_deref(), Some("client") | Some("producer"))
+                })
+                .map(|(_, span)| span)
+                .for_each(|span| {
+                    if let Some(peer) = get_peer_name(span) {
+                        if !known.contains(&peer) {
+                            let un = inventory.uninstrumented.entry(peer).or_default();
+                            un.num_calls += 1;
+                            un.callers.insert(span.get_process_str().to_owned());
+                        }
+                    }
+                })
+        });
+        inventory
+    }
+
+    /// write the inventory to a csv-file and report the uninstrumented services
+    pub fn write_csv(&self, path: &Path) {
+        let mut csv = CsvFileBuffer::new();
+        csv.add_toc(3);
+
+        csv.add_section("Instrumentation per service");
+        csv.add_line(
+            "Service; Num_spans; Sdk; Jaeger_version; Span_kinds; Emits_server_spans; Num_tag_keys"
+                .to_owned(),
+        );
+        self.services.iter().for_each(|(service, si)| {
+            let kinds = si
+                .span_kinds
+                .iter()
+                .map(|(k, cnt)| format!("{k}({cnt})"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            csv.add_line(format!(
+                "{service}; {}; {}; {}; {kinds}; {}; {}",
+                si.num_spans,
+                si.sdk.iter().cloned().collect::<Vec<_>>().join(", "),
+                si.jaeger_versions
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                si.emits_server_spans(),
+                si.tag_values.len()
+            ));
+        });
+
+        csv.add_section("Tag keys per service");
+        csv.add_line("Service; Tag_key; Cardinality".to_owned());
+        self.services.iter().for_each(|(service, si)| {
+            si.tag_values.iter().for_each(|(key, values)| {
+                let cardinality = if values.len() > MAX_CARDINALITY {
+                    format!(">{MAX_CARDINALITY}")
+                } else {
+                    values.len().to_string()
+                };
+                csv.add_line(format!("{service}; {key}; {cardinality}"));
+            })
+        });
+
+        csv.add_section("Uninstrumented services (called, but never emitting spans)");
+        csv.add_line("Peer; Num_calls; Callers".to_owned());
+        self.uninstrumented.iter().for_each(|(peer, un)| {
+            csv.add_line(format!(
+                "{peer}; {}; {}",
+                un.num_calls,
+                un.callers.iter().cloned().collect::<Vec<_>>().join(", ")
+            ));
+        });
+        csv.write_file(path);
+
+        let no_server: Vec<_> = self
+            .services
+            .iter()
+            .filter(|(_, si)| !si.emits_server_spans())
+            .map(|(s, _)| s.to_owned())
+            .collect();
+        if !no_server.is_empty() {
+            utils::report(
+                Chapter::Analysis,
+                format!("Services that do not emit server-spans: {no_server:?}"),
+            );
+        }
+        if !self.uninstrumented.is_empty() {
+            let peers: Vec<_> = self.uninstrumented.keys().collect();
+            utils::report(
+                Chapter::Summary,
+                format!(
+                    "Found {} uninstrumented services (called, but not emitting spans): {peers:?}",
+                    peers.len()
+                ),
+            );
+        }
+    }
+}
@@ -4,12 +4,17 @@ use crate::{
     utils, MermaidScope, Metric,
 };
 pub use api::TraceDataSet;
+pub use canary::{analyze_canary, CanaryComparison, CanaryParameters};
+pub use time_series::set_bucket_interval;
 
 use std::path::{Path, PathBuf};
 
 mod api;
+mod canary;
 mod dedup;
+mod inventory;
 mod stats;
+mod time_series;
 mod write;
 
 /// analyze_file_or_folder does the full analysis over a single Jaeger json-file, or a folder that contains a set of json files.
@@ -59,6 +64,10 @@ pub fn analyze_file_or_folder(
         traces.iter().for_each(|trace| trace.write_trace());
     }
 
+    let mut inventory_file = utils::extend_create_folder(&folder, "Stats");
+    inventory_file.push("instrumentation_inventory.csv");
+    inventory::InstrumentationInventory::new(&traces).write_csv(&inventory_file);
+
     let stats_rec = stats::process_and_fix_traces(folder.clone(), traces, bsr, cc_path, output_ext);
 
     // Assume some default parameters
@@ -66,7 +75,7 @@ pub fn analyze_file_or_folder(
     let scope = MermaidScope::Full;
     let edge_value = Metric::Count;
     if let Some(display_service_oper) = display_service_oper {
-        TraceDataSet(stats_rec).write_mermaid_diagram(
+        TraceDataSet::new(stats_rec).write_mermaid_diagram(
             &folder,
             display_service_oper,
             display_call_chain,
@@ -1,7 +1,11 @@
 //! Creating the statistics
+use super::time_series::{self, BucketedStats};
 use crate::{
+    graph::{build_graph, GraphAnalytics},
     stats::{
-        self, call_chain::CChainEndPointCache, file, BasicStatsRec, StatsRec, TraceExt, TraceExtVec,
+        self,
+        call_chain::{repair_gaps, CChainEndPointCache},
+        file, AmplificationMatrix, BasicStatsRec, StatsRec, TraceExt, TraceExtVec,
     },
     utils::{self, Chapter},
 };
@@ -114,7 +118,7 @@ fn write_end_point_stats_and_correct_incomplete(
             let cumm_stats = create_trace_statistics(&traces[..], &bsr, rooted_spans_only);
 
 
-            cchain_cache.create_update_entry(&k, cumm_stats.call_chain_keys());
+            cchain_cache.create_update_entry(&k, cumm_stats.call_chain_infos());
 
             cumm_stats
         } else {
@@ -164,7 +168,7 @@ fn write_end_point_stats_and_correct_incomplete(
 /// process a vector of traces
 pub fn process_and_fix_traces(
     folder: PathBuf,
-    traces: Vec<TraceExt>,
+    mut traces: Vec<TraceExt>,
     mut bsr: BasicStatsRec,
     cc_path: &str,
     output_ext: &str,
@@ -186,6 +190,24 @@ pub fn process_and_fix_traces(
     let num_files: i32 = TraceExtVec(&traces[..]).num_files().try_into().unwrap();
 
     let mut cchain_cache = CChainEndPointCache::new(get_cchain_folder(&folder, cc_path));
+    cchain_cache.set_source(
+        &folder
+            .canonicalize()
+            .unwrap_or(folder.clone())
+            .to_string_lossy(),
+    );
+
+    // join orphan sub-trees to their ancestor, and recompute the statistics such that these sub-trees are counted under their full call-chain
+    if repair_gaps(&mut traces, &mut cchain_cache) > 0 {
+        let init_num_unrooted_cc = cumm_stats.init_num_unrooted_cc;
+        cumm_stats = create_trace_statistics(&traces, &bsr, false);
+        cumm_stats.init_num_unrooted_cc = init_num_unrooted_cc;
+        cumm_stats.num_call_chains = cumm_stats.count_call_chains().0;
+    }
+
+    // the statistics per time-bucket are computed before the traces are moved
+    let bucketed = time_series::get_bucket_interval()
+        .map(|interval_secs| BucketedStats::new(&traces, &bsr, interval_secs));
 
     let (num_end_points, incomplete_traces_read) = write_end_point_stats_and_correct_incomplete(
         &stats_folder,
@@ -206,6 +228,7 @@ pub fn process_and_fix_traces(
     }
 
     let num_fixes = cumm_stats.fix_call_chain(&mut cchain_cache); // mutable needed for internal reasons of CChain_cache
+    cumm_stats.report_repairs();
 
     let (total_cc, num_unrooted) = cumm_stats.count_call_chains();
     if cumm_stats.num_call_chains != total_cc {
@@ -228,6 +251,96 @@ pub fn process_and_fix_traces(
     csv_file.push("cummulative_trace_stats.csv");
     write_cumulative_trace_stats(csv_file, cumm_stats.clone(), output_ext);
 
+    if let Some(mut bucketed) = bucketed {
+        bucketed.fix_call_chains(&mut cchain_cache);
+        let mut stats_file = stats_folder.clone();
+        stats_file.push("cummulative_trace_stats.csv");
+        time_series::write_time_series(bucketed, &stats_file, output_ext);
+    }
+
+    if let Some(slo_config) = stats::get_slo_config() {
+        slo_config
+            .evaluate_all(&cumm_stats)
+            .into_iter()
+            .filter(|(_, _, status)| status.burn_rate.is_some_and(|br| br > 1.0))
+            .for_each(|(obj, _, status)| {
+                utils::report(
+                    Chapter::Analysis,
+                    format!(
+                        "SLO of '{}' is violated: burn-rate {} (compliance {}, error-rate {})",
+                        obj.key,
+                        utils::format_float_opt(status.burn_rate),
+                        utils::format_float_opt(status.compliance),
+                        utils::format_float(status.error_rate)
+                    ),
+                )
+            });
+    }
+
+    let mut csv_file = stats_folder.clone();
+    csv_file.push("db_queries.csv");
+    cumm_stats.db_queries.write_csv(&csv_file);
+
+    let amplification = AmplificationMatrix::new(&cumm_stats);
+    let mut amplification_file = stats_folder.clone();
+    amplification_file.push("amplification_matrix.csv");
+    amplification.write_csv(&amplification_file);
+    if let Err(err) = amplification.write_json(&amplification_file.with_extension("json")) {
+        utils::report(
+            Chapter::Issues,
+            format!("Failed to write the amplification matrix as json: {err}"),
+        )
+    }
+
+    let graph_analytics = GraphAnalytics::new(&build_graph(&cumm_stats));
+    let mut graph_analytics_file = stats_folder.clone();
+    graph_analytics_file.push("service_graph_analytics.csv");
+    graph_analytics.write_csv(&graph_analytics_file);
+    if let Err(err) = graph_analytics.write_json(&graph_analytics_file.with_extension("json")) {
+        utils::report(
+            Chapter::Issues,
+            format!("Failed to write the service graph analytics as json: {err}"),
+        )
+    }
+    graph_analytics.report();
+
+    cumm_stats
+        .fan_out
+        .chatty_edges()
+        .into_iter()
+        .filter(|(_, fov)| fov.is_n_plus_1())
+        .for_each(|(k, fov)| {
+            utils::report(
+                Chapter::Analysis,
+                format!(
+                    "Possible N+1 pattern: '{}' calls '{}' on average {} times per invocation (max {})",
+                    k.parent,
+                    k.child,
+                    utils::CountStats(&fov.calls_per_invocation).get_mean_str(),
+                    utils::CountStats(&fov.calls_per_invocation).get_max()
+                ),
+            )
+        });
+
+    cumm_stats
+        .instance_stats
+        .get_deviations()
+        .into_iter()
+        .for_each(|dev| {
+            utils::report(
+                Chapter::Analysis,
+                format!(
+                    "Instance '{}' of '{}' deviates from its siblings: avg {} ms versus {} ms, error-rate {} versus {}",
+                    dev.key.instance,
+                    dev.key.service_oper,
+                    utils::format_float(dev.avg_millis),
+                    utils::format_float(dev.siblings_avg_millis),
+                    utils::format_float(dev.frac_errors),
+                    utils::format_float(dev.siblings_frac_errors),
+                ),
+            )
+        });
+
     println!();
     utils::report(Chapter::Summary, format!("Processed {total_traces} traces covering {} end-points  (on average {:.1} traces per end-point).",
         bsr.num_endpoints,
@@ -0,0 +1,233 @@
+//! Time-series within a single analysis. The traces are assigned to fixed time-buckets (for example 5 minutes) based on their start-time,
+//! which is the start_dt of the root span, and the statistics are computed per bucket.
+//! The buckets are stitched to a time-series (same format as the output of the stitch-tool), such that each Service/Operation and call-chain metric
+//! is available as a series over the day. The time-series is only computed when a bucket interval is set via set_bucket_interval.
+use crate::{
+    stats::{call_chain::CChainEndPointCache, BasicStatsRec, StatsRec, TraceExt},
+    stitch::{StitchParameters, StitchSourceItem, StitchSources, Stitched, DEFAULT_ANOMALY_PARS},
+    utils::{self, Chapter},
+};
+use chrono::{DateTime, NaiveDateTime};
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+static BUCKET_INTERVAL: Mutex<Option<i64>> = Mutex::new(None);
+
+/// Set the interval in seconds of the time-buckets (None disables the time-series)
+pub fn set_bucket_interval(secs: Option<i64>) {
+    let mut guard = BUCKET_INTERVAL.lock().unwrap();
+    *guard = secs
+}
+
+pub fn get_bucket_interval() -> Option<i64> {
+    *BUCKET_INTERVAL.lock().unwrap()
+}
+
+/// maximal number of buckets of a time-series, including the empty buckets
+const MAX_BUCKETS: i64 = 10_000;
+
+/// The statistics per time-bucket, including the empty buckets such that the time-line does not have gaps (up to MAX_BUCKETS buckets).
+pub struct BucketedStats {
+    pub interval_secs: i64,
+    /// start of each bucket and the statistics of the traces that start in this bucket (None for an empty bucket)
+    pub buckets: Vec<(NaiveDateTime, Option<StatsRec>)>,
+}
+
+impl BucketedStats {
+    /// Compute the statistics per time-bucket. The call-chains are corrected afterwards via 'fix_call_chains'.
+    pub fn new(traces: &[TraceExt], bsr: &BasicStatsRec, interval_secs: i64) -> Self {
+        let interval_micros = interval_secs * 1_000_000;
+        let bucket_idx = |tr: &TraceExt| {
+            tr.trace
+                .start_dt
+                .and_utc()
+                .timestamp_micros()
+                .div_euclid(interval_micros)
+        };
+        let mut bucket_traces: BTreeMap<i64, Vec<&TraceExt>> = BTreeMap::new();
+        traces
+            .iter()
+            .for_each(|tr| bucket_traces.entry(bucket_idx(tr)).or_default().push(tr));
+        let (Some(first), Some(last)) = (
+            bucket_traces.keys().next().copied(),
+            bucket_traces.keys().next_back().copied(),
+        ) else {
+            return Self {
+                interval_secs,
+                buckets: Vec::new(),
+            };
+        };
+        // fill the gaps with empty buckets, unless a few outlying traces would result in a huge number of empty buckets
+        let bucket_indices: Vec<_> = if last - first < MAX_BUCKETS {
+            (first..=last).collect()
+        } else {
+            utils::report(
+                Chapter::Issues,
+                format!(
+                    "The traces span {} buckets of {interval_secs} seconds, so the empty buckets are omitted from the time-series",
+                    last - first + 1
+                ),
+            );
+            bucket_traces.keys().copied().collect()
+        };
+
+        let buckets = bucket_indices
+            .into_iter()
+            .map(|idx| {
+                let start = DateTime::from_timestamp_micros(idx * interval_micros)
+                    .expect("Invalid bucket start")
+                    .naive_utc();
+                let stats_rec = bucket_traces.remove(&idx).map(|traces| {
+                    let num_files = traces
+                        .iter()
+                        .map(|tr| tr.trace.source_file_id)
+                        .collect::<HashSet<_>>()
+                        .len();
+                    let mut stats_rec = StatsRec::new(BasicStatsRec {
+                        num_files: num_files as i32,
+                        ..bsr.clone()
+                    });
+                    traces
+                        .iter()
+                        .for_each(|tr| stats_rec.extend_statistics(&tr.trace, false));
+                    stats_rec
+                });
+                (start, stats_rec)
+            })
+            .collect();
+        Self {
+            interval_secs,
+            buckets,
+        }
+    }
+
+    /// correct the call-chains of each bucket based on the call-chain cache (same correction as applied on the cumulative statistics)
+    pub fn fix_call_chains(&mut self, cchain_cache: &mut CChainEndPointCache) {
+        self.buckets
+            .iter_mut()
+            .filter_map(|(_, sr)| sr.as_mut())
+            .for_each(|sr| {
+                sr.num_fixes = sr.fix_call_chain(cchain_cache);
+                sr.num_endpoints = sr.root_call.iter().collect::<HashSet<_>>().len();
+                let (total_cc, num_unrooted) = sr.count_call_chains();
+                sr.num_call_chains = total_cc;
+                sr.num_unrooted_cc_after_fixes = num_unrooted;
+            })
+    }
+
+    /// stitch the buckets to a time-series. The description of each bucket is its start-time.
+    pub fn into_stitched(self) -> Stitched {
+        let sources = StitchSources(
+            self.buckets
+                .iter()
+                .enumerate()
+                .map(|(idx, (start, _))| {
+                    StitchSourceItem::new(
+                        Some(idx as u32),
+                        &format!("bucket {}", start.format("%Y-%m-%dT%H:%M")),
+                    )
+                })
+                .collect(),
+        );
+        let data = self.buckets.into_iter().map(|(_, sr)| sr).collect();
+        let pars = StitchParameters {
+            drop_count: 0,
+            anomaly_pars: DEFAULT_ANOMALY_PARS,
+        };
+        Stitched::build_from_data(sources, data, &pars)
+    }
+}
+
+/// The file containing the time-series that belongs to a statistics-file, for example 'cummulative_trace_stats_time_series.json'.
+/// The time-series is stored in the stitched format, which supports 'json' and 'bincode' only.
+pub fn time_series_path(stats_file: &Path, ext: &str) -> PathBuf {
+    let stem = stats_file
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let ext = if ext == "bincode" { "bincode" } else { "json" };
+    stats_file.with_file_name(format!("{stem}_time_series.{ext}"))
+}
+
+/// write the time-series in the stitched format and as csv
+pub fn write_time_series(bucketed: BucketedStats, stats_file: &Path, output_ext: &str) {
+    let num_buckets = bucketed.buckets.len();
+    let interval_secs = bucketed.interval_secs;
+    let stitched = bucketed.into_stitched();
+    let path = time_series_path(stats_file, output_ext);
+    stitched.write_csv(&path.with_extension("csv"));
+    stitched.to_json(path.to_str().expect("invalid file-path"));
+    utils::report(
+        Chapter::Summary,
+        format!(
+            "Time-series of {num_buckets} buckets of {interval_secs} seconds written to '{}'",
+            path.display()
+        ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BucketedStats;
+    use crate::{
+        processed::test_traces::{build_trace, TestSpan},
+        stats::{BasicStatsRec, TraceExt},
+    };
+    use std::path::Path;
+
+    const MINUTE: i64 = 60_000_000;
+
+    fn traces(offsets_minutes: &[i64]) -> Vec<TraceExt> {
+        offsets_minutes
+            .iter()
+            .enumerate()
+            .map(|(idx, offset)| {
+                let trace = build_trace(
+                    &format!("ts{idx}-{offset}"),
+                    &[TestSpan::new("r", None, "gw/GET", offset * MINUTE, 1_000)],
+                );
+                TraceExt::new(trace, Path::new(""))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn empty_buckets_fill_the_gaps() {
+        let traces = traces(&[0, 1, 10]);
+        let bucketed = BucketedStats::new(&traces, &BasicStatsRec::default(), 300);
+        let counts: Vec<_> = bucketed
+            .buckets
+            .iter()
+            .map(|(_, sr)| sr.as_ref().map(|sr| sr.trace_id.len()))
+            .collect();
+        assert_eq!(counts, vec![Some(2), None, Some(1)]);
+
+        let stitched = bucketed.into_stitched();
+        let descriptions: Vec<_> = stitched
+            .sources
+            .0
+            .iter()
+            .map(|src| src.description.as_str())
+            .collect();
+        assert_eq!(
+            descriptions,
+            vec![
+                "bucket 2023-11-14T22:10",
+                "bucket 2023-11-14T22:15",
+                "bucket 2023-11-14T22:20"
+            ]
+        );
+    }
+
+    #[test]
+    fn outlier_does_not_allocate_all_buckets() {
+        // a trace a year later would need more than 100.000 buckets of 5 minutes
+        let traces = traces(&[0, 365 * 24 * 60]);
+        let bucketed = BucketedStats::new(&traces, &BasicStatsRec::default(), 300);
+        assert_eq!(bucketed.buckets.len(), 2);
+        assert!(bucketed.buckets.iter().all(|(_, sr)| sr.is_some()));
+    }
+}
@@ -24,7 +24,7 @@ impl AggregateData for AverageData {
     fn add(&mut self, count: u64, value: Option<f64>) {
         if let Some(value) = value {
             self.count += count;
-            self.cumulator = Some(self.cumulator.map_or(value, |v| v + value * count as f64));
+            self.cumulator = Some(self.cumulator.unwrap_or(0.0) + value * count as f64);
         }
     }
 
@@ -39,3 +39,25 @@ impl AggregateData for AverageData {
         self.count
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{AggregateData, AverageData};
+
+    #[test]
+    fn first_value_is_weighted_by_count() {
+        let mut avg = AverageData::new(3, Some(10.0));
+        assert_eq!(avg.get_value(), Some(10.0));
+        avg.add(1, Some(30.0));
+        assert_eq!(avg.get_count(), 4);
+        assert_eq!(avg.get_value(), Some(15.0));
+
+        // missing values are not counted
+        avg.add(4, None);
+        assert_eq!(avg.get_value(), Some(15.0));
+        let mut empty = AverageData::new(2, None);
+        assert_eq!(empty.get_value(), None);
+        empty.add(2, Some(5.0));
+        assert_eq!(empty.get_value(), Some(5.0));
+    }
+}
@@ -0,0 +1,90 @@
+//! Statistics over a series of counts, such as the number of calls per invocation (mean, percentiles, max).
+
+/// Wrapper to implement statistical functions on an array of counts.
+pub struct CountStats<'a>(pub &'a Vec<usize>);
+
+impl<'a> CountStats<'a> {
+    pub fn get_min(&self) -> usize {
+        *self.0.iter().min().unwrap_or(&0)
+    }
+
+    pub fn get_mean(&self) -> f64 {
+        if self.0.is_empty() {
+            0.0
+        } else {
+            self.0.iter().sum::<usize>() as f64 / self.0.len() as f64
+        }
+    }
+
+    pub fn get_mean_str(&self) -> String {
+        super::format_float(self.get_mean())
+    }
+
+    /// Computation of a P-percentile value, which is an existing value that equals or exceeds P% of the measured values.
+    pub fn get_p(&self, p: f64) -> Option<usize> {
+        if self.0.is_empty() {
+            None
+        } else {
+            let mut data = self.0.clone();
+            data.sort_unstable();
+            let idx = ((data.len() as f64 * p).ceil() as usize).clamp(1, data.len()) - 1;
+            Some(data[idx])
+        }
+    }
+
+    pub fn get_max(&self) -> usize {
+        *self.0.iter().max().unwrap_or(&0)
+    }
+
+    /// Pearson correlation between the counts and a second series of the same length (for example the duration of the parent).
+    /// Returns None if one of both series is constant (or too short).
+    pub fn get_correlation(&self, other: &[i64]) -> Option<f64> {
+        let n = self.0.len();
+        if n < 3 || n != other.len() {
+            return None;
+        }
+        let mean_x = self.get_mean();
+        let mean_y = other.iter().sum::<i64>() as f64 / n as f64;
+        let (sxy, sxx, syy) =
+            self.0
+                .iter()
+                .zip(other.iter())
+                .fold((0.0, 0.0, 0.0), |(sxy, sxx, syy), (x, y)| {
+                    let dx = *x as f64 - mean_x;
+                    let dy = *y as f64 - mean_y;
+                    (sxy + dx * dy, sxx + dx * dx, syy + dy * dy)
+                });
+        if sxx == 0.0 || syy == 0.0 {
+            None
+        } else {
+            Some(sxy / (sxx * syy).sqrt())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CountStats;
+
+    #[test]
+    fn basic_count_stats() {
+        let input = vec![1_usize, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let cs = CountStats(&input);
+        assert_eq!(cs.get_min(), 1);
+        assert_eq!(cs.get_max(), 10);
+        assert!((cs.get_mean() - 5.5).abs() < 1e-10);
+        assert_eq!(cs.get_p(0.95), Some(10));
+        assert_eq!(cs.get_p(0.5), Some(5));
+    }
+
+    #[test]
+    fn correlation_with_duration() {
+        let counts = vec![1_usize, 2, 3, 4];
+        let durations = vec![100_i64, 200, 300, 400];
+        let corr = CountStats(&counts).get_correlation(&durations).unwrap();
+        assert!((corr - 1.0).abs() < 1e-10);
+
+        let constant = vec![3_usize, 3, 3, 3];
+        assert!(CountStats(&constant).get_correlation(&durations).is_none());
+    }
+}
@@ -37,4 +37,16 @@ impl<T: Hash + Eq + PartialEq> Counted<T> {
             .or_insert(count);
         *cnt
     }
+
+    /// iterate over the items and their counts
+    pub fn iter(&self) -> impl Iterator<Item = (&T, &usize)> {
+        self.0.iter()
+    }
+
+    /// merge the counts of 'other' into this Counted list
+    pub fn merge(&mut self, other: Counted<T>) {
+        other.0.into_iter().for_each(|(item, count)| {
+            self.add_item_count(item, count);
+        })
+    }
 }
@@ -33,9 +33,36 @@ pub fn datetime_millis_str(dt: NaiveDateTime) -> String {
     dt.format("%Y-%m-%d %H:%M:%S.%.3f").to_string()
 }
 
+/// Parse an interval such as '30s', '5m' or '1h' to seconds. A value without a unit is interpreted as seconds.
+pub fn parse_interval_secs(interval: &str) -> Result<i64, String> {
+    let interval = interval.trim();
+    let (value, unit) = match interval.find(|c: char| !c.is_ascii_digit()) {
+        Some(pos) => interval.split_at(pos),
+        None => (interval, "s"),
+    };
+    let value: i64 = value
+        .parse()
+        .map_err(|_| format!("Invalid interval '{interval}', expected for example '5m'"))?;
+    let secs = match unit {
+        "s" => value,
+        "m" => value * 60,
+        "h" => value * 3600,
+        unit => {
+            return Err(format!(
+                "Unknown unit '{unit}' in interval '{interval}' (use s, m or h)"
+            ))
+        }
+    };
+    if secs > 0 {
+        Ok(secs)
+    } else {
+        Err(format!("Interval '{interval}' should be positive"))
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::{datetime_micros_str, micros_to_datetime};
+    use super::{datetime_micros_str, micros_to_datetime, parse_interval_secs};
 
     use chrono::{NaiveDate, NaiveDateTime};
 
@@ -65,4 +92,15 @@ mod test {
         //        assert_eq!(dt1, DateTime::<Utc>::from_utc(ndt1, Utc));
         assert_eq!(actual, dt1)
     }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval_secs("1m"), Ok(60));
+        assert_eq!(parse_interval_secs("5m"), Ok(300));
+        assert_eq!(parse_interval_secs("1h"), Ok(3600));
+        assert_eq!(parse_interval_secs("45"), Ok(45));
+        assert!(parse_interval_secs("5d").is_err());
+        assert!(parse_interval_secs("0m").is_err());
+        assert!(parse_interval_secs("m").is_err());
+    }
 }
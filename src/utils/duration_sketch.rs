@@ -0,0 +1,288 @@
+//! A compact, mergeable representation of a distribution of durations (DDSketch).
+//! Values are counted in logarithmic bins such that each percentile is returned with a bounded relative error,
+//! while the size of the sketch only depends on the range of the values and not on the number of values.
+//! The count, sum, min and max are tracked exactly.
+use serde::{Deserialize, Serialize};
+
+/// Default relative accuracy of the percentiles (1%)
+pub const DEFAULT_RELATIVE_ACCURACY: f64 = 0.01;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DurationSketch {
+    /// gamma = (1 + alpha) / (1 - alpha) where alpha is the relative accuracy
+    pub gamma: f64,
+    pub count: usize,
+    /// sum of the values in microseconds (used for exact averages)
+    pub sum: i64,
+    pub min: i64,
+    pub max: i64,
+    /// number of values <= 0, which can not be mapped to a logarithmic bin
+    pub zero_count: usize,
+    /// ordered list of (bin-index, count). A Vec is used instead of a map, as json and bson do not allow integer keys.
+    pub bins: Vec<(i32, usize)>,
+}
+
+impl Default for DurationSketch {
+    fn default() -> Self {
+        Self::new(DEFAULT_RELATIVE_ACCURACY)
+    }
+}
+
+impl DurationSketch {
+    pub fn new(relative_accuracy: f64) -> Self {
+        assert!(relative_accuracy > 0.0 && relative_accuracy < 1.0);
+        Self {
+            gamma: (1.0 + relative_accuracy) / (1.0 - relative_accuracy),
+            count: 0,
+            sum: 0,
+            min: i64::MAX,
+            max: i64::MIN,
+            zero_count: 0,
+            bins: Vec::new(),
+        }
+    }
+
+    /// build a sketch with the default accuracy from a series of values in microseconds
+    pub fn from_values(values: &[i64]) -> Self {
+        let mut sketch = Self::default();
+        values.iter().for_each(|v| sketch.add(*v));
+        sketch
+    }
+
+    fn bin_index(&self, value: i64) -> i32 {
+        ((value as f64).ln() / self.gamma.ln()).ceil() as i32
+    }
+
+    /// representative value of a bin, which is within the relative accuracy of all values in the bin
+    fn bin_value(&self, idx: i32) -> f64 {
+        2.0 * self.gamma.powi(idx) / (self.gamma + 1.0)
+    }
+
+    fn add_to_bin(&mut self, idx: i32, cnt: usize) {
+        match self.bins.binary_search_by_key(&idx, |(i, _)| *i) {
+            Ok(pos) => self.bins[pos].1 += cnt,
+            Err(pos) => self.bins.insert(pos, (idx, cnt)),
+        }
+    }
+
+    /// add a value in microseconds
+    pub fn add(&mut self, value: i64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        if value <= 0 {
+            self.zero_count += 1;
+        } else {
+            self.add_to_bin(self.bin_index(value), 1);
+        }
+    }
+
+    /// merge 'other' into this sketch. Both sketches should have the same accuracy.
+    pub fn merge(&mut self, other: &DurationSketch) {
+        assert!(
+            (self.gamma - other.gamma).abs() < 1e-12,
+            "Can not merge sketches with a different accuracy"
+        );
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.zero_count += other.zero_count;
+        other
+            .bins
+            .iter()
+            .for_each(|(idx, cnt)| self.add_to_bin(*idx, *cnt));
+    }
+
+    /// the (approximate) value at 0-based rank 'rank' in microseconds. The min and max are returned exactly.
+    fn value_at_rank(&self, rank: usize) -> f64 {
+        if rank == 0 {
+            return self.min as f64;
+        }
+        if rank + 1 >= self.count {
+            return self.max as f64;
+        }
+        if rank < self.zero_count {
+            return 0.0;
+        }
+        let mut seen = self.zero_count;
+        for (idx, cnt) in self.bins.iter() {
+            seen += cnt;
+            if seen > rank {
+                return self.bin_value(*idx).clamp(self.min as f64, self.max as f64);
+            }
+        }
+        self.max as f64
+    }
+
+    /// (approximate) number of values that are at most 'value' microseconds. Values in the bin that contains 'value' are all counted.
+    pub fn count_at_most(&self, value: i64) -> usize {
+        if value < 0 {
+            return 0;
+        }
+        if value >= self.max {
+            return self.count;
+        }
+        let max_idx = if value == 0 {
+            i32::MIN
+        } else {
+            self.bin_index(value)
+        };
+        self.zero_count
+            + self
+                .bins
+                .iter()
+                .take_while(|(idx, _)| *idx <= max_idx)
+                .map(|(_, cnt)| cnt)
+                .sum::<usize>()
+    }
+
+    /// minimum in milliseconds (0.0 for an empty sketch)
+    pub fn get_min_millis(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.min as f64 / 1000.0
+        }
+    }
+
+    /// maximum in milliseconds (0.0 for an empty sketch)
+    pub fn get_max_millis(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.max as f64 / 1000.0
+        }
+    }
+
+    /// (approximate) population variance in milliseconds², based on the representative values of the bins
+    pub fn get_variance_millis(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let avg = self.get_avg_millis();
+        let sum_sq = self.zero_count as f64 * avg.powi(2)
+            + self
+                .bins
+                .iter()
+                .map(|(idx, cnt)| *cnt as f64 * (self.bin_value(*idx) / 1000.0 - avg).powi(2))
+                .sum::<f64>();
+        sum_sq / self.count as f64
+    }
+
+    /// average in milliseconds (0.0 for an empty sketch)
+    pub fn get_avg_millis(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        self.sum as f64 / (1000.0 * self.count as f64)
+    }
+
+    /// Computation of a P-percentile value (same conventions as TimeStats::get_p_millis)
+    pub fn get_p_millis(&self, p: f64) -> Option<f64> {
+        let idx = (self.count as f64 * p).ceil() as usize;
+        if idx == 0 || idx >= self.count {
+            None
+        } else {
+            Some(self.value_at_rank(idx - 1) / 1000.0)
+        }
+    }
+
+    /// Median in milliseconds (same conventions as TimeStats::get_median_millis)
+    pub fn get_median_millis(&self) -> Option<f64> {
+        let len = self.count;
+        if len < 3 {
+            None
+        } else if len % 2 == 1 {
+            Some(self.value_at_rank(len / 2) / 1000.0)
+        } else {
+            Some((self.value_at_rank(len / 2 - 1) + self.value_at_rank(len / 2)) / 2000.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DurationSketch, DEFAULT_RELATIVE_ACCURACY};
+    use crate::utils::TimeStats;
+
+    fn within_accuracy(val: f64, expect: f64) -> bool {
+        (val - expect).abs() <= DEFAULT_RELATIVE_ACCURACY * expect + 1e-9
+    }
+
+    #[test]
+    fn percentiles_within_relative_error() {
+        let values: Vec<i64> = (1..=10_000).map(|i| i * i).collect();
+        let sketch = DurationSketch::from_values(&values);
+        let ts = TimeStats(&values);
+        assert_eq!(sketch.get_min_millis(), ts.get_min_millis());
+        assert_eq!(sketch.get_max_millis(), ts.get_max_millis());
+        assert_eq!(sketch.get_avg_millis(), ts.get_avg_millis());
+        for p in [0.5, 0.75, 0.9, 0.95, 0.99] {
+            let expect = ts.get_p_millis(p).unwrap();
+            let val = sketch.get_p_millis(p).unwrap();
+            assert!(within_accuracy(val, expect), "p{p}: {val} versus {expect}");
+        }
+        assert!(within_accuracy(
+            sketch.get_median_millis().unwrap(),
+            ts.get_median_millis().unwrap()
+        ));
+        // far fewer bins than values
+        assert!(sketch.bins.len() < 1000);
+    }
+
+    #[test]
+    fn merge_equals_combined() {
+        let a: Vec<i64> = (0..500).map(|i| 1000 + i * 7).collect();
+        let b: Vec<i64> = (0..700).map(|i| 20_000 + i * 13).collect();
+        let mut merged = DurationSketch::from_values(&a);
+        merged.merge(&DurationSketch::from_values(&b));
+        let combined = DurationSketch::from_values(&[a, b].concat());
+        assert_eq!(merged.count, combined.count);
+        assert_eq!(merged.sum, combined.sum);
+        assert_eq!(merged.bins, combined.bins);
+        assert_eq!(merged.get_p_millis(0.9), combined.get_p_millis(0.9));
+    }
+
+    #[test]
+    fn count_at_most_within_accuracy() {
+        let values: Vec<i64> = (1..=1000).map(|i| i * 1000).collect();
+        let sketch = DurationSketch::from_values(&values);
+        assert_eq!(sketch.count_at_most(-1), 0);
+        assert_eq!(sketch.count_at_most(1_000_000), 1000);
+        // the bin containing 500ms is about 2% (10 values) wide
+        let cnt = sketch.count_at_most(500_000);
+        assert!((500..=510).contains(&cnt), "count = {cnt}");
+    }
+
+    #[test]
+    fn small_samples_follow_time_stats() {
+        let values = vec![0_i64, 2000, 6000];
+        let sketch = DurationSketch::from_values(&values);
+        assert!(sketch.get_p_millis(0.9).is_none());
+        assert_eq!(sketch.get_median_millis().map(|m| m.round()), Some(2.0));
+        assert!(DurationSketch::from_values(&values[..2])
+            .get_median_millis()
+            .is_none());
+    }
+
+    #[test]
+    fn empty_sketch_and_variance() {
+        let empty = DurationSketch::default();
+        assert_eq!(empty.get_min_millis(), 0.0);
+        assert_eq!(empty.get_max_millis(), 0.0);
+        assert_eq!(empty.get_avg_millis(), 0.0);
+        assert_eq!(empty.get_variance_millis(), 0.0);
+
+        let values: Vec<i64> = (1..=1_000).map(|i| i * 1_000).collect();
+        let avg = TimeStats(&values).get_avg_millis();
+        let variance = values
+            .iter()
+            .map(|v| (*v as f64 / 1000.0 - avg).powi(2))
+            .sum::<f64>()
+            / values.len() as f64;
+        let sketch_variance = DurationSketch::from_values(&values).get_variance_millis();
+        assert!((sketch_variance - variance).abs() < 0.05 * variance);
+    }
+}
@@ -1,4 +1,12 @@
-use std::path::{Path, PathBuf};
+use std::{
+    error::Error,
+    fs::{self, File, OpenOptions, TryLockError},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, SystemTime},
+};
 
 /// Canonilize Path and change empty path to current folder
 pub fn canonicalize_path(folder: &Path) -> PathBuf {
@@ -12,3 +20,81 @@ pub fn canonicalize_path(folder: &Path) -> PathBuf {
         .canonicalize()
         .expect("Failed to make canonical path. Path probably does not exist!")
 }
+
+/// counter to give each temporary file of this process a unique name
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Write the data to a temporary file in the same folder and rename it to 'path', such that readers either see the old or the new contents.
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<(), Box<dyn Error>> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(format!(
+        ".tmp.{}.{}",
+        process::id(),
+        TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let tmp_path = PathBuf::from(tmp_name);
+    let result = fs::write(&tmp_path, data).and_then(|_| fs::rename(&tmp_path, path));
+    if result.is_err() {
+        _ = fs::remove_file(&tmp_path);
+    }
+    Ok(result?)
+}
+
+/// An exclusive lock on a resource, represented by an advisory lock (flock) on a lock-file.
+/// The operating system releases the lock when the holding process exits, so a crashed process does not leave a stale lock behind.
+/// The lock-file itself is not removed, as removing it would allow two processes to lock different files with the same path.
+pub struct FileLock {
+    file: File,
+}
+
+impl FileLock {
+    const RETRY_INTERVAL: Duration = Duration::from_millis(20);
+
+    /// Acquire the lock-file at 'path', waiting at most 'timeout' for another holder to release it.
+    pub fn acquire(path: &Path, timeout: Duration) -> Result<Self, Box<dyn Error>> {
+        let start = SystemTime::now();
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(Self { file }),
+                Err(TryLockError::WouldBlock) => {
+                    if start.elapsed().unwrap_or_default() > timeout {
+                        Err(format!(
+                            "Timeout while waiting for lock-file '{}'",
+                            path.display()
+                        ))?
+                    }
+                    thread::sleep(Self::RETRY_INTERVAL);
+                }
+                Err(TryLockError::Error(err)) => Err(err)?,
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    /// release the lock
+    fn drop(&mut self) {
+        _ = self.file.unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileLock;
+    use std::{fs, time::Duration};
+
+    #[test]
+    fn lock_excludes_second_holder() {
+        let path = std::env::temp_dir().join(format!("file_lock_test_{}.lock", std::process::id()));
+        let lock = FileLock::acquire(&path, Duration::ZERO).unwrap();
+        assert!(FileLock::acquire(&path, Duration::from_millis(50)).is_err());
+        drop(lock);
+        assert!(FileLock::acquire(&path, Duration::ZERO).is_ok());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,36 @@
+//! Operations on time-intervals, such as the length of the union of a set of (possibly overlapping) intervals.
+
+/// Compute the total length of the union of a set of intervals (start, end).
+/// Overlapping intervals are only counted once, so the result is the time during which at least one of the intervals is active.
+pub fn interval_union_length(intervals: &[(i64, i64)]) -> i64 {
+    let mut intervals = intervals.to_vec();
+    intervals.sort_unstable();
+    let (total, current) =
+        intervals
+            .into_iter()
+            .fold((0, None), |(total, current), (start, end)| match current {
+                None => (total, Some((start, end))),
+                Some((cs, ce)) if start <= ce => (total, Some((cs, ce.max(end)))),
+                Some((cs, ce)) => (total + ce - cs, Some((start, end))),
+            });
+    match current {
+        Some((cs, ce)) => total + ce - cs,
+        None => total,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::interval_union_length;
+
+    #[test]
+    fn union_of_intervals() {
+        assert_eq!(interval_union_length(&[]), 0);
+        // sequential intervals
+        assert_eq!(interval_union_length(&[(0, 10), (10, 20), (30, 35)]), 25);
+        // fully parallel intervals
+        assert_eq!(interval_union_length(&[(0, 10), (0, 10), (2, 8)]), 10);
+        // partially overlapping and unordered
+        assert_eq!(interval_union_length(&[(5, 15), (0, 10), (20, 25)]), 20);
+    }
+}
@@ -2,15 +2,21 @@
 //!
 mod aggregate_data;
 mod comma_float;
+mod count_stats;
 mod counted;
 mod csv_file;
 mod datetime;
+mod duration_sketch;
 mod file;
 mod fs;
 mod hash;
+mod intervals;
 mod rate;
 mod regression;
 mod report;
+mod significance;
+mod sql_normalize;
+mod time_buckets;
 mod time_stats;
 
 pub use self::{
@@ -18,22 +24,28 @@ pub use self::{
     comma_float::{
         floats_ref_to_string, floats_to_string, format_float, format_float_opt, set_comma_float,
     },
+    count_stats::CountStats,
     counted::Counted,
     csv_file::CsvFileBuffer,
     datetime::{
         datetime_micros_str, datetime_millis_str, datetime_to_micros, micros_to_datetime,
-        set_tz_offset_minutes,
+        parse_interval_secs, set_tz_offset_minutes,
     },
+    duration_sketch::DurationSketch,
     file::{
         clean_os_string, current_folder, extend_create_folder, extend_with_base_path,
         extend_with_base_path_opt, extract_base_path, is_rooted_path, read_lines,
         write_string_to_file,
     },
-    fs::canonicalize_path,
+    fs::{canonicalize_path, write_atomic, FileLock},
     hash::{hash, string_hash},
+    intervals::interval_union_length,
     rate::{calc_rate, set_show_rate_output},
     regression::ExponentialRegression,
     regression::LinearRegression,
     report::{report, write_report, Chapter},
+    significance::{mann_whitney_u, two_proportion_p_value, two_sided_p_value, MannWhitneyU},
+    sql_normalize::{normalize_sql, sql_fingerprint},
+    time_buckets::TimeBuckets,
     time_stats::TimeStats,
 };
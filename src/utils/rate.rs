@@ -9,7 +9,7 @@ pub fn set_show_rate_output(val: bool) {
 }
 
 /// When having too few points the rates will become unreliable
-const POINTS_NEEDED_FOR_RATE: i32 = 10;
+pub(super) const POINTS_NEEDED_FOR_RATE: i32 = 10;
 
 /// returns an average and a median rate (after dropping the outliers)
 pub fn calc_rate(data: &[i64], num_outliers: i32) -> Option<(f64, f64)> {
@@ -0,0 +1,161 @@
+//! Non-parametric significance tests used to compare two samples, for example the latencies of a baseline and a canary release.
+//! Latency distributions are heavily skewed, so a rank-based test (Mann-Whitney U) is used instead of a t-test.
+
+/// The outcome of a Mann-Whitney U test comparing a candidate sample to a baseline sample.
+#[derive(Debug, Clone, Copy)]
+pub struct MannWhitneyU {
+    /// The U-statistic of the candidate sample
+    pub u: f64,
+    /// The normal approximation of the U-statistic (positive when the candidate tends to have the larger values)
+    pub z: f64,
+    /// two-sided p-value
+    pub p_value: f64,
+    /// Effect-size: the probability that a random value of the candidate exceeds a random value of the baseline (ties count for half).
+    /// A value of 0.5 means no difference.
+    pub prob_superiority: f64,
+}
+
+/// Complementary error-function (fractional error below 1.2e-7, Numerical Recipes 'erfcc')
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let r = t
+        * (-z * z - 1.26551223
+            + t * (1.00002368
+                + t * (0.37409196
+                    + t * (0.09678418
+                        + t * (-0.18628806
+                            + t * (0.27886807
+                                + t * (-1.13520398
+                                    + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277)))))))))
+            .exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
+    }
+}
+
+/// two-sided p-value for a standard normal z-score
+pub fn two_sided_p_value(z: f64) -> f64 {
+    erfc(z.abs() / std::f64::consts::SQRT_2).min(1.0)
+}
+
+/// Mann-Whitney U test (normal approximation with tie- and continuity-correction) of the candidate versus the baseline.
+/// Returns None when one of the samples is empty or when all values are equal.
+pub fn mann_whitney_u(baseline: &[f64], candidate: &[f64]) -> Option<MannWhitneyU> {
+    let (n_b, n_c) = (baseline.len() as f64, candidate.len() as f64);
+    if baseline.is_empty() || candidate.is_empty() {
+        return None;
+    }
+    let mut values: Vec<_> = baseline
+        .iter()
+        .map(|v| (*v, false))
+        .chain(candidate.iter().map(|v| (*v, true)))
+        .collect();
+    values.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // assign (average) ranks and collect the tie-correction term
+    let mut rank_sum_candidate = 0.0;
+    let mut tie_term = 0.0;
+    let mut i = 0;
+    while i < values.len() {
+        let mut j = i + 1;
+        while j < values.len() && values[j].0 == values[i].0 {
+            j += 1;
+        }
+        let avg_rank = (i + j + 1) as f64 / 2.0; // ranks are 1-based
+        let num_candidate = values[i..j].iter().filter(|(_, cand)| *cand).count();
+        rank_sum_candidate += avg_rank * num_candidate as f64;
+        let t = (j - i) as f64;
+        tie_term += t * t * t - t;
+        i = j;
+    }
+
+    let n = n_b + n_c;
+    let u = rank_sum_candidate - n_c * (n_c + 1.0) / 2.0;
+    let mean = n_b * n_c / 2.0;
+    let sigma = (n_b * n_c / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)))).sqrt();
+    if sigma == 0.0 || sigma.is_nan() {
+        return None;
+    }
+    // continuity correction towards the mean (note that f64::signum(0.0) is 1.0)
+    let diff = u - mean;
+    let diff = diff.signum() * (diff.abs() - 0.5).max(0.0);
+    let z = diff / sigma;
+    Some(MannWhitneyU {
+        u,
+        z,
+        p_value: two_sided_p_value(z),
+        prob_superiority: u / (n_b * n_c),
+    })
+}
+
+/// two-sided p-value of a two-proportion z-test comparing the fraction of successes of a candidate to the baseline.
+/// Returns None when one of the samples is empty or when the pooled proportion is 0 or 1.
+pub fn two_proportion_p_value(
+    base_count: usize,
+    base_hits: usize,
+    cand_count: usize,
+    cand_hits: usize,
+) -> Option<f64> {
+    if base_count == 0 || cand_count == 0 {
+        return None;
+    }
+    let (p_b, p_c) = (
+        base_hits as f64 / base_count as f64,
+        cand_hits as f64 / cand_count as f64,
+    );
+    let pooled = (base_hits + cand_hits) as f64 / (base_count + cand_count) as f64;
+    let se = (pooled * (1.0 - pooled) * (1.0 / base_count as f64 + 1.0 / cand_count as f64)).sqrt();
+    if se > 0.0 {
+        Some(two_sided_p_value((p_c - p_b) / se))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mann_whitney_u, two_proportion_p_value, two_sided_p_value};
+
+    #[test]
+    fn normal_p_values() {
+        assert!((two_sided_p_value(0.0) - 1.0).abs() < 1e-6);
+        assert!((two_sided_p_value(1.96) - 0.05).abs() < 1e-3);
+        assert!((two_sided_p_value(-2.576) - 0.01).abs() < 1e-3);
+    }
+
+    #[test]
+    fn mann_whitney_separated_samples() {
+        let baseline = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let candidate = [6.0, 7.0, 8.0, 9.0, 10.0];
+        let mw = mann_whitney_u(&baseline, &candidate).unwrap();
+        assert_eq!(mw.u, 25.0);
+        assert_eq!(mw.prob_superiority, 1.0);
+        assert!(mw.z > 0.0);
+        // z = (25 - 12.5 - 0.5) / sqrt(25 * 11 / 12) = 2.507
+        assert!((mw.p_value - 0.0122).abs() < 1e-3);
+
+        let mw = mann_whitney_u(&candidate, &baseline).unwrap();
+        assert_eq!(mw.prob_superiority, 0.0);
+        assert!(mw.z < 0.0);
+    }
+
+    #[test]
+    fn mann_whitney_identical_samples() {
+        let sample = [3.0, 1.0, 2.0, 2.0, 5.0];
+        let mw = mann_whitney_u(&sample, &sample).unwrap();
+        assert_eq!(mw.prob_superiority, 0.5);
+        assert!(mw.p_value > 0.99);
+        assert!(mann_whitney_u(&[1.0, 1.0], &[1.0]).is_none());
+        assert!(mann_whitney_u(&[], &[1.0]).is_none());
+    }
+
+    #[test]
+    fn two_proportions() {
+        assert!(two_proportion_p_value(1000, 10, 1000, 50).unwrap() < 0.001);
+        assert!(two_proportion_p_value(1000, 10, 1000, 11).unwrap() > 0.5);
+        assert!(two_proportion_p_value(100, 0, 100, 0).is_none());
+    }
+}
@@ -0,0 +1,58 @@
+//! Normalization of SQL-statements, such that statements that only differ in their literal values map to the same fingerprint.
+use super::string_hash;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// Normalize a SQL-statement by:
+///    1. replacing string-literals and numeric literals by '?'
+///    2. collapsing IN-lists to a single value 'IN (?)'
+///    3. squeezing all whitespace to a single space (and trimming the statement)
+pub fn normalize_sql(statement: &str) -> String {
+    lazy_static! {
+        static ref STRING_LITERAL: Regex = Regex::new(r"'(?:[^']|'')*'").unwrap();
+        static ref NUMERIC_LITERAL: Regex =
+            Regex::new(r"(?i)\b(?:0x[0-9a-f]+|\d+(?:\.\d+)?(?:e[+-]?\d+)?)\b").unwrap();
+        static ref IN_LIST: Regex = Regex::new(r"(?i)\bIN\s*\(\s*\?(?:\s*,\s*\?)*\s*\)").unwrap();
+        static ref WHITESPACE: Regex = Regex::new(r"\s+").unwrap();
+    }
+    let s = STRING_LITERAL.replace_all(statement, "?");
+    let s = NUMERIC_LITERAL.replace_all(&s, "?");
+    let s = WHITESPACE.replace_all(&s, " ");
+    let s = IN_LIST.replace_all(&s, "IN (?)");
+    s.trim().to_owned()
+}
+
+/// Compute the fingerprint of a SQL-statement, which is the hash of the normalized statement.
+pub fn sql_fingerprint(statement: &str) -> String {
+    string_hash(&normalize_sql(statement))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_sql, sql_fingerprint};
+
+    #[test]
+    fn strip_literals() {
+        assert_eq!(
+            normalize_sql("SELECT * FROM orders WHERE id = 42 AND name = 'O''Brien'"),
+            "SELECT * FROM orders WHERE id = ? AND name = ?"
+        );
+        // identifiers containing digits are not replaced
+        assert_eq!(
+            normalize_sql("select col1 from table2 where x > 3.5e10"),
+            "select col1 from table2 where x > ?"
+        );
+    }
+
+    #[test]
+    fn collapse_in_lists_and_whitespace() {
+        assert_eq!(
+            normalize_sql("SELECT *\n  FROM items\tWHERE id IN (1, 2,3 ,  4)"),
+            "SELECT * FROM items WHERE id IN (?)"
+        );
+        assert_eq!(
+            sql_fingerprint("SELECT * FROM items WHERE id in ('a', 'b')"),
+            sql_fingerprint("SELECT  * FROM items WHERE id in ('c')")
+        );
+    }
+}
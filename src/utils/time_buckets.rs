@@ -0,0 +1,125 @@
+//! Compact representation of a series of (start) timestamps as counts per fixed time-bucket.
+//! The rate is derived from the number of events and the time covered by the buckets, where the largest gaps
+//! between buckets are skipped as these are expected to be the gaps between the input-files (see calc_rate).
+use super::rate::POINTS_NEEDED_FOR_RATE;
+use serde::{Deserialize, Serialize};
+
+/// Default width of a time-bucket (10 seconds)
+pub const DEFAULT_BUCKET_MICROS: i64 = 10_000_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TimeBuckets {
+    pub bucket_micros: i64,
+    /// ordered list of (bucket-index, count), where the bucket-index is the timestamp in micros divided by bucket_micros.
+    pub buckets: Vec<(i64, usize)>,
+}
+
+impl Default for TimeBuckets {
+    fn default() -> Self {
+        Self::new(DEFAULT_BUCKET_MICROS)
+    }
+}
+
+impl TimeBuckets {
+    pub fn new(bucket_micros: i64) -> Self {
+        assert!(bucket_micros > 0);
+        Self {
+            bucket_micros,
+            buckets: Vec::new(),
+        }
+    }
+
+    /// build the buckets with the default bucket-width from a series of timestamps in microseconds
+    pub fn from_values(values: &[i64]) -> Self {
+        let mut tb = Self::default();
+        values.iter().for_each(|v| tb.add(*v));
+        tb
+    }
+
+    fn add_to_bucket(&mut self, idx: i64, cnt: usize) {
+        match self.buckets.binary_search_by_key(&idx, |(i, _)| *i) {
+            Ok(pos) => self.buckets[pos].1 += cnt,
+            Err(pos) => self.buckets.insert(pos, (idx, cnt)),
+        }
+    }
+
+    /// add a timestamp in microseconds
+    pub fn add(&mut self, micros: i64) {
+        self.add_to_bucket(micros.div_euclid(self.bucket_micros), 1);
+    }
+
+    /// merge 'other' into these buckets. Both should have the same bucket-width.
+    pub fn merge(&mut self, other: &TimeBuckets) {
+        assert_eq!(
+            self.bucket_micros, other.bucket_micros,
+            "Can not merge time-buckets with a different width"
+        );
+        other
+            .buckets
+            .iter()
+            .for_each(|(idx, cnt)| self.add_to_bucket(*idx, *cnt));
+    }
+
+    pub fn count(&self) -> usize {
+        self.buckets.iter().map(|(_, cnt)| cnt).sum()
+    }
+
+    /// start of the first non-empty bucket in microseconds
+    pub fn first_micros(&self) -> Option<i64> {
+        self.buckets
+            .first()
+            .map(|(idx, _)| idx * self.bucket_micros)
+    }
+
+    /// start of the last non-empty bucket in microseconds
+    pub fn last_micros(&self) -> Option<i64> {
+        self.buckets.last().map(|(idx, _)| idx * self.bucket_micros)
+    }
+
+    /// Average rate (per second) after skipping the 'num_outliers' largest gaps between the buckets.
+    /// Returns None when there are too few events (same threshold as calc_rate)
+    pub fn get_avg_rate(&self, num_outliers: i32) -> Option<f64> {
+        assert!(num_outliers >= 0);
+        let count = self.count();
+        if count as i32 - num_outliers - 2 - POINTS_NEEDED_FOR_RATE < 0 {
+            return None;
+        }
+        let mut gaps: Vec<_> = self.buckets.windows(2).map(|w| w[1].0 - w[0].0).collect();
+        gaps.sort_unstable();
+        let num_skipped = (num_outliers as usize).min(gaps.len());
+        gaps.truncate(gaps.len() - num_skipped);
+        // each continuous segment of buckets also covers its last bucket
+        let num_buckets = gaps.iter().sum::<i64>() + num_skipped as i64 + 1;
+        Some(count as f64 / (num_buckets * self.bucket_micros) as f64 * 1e6)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TimeBuckets;
+
+    #[test]
+    fn rate_skips_file_gaps() {
+        // two files of 100 seconds each with 2 events per second, separated by an hour
+        let values: Vec<i64> = (0..200)
+            .map(|i| i * 500_000)
+            .chain((0..200).map(|i| 3_600_000_000 + i * 500_000))
+            .collect();
+        let tb = TimeBuckets::from_values(&values);
+        assert_eq!(tb.count(), 400);
+        let rate = tb.get_avg_rate(1).unwrap();
+        assert!((rate - 2.0).abs() < 1e-9, "rate = {rate}");
+        // without skipping the gap the rate collapses
+        assert!(tb.get_avg_rate(0).unwrap() < 0.2);
+        assert!(TimeBuckets::from_values(&values[..5])
+            .get_avg_rate(1)
+            .is_none());
+    }
+
+    #[test]
+    fn merge_buckets() {
+        let mut a = TimeBuckets::from_values(&[0, 1, 20_000_000]);
+        a.merge(&TimeBuckets::from_values(&[2, 40_000_000]));
+        assert_eq!(a.buckets, vec![(0, 3), (2, 1), (4, 1)]);
+    }
+}
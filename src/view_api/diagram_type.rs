@@ -0,0 +1,10 @@
+use clap::ValueEnum;
+
+/// The type of Mermaid diagram to generate
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DiagramType {
+    /// a 'graph LR' flowchart of the services and operations around a Service/Operation
+    Flowchart,
+    /// a 'sequenceDiagram' of the most common call-chains of an endpoint, or of a single trace
+    Sequence,
+}
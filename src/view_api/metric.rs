@@ -27,6 +27,13 @@ pub enum Metric {
     P90Millis,
     P95Millis,
     P99Millis,
+    SloCompliance,
+    Apdex,
+    ErrorBudgetConsumed,
+    BurnRate,
+    CacheHitRatio,
+    DownstreamCalls,
+    FlushedDownstreamCalls,
 }
 
 impl Metric {
@@ -40,7 +47,7 @@ impl Metric {
 }
 
 /// The Metric_labels should all be set in Lower-case
-const METRIC_LABELS: [&str; 22] = [
+const METRIC_LABELS: [&str; 29] = [
     "NONE",
     "num_files",
     "occurance percentage",
@@ -63,6 +70,13 @@ const METRIC_LABELS: [&str; 22] = [
     "p90 millis",
     "p95 millis",
     "p99 millis",
+    "slo compliance",
+    "apdex",
+    "error budget consumed",
+    "burn rate",
+    "cache hit ratio",
+    "downstream calls",
+    "flushed downstream calls",
 ];
 
 impl ToString for Metric {
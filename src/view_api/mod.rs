@@ -1,3 +1,4 @@
+mod diagram_type;
 mod file;
 mod mermaid_scope;
 mod metric;
@@ -8,6 +9,7 @@ mod version;
 mod view_error;
 mod viewer;
 
+pub use diagram_type::DiagramType;
 pub use file::load_viewer;
 pub use mermaid_scope::MermaidScope;
 pub use metric::Metric;
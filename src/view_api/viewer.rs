@@ -1,4 +1,5 @@
 use super::{
+    super::mermaid::{BlastRadius, ServicePaths},
     types::{ChartDataParameters, ProcessList, Selection, Table},
     MermaidScope, Metric, TraceScope, ViewError,
 };
@@ -17,6 +18,14 @@ pub trait Viewer {
     /// Get the list of processes that exist in the current dataset.
     fn get_process_list(&self, metric: Metric) -> ProcessList;
 
+    #[allow(unused_variables)]
+    /// Get the breakdown of a Service-Operation per instance and per dimension-value (keys 'service/oper@instance' and
+    /// 'service/oper@dimension=value'). These keys can not be used as Service-Operation in the other methods.
+    fn get_breakdown_list(&self, service_oper: &str, metric: Metric) -> ProcessList {
+        // default implementation has no breakdown, as it does not contain instance- or dimension-statistics.
+        Vec::new()
+    }
+
     /// Get the list of call-chains for a given Service-Operation
     fn get_call_chain_list(
         &self,
@@ -72,4 +81,30 @@ pub trait Viewer {
         scope: MermaidScope,
         compact: bool,
     ) -> String;
+
+    #[allow(unused_variables)]
+    /// get a mermaid sequence diagram of the 'max_chains' most common call-chains of the endpoint 'service_oper' with their average latencies.
+    fn get_sequence_diagram(&self, service_oper: &str, max_chains: usize) -> String {
+        // default implementation has no sequence diagram, as it does not contain call-chains.
+        String::new()
+    }
+
+    /// get all endpoints whose call-chains pass through 'service_oper', weighted by their share of the traffic.
+    fn get_blast_radius(&self, service_oper: &str) -> BlastRadius {
+        // default implementation has no affected endpoints, as it does not contain call-chains.
+        BlastRadius {
+            service_oper: service_oper.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// get all distinct paths from service/operation 'from' to service/operation 'to' with their counts and latencies.
+    fn get_paths_between(&self, from: &str, to: &str) -> ServicePaths {
+        // default implementation has no paths, as it does not contain call-chains.
+        ServicePaths {
+            from: from.to_owned(),
+            to: to.to_owned(),
+            ..Default::default()
+        }
+    }
 }